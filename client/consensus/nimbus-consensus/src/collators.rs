@@ -58,6 +58,7 @@ use std::time::Duration;
 pub(crate) async fn collate<ADP, Block, BI, CS, Proposer>(
 	additional_digests_provider: &ADP,
 	author_id: NimbusId,
+	pre_digests: Vec<DigestItem>,
 	block_import: &mut BI,
 	collator_service: &CS,
 	keystore: &dyn Keystore,
@@ -75,6 +76,7 @@ where
 	Proposer: ProposerInterface<Block> + Send + Sync + 'static,
 {
 	let mut logs = vec![CompatibleDigestItem::nimbus_pre_digest(author_id.clone())];
+	logs.extend(pre_digests);
 	logs.extend(
 		additional_digests_provider.provide_digests(author_id.clone(), parent_header.hash()),
 	);
@@ -27,7 +27,7 @@ use futures::prelude::*;
 use nimbus_primitives::{DigestsProvider, NimbusApi, NimbusId};
 use polkadot_node_primitives::CollationResult;
 use polkadot_primitives::CollatorPair;
-use sc_client_api::{BlockBackend, BlockOf};
+use sc_client_api::{BlockBackend, BlockOf, BlockchainEvents};
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::HeaderBackend;
 use sp_core::Decode;
@@ -37,9 +37,11 @@ use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
 use std::{sync::Arc, time::Duration};
 
 /// Parameters for [`run`].
-pub struct Params<Proposer, BI, ParaClient, RClient, CIDP, CS, ADP = ()> {
+pub struct Params<Proposer, BI, ParaClient, RClient, CIDP, CS, ADP = (), SC = ()> {
 	/// Additional digest provider
 	pub additional_digests_provider: ADP,
+	/// Claims the slots of filters whose eligibility the runtime cannot predict
+	pub slot_claimer: SC,
 	/// Parachain id
 	pub para_id: ParaId,
 	/// A handle to the relay-chain client's "Overseer" or task orchestrator.
@@ -66,8 +68,8 @@ pub struct Params<Proposer, BI, ParaClient, RClient, CIDP, CS, ADP = ()> {
 }
 
 /// Run bare Nimbus consensus as a relay-chain-driven collator.
-pub fn run<Block, BI, CIDP, Backend, Client, RClient, Proposer, CS, ADP>(
-	params: Params<Proposer, BI, Client, RClient, CIDP, CS, ADP>,
+pub fn run<Block, BI, CIDP, Backend, Client, RClient, Proposer, CS, ADP, SC>(
+	params: Params<Proposer, BI, Client, RClient, CIDP, CS, ADP, SC>,
 ) -> impl Future<Output = ()> + Send + 'static
 where
 	Block: BlockT + Send,
@@ -78,6 +80,7 @@ where
 		+ BlockOf
 		+ HeaderBackend<Block>
 		+ BlockBackend<Block>
+		+ BlockchainEvents<Block>
 		+ Send
 		+ Sync
 		+ 'static,
@@ -86,6 +89,7 @@ where
 	Proposer: ProposerInterface<Block> + Send + Sync + 'static,
 	CS: CollatorServiceInterface<Block> + Send + Sync + 'static,
 	ADP: DigestsProvider<NimbusId, <Block as BlockT>::Hash> + Send + Sync + 'static,
+	SC: SlotClaimer<Block> + Send + Sync + 'static,
 {
 	async move {
 		let mut collation_requests = cumulus_client_collator::relay_chain_driven::init(
@@ -105,6 +109,7 @@ where
 			mut proposer,
 			para_client,
 			relay_client,
+			slot_claimer,
 			force_authoring,
 			..
 		} = params;
@@ -148,9 +153,10 @@ where
 				Ok(Some(h)) => h,
 			};
 
-			let nimbus_id = match claim_slot::<Block, Client>(
+			let (nimbus_id, pre_digests) = match claim_slot::<Block, Client, SC>(
 				&keystore,
 				&para_client,
+				&slot_claimer,
 				&parent_header,
 				&relay_parent_header,
 				force_authoring,
@@ -158,7 +164,7 @@ where
 			.await
 			{
 				Ok(None) => continue,
-				Ok(Some(nimbus_id)) => nimbus_id,
+				Ok(Some(claim)) => claim,
 				Err(e) => reject_with_error!(e),
 			};
 
@@ -179,6 +185,7 @@ where
 				super::collate::<ADP, Block, BI, CS, Proposer>(
 					&additional_digests_provider,
 					nimbus_id,
+					pre_digests,
 					&mut block_import,
 					&collator_service,
 					&*keystore,
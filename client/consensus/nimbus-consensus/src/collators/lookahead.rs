@@ -31,7 +31,7 @@ use polkadot_node_subsystem::messages::{
 	CollationGenerationMessage, RuntimeApiMessage, RuntimeApiRequest,
 };
 use polkadot_primitives::{CollatorPair, OccupiedCoreAssumption};
-use sc_client_api::{BlockBackend, BlockOf};
+use sc_client_api::{BlockBackend, BlockOf, BlockchainEvents};
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::HeaderBackend;
 use sp_consensus::SyncOracle;
@@ -43,7 +43,7 @@ use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
 use std::{sync::Arc, time::Duration};
 
 /// Parameters for [`run`].
-pub struct Params<BI, CIDP, Client, Backend, RClient, CHP, SO, Proposer, CS, DP = ()> {
+pub struct Params<BI, CIDP, Client, Backend, RClient, CHP, SO, Proposer, CS, DP = (), SC = ()> {
 	/// Additional digest provider
 	pub additional_digests_provider: DP,
 	/// The amount of time to spend authoring each block.
@@ -78,6 +78,8 @@ pub struct Params<BI, CIDP, Client, Backend, RClient, CHP, SO, Proposer, CS, DP
 	pub relay_chain_slot_duration: Duration,
 	/// A handle to the relay-chain client.
 	pub relay_client: RClient,
+	/// Claims the slots of filters whose eligibility the runtime cannot predict
+	pub slot_claimer: SC,
 	/// The length of slots in this parachain.
 	/// If the parachain doesn't have slot and rely only on relay slots, set it to None.
 	pub slot_duration: Option<SlotDuration>,
@@ -86,8 +88,8 @@ pub struct Params<BI, CIDP, Client, Backend, RClient, CHP, SO, Proposer, CS, DP
 }
 
 /// Run async-backing-friendly collator.
-pub fn run<Block, BI, CIDP, Client, Backend, RClient, CHP, SO, Proposer, CS, DP>(
-	mut params: Params<BI, CIDP, Client, Backend, RClient, CHP, SO, Proposer, CS, DP>,
+pub fn run<Block, BI, CIDP, Client, Backend, RClient, CHP, SO, Proposer, CS, DP, SC>(
+	mut params: Params<BI, CIDP, Client, Backend, RClient, CHP, SO, Proposer, CS, DP, SC>,
 ) -> impl Future<Output = ()> + Send + 'static
 where
 	Block: BlockT,
@@ -95,6 +97,7 @@ where
 		+ BlockOf
 		+ HeaderBackend<Block>
 		+ BlockBackend<Block>
+		+ BlockchainEvents<Block>
 		+ Send
 		+ Sync
 		+ 'static,
@@ -109,6 +112,7 @@ where
 	CS: CollatorServiceInterface<Block> + Send + Sync + 'static,
 	CHP: consensus_common::ValidationCodeHashProvider<Block::Hash> + Send + 'static,
 	DP: DigestsProvider<NimbusId, <Block as BlockT>::Hash> + Send + Sync + 'static,
+	SC: SlotClaimer<Block> + Send + Sync + 'static,
 {
 	// This is an arbitrary value which is likely guaranteed to exceed any reasonable
 	// limit, as it would correspond to 10 non-included blocks.
@@ -264,19 +268,20 @@ where
 				// (This will claim the slot internally)
 				let para_client = &*params.para_client;
 				let keystore = &params.keystore;
-				let author_id = match can_build_upon::<_, _>(
+				let (author_id, pre_digests) = match can_build_upon::<_, _, _>(
 					slot_now,
 					&parent_header,
 					&relay_parent_header,
 					included_block,
 					para_client,
+					&params.slot_claimer,
 					&keystore,
 					params.force_authoring,
 				)
 				.await
 				{
 					None => break,
-					Some(author_id) => author_id,
+					Some(claim) => claim,
 				};
 
 				tracing::debug!(
@@ -334,6 +339,7 @@ where
 				match super::collate(
 					&params.additional_digests_provider,
 					author_id,
+					pre_digests,
 					&mut params.block_import,
 					&params.collator_service,
 					keystore,
@@ -389,37 +395,40 @@ where
 
 // Checks if we own the slot at the given block and whether there
 // is space in the unincluded segment.
-async fn can_build_upon<Block, Client>(
+async fn can_build_upon<Block, Client, SC>(
 	slot: Slot,
 	parent: &Block::Header,
 	relay_parent: &PHeader,
 	included_block: Block::Hash,
 	client: &Client,
+	slot_claimer: &SC,
 	keystore: &KeystorePtr,
 	force_authoring: bool,
-) -> Option<NimbusId>
+) -> Option<(NimbusId, Vec<DigestItem>)>
 where
 	Block: BlockT,
-	Client: ProvideRuntimeApi<Block>,
+	Client: ProvideRuntimeApi<Block> + BlockchainEvents<Block>,
 	Client::Api: NimbusApi<Block> + UnincludedSegmentApi<Block>,
+	SC: SlotClaimer<Block>,
 {
 	let runtime_api = client.runtime_api();
-	match crate::claim_slot::<Block, Client>(
+	match crate::claim_slot::<Block, Client, SC>(
 		keystore,
 		client,
+		slot_claimer,
 		parent,
 		relay_parent,
 		force_authoring,
 	)
 	.await
 	{
-		Ok(Some(nimbus_id)) => {
+		Ok(Some(claim)) => {
 			// Here we lean on the property that building on an empty unincluded segment must always
 			// be legal. Skipping the runtime API query here allows us to seamlessly run this
 			// collator against chains which have not yet upgraded their runtime.
 			if parent.hash() != included_block {
 				match runtime_api.can_build_upon(parent.hash(), included_block, slot) {
-					Ok(true) => Some(nimbus_id),
+					Ok(true) => Some(claim),
 					Ok(false) => None,
 					Err(err) => {
 						tracing::error!(
@@ -434,7 +443,7 @@ where
 					}
 				}
 			} else {
-				Some(nimbus_id)
+				Some(claim)
 			}
 		}
 		Ok(None) => None,
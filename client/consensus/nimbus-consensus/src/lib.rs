@@ -24,9 +24,11 @@ pub mod collators;
 
 mod import_queue;
 mod manual_seal;
+mod slot_claimer;
 
 pub use import_queue::import_queue;
 pub use manual_seal::NimbusManualSealConsensusDataProvider;
+pub use slot_claimer::{SlotClaim, SlotClaimer, VrfSlotClaimer};
 
 use cumulus_primitives_core::{
 	relay_chain::{Hash as PHash, Header as PHeader},
@@ -37,13 +39,18 @@ use cumulus_relay_chain_interface::RelayChainInterface;
 use futures::prelude::*;
 use log::{info, warn};
 use nimbus_primitives::{NimbusApi, NimbusId, NIMBUS_KEY_ID};
+use sc_client_api::BlockchainEvents;
 use sc_consensus::BlockImport;
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_application_crypto::ByteArray;
 use sp_inherents::{CreateInherentDataProviders, InherentData, InherentDataProvider};
 use sp_keystore::{Keystore, KeystorePtr};
-use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+use sp_runtime::{
+	traits::{Block as BlockT, Header as HeaderT},
+	DigestItem,
+};
 use std::error::Error;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const LOG_TARGET: &str = "filtering-consensus";
 
@@ -63,18 +70,36 @@ where
 }*/
 
 /// Attempt to claim a slot derived from the given relay-parent header's slot.
-pub(crate) async fn claim_slot<Block, Client>(
+/// Returns the key to author with, and the pre-runtime digests proving its eligibility.
+pub(crate) async fn claim_slot<Block, Client, SC>(
 	keystore: &KeystorePtr,
 	para_client: &Client,
+	slot_claimer: &SC,
 	parent: &Block::Header,
 	relay_parent_header: &PHeader,
 	skip_prediction: bool,
-) -> Result<Option<NimbusId>, Box<dyn Error>>
+) -> Result<Option<(NimbusId, Vec<DigestItem>)>, Box<dyn Error>>
 where
 	Block: BlockT,
-	Client: ProvideRuntimeApi<Block>,
+	Client: ProvideRuntimeApi<Block> + BlockchainEvents<Block>,
 	Client::Api: NimbusApi<Block>,
+	SC: SlotClaimer<Block>,
 {
+	// Filters whose eligibility is proven by the author are claimed without the runtime prediction
+	let slot_number = *relay_parent_header.number();
+	match slot_claimer.claim_slot(&**keystore, parent, slot_number, skip_prediction) {
+		SlotClaim::Claimed(nimbus_id, pre_digests) => return Ok(Some((nimbus_id, pre_digests))),
+		SlotClaim::NotEligible => {
+			info!(
+				target: LOG_TARGET,
+				"🔮 Skipping candidate production because we are not eligible for slot {}",
+				slot_number
+			);
+			return Ok(None);
+		}
+		SlotClaim::Unsupported => (),
+	}
+
 	// Determine if runtime change
 	let runtime_upgraded = if *parent.number() > sp_runtime::traits::Zero::zero() {
 		use sp_api::Core as _;
@@ -95,18 +120,22 @@ where
 	let maybe_key = if skip_prediction || runtime_upgraded {
 		first_available_key(&*keystore)
 	} else {
-		first_eligible_key::<Block, Client>(
-			para_client.clone(),
-			&*keystore,
-			parent,
-			*relay_parent_header.number(),
-		)
+		first_eligible_key::<Block, Client>(para_client.clone(), &*keystore, parent, slot_number)
+	};
+
+	// If we are not eligible, we may still be a backup author of this slot.
+	let maybe_key = match maybe_key {
+		None if !(skip_prediction || runtime_upgraded) => {
+			first_backup_key::<Block, Client>(para_client, &**keystore, parent, slot_number).await
+		}
+		maybe_key => maybe_key,
 	};
 
 	if let Some(key) = maybe_key {
-		Ok(Some(
+		Ok(Some((
 			NimbusId::from_slice(&key).map_err(|_| "invalid nimbus id (wrong length)")?,
-		))
+			Vec::new(),
+		)))
 	} else {
 		Ok(None)
 	}
@@ -231,3 +260,81 @@ where
 
 	maybe_key
 }
+
+/// Grab the nimbus key from the keystore that becomes eligible the soonest as a backup author
+/// of this slot, and wait until it is eligible.
+/// If a block is imported on top of the same parent while waiting, presumably from the eligible
+/// author, the slot is not claimed, so that the backup author does not fork the chain.
+/// Runtimes that do not implement version 2 of the `NimbusApi` have no backup authors.
+pub(crate) async fn first_backup_key<Block, Client>(
+	client: &Client,
+	keystore: &dyn Keystore,
+	parent: &Block::Header,
+	slot_number: u32,
+) -> Option<Vec<u8>>
+where
+	Block: BlockT,
+	Client: ProvideRuntimeApi<Block> + BlockchainEvents<Block>,
+	Client::Api: NimbusApi<Block>,
+{
+	// Subscribe before looking for a key, so that no block imported in the meantime is missed.
+	let mut import_notifications = client.import_notification_stream().fuse();
+
+	// The runtime api is not held across the wait below.
+	let (key, eligible_from) = {
+		let runtime_api = client.runtime_api();
+		let api_version = runtime_api
+			.api_version::<dyn NimbusApi<Block>>(parent.hash())
+			.ok()
+			.flatten()
+			.unwrap_or_default();
+		if api_version < 2 {
+			return None;
+		}
+
+		Keystore::keys(keystore, NIMBUS_KEY_ID)
+			.ok()?
+			.into_iter()
+			.filter_map(|key| {
+				let nimbus_id = NimbusId::from_slice(&key).ok()?;
+				let eligible_from = runtime_api
+					.backup_eligible_from(parent.hash(), nimbus_id, slot_number, parent)
+					.ok()??;
+				Some((key, eligible_from))
+			})
+			.min_by_key(|(_, eligible_from)| *eligible_from)?
+	};
+
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.ok()?
+		.as_millis() as u64;
+	if eligible_from > now {
+		let mut delay =
+			futures_timer::Delay::new(Duration::from_millis(eligible_from - now)).fuse();
+		loop {
+			futures::select! {
+				_ = delay => break,
+				notification = import_notifications.next() => match notification {
+					Some(notification) if notification.header.parent_hash() == &parent.hash() => {
+						info!(
+							target: LOG_TARGET,
+							"🔮 Not authoring as a backup author for slot {}, a block was imported",
+							slot_number
+						);
+						return None;
+					}
+					Some(_) => (),
+					None => break,
+				},
+			}
+		}
+	}
+
+	info!(
+		target: LOG_TARGET,
+		"🔮 Authoring as a backup author for slot {}", slot_number
+	);
+
+	Some(key)
+}
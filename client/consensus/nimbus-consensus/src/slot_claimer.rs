@@ -0,0 +1,135 @@
+// Copyright Moonsong Labs
+// This file is part of Moonkit.
+
+// Moonkit is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Moonkit is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Slot claiming for filters whose eligibility cannot be predicted by the runtime.
+//!
+//! Filters such as `pallet-vrf-slot-filter` elect the authors of each slot privately: an author
+//! only knows it is eligible after evaluating a VRF with its own key, and proves it with a
+//! pre-runtime digest. `NimbusApi::can_author` cannot predict this from the parent header, so
+//! the collators ask a [`SlotClaimer`] first, and only fall back to the runtime prediction when
+//! the claimer does not apply.
+
+use nimbus_primitives::{NimbusId, NIMBUS_KEY_ID};
+use session_keys_primitives::{claim_slot_vrf, CompatibleDigestItem, SlotVrfApi};
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_application_crypto::ByteArray;
+use sp_keystore::Keystore;
+use sp_runtime::{
+	traits::{Block as BlockT, Header as HeaderT},
+	DigestItem,
+};
+use std::sync::Arc;
+
+/// The outcome of claiming a slot with a [`SlotClaimer`].
+pub enum SlotClaim {
+	/// The claimer does not apply to this runtime. Eligibility is predicted with the `NimbusApi`.
+	Unsupported,
+	/// None of the keys in the keystore is eligible in this slot.
+	NotEligible,
+	/// The key is eligible in this slot, and proves it with these pre-runtime digests.
+	Claimed(NimbusId, Vec<DigestItem>),
+}
+
+/// Claims slots on behalf of the keys in the keystore, for filters whose eligibility is proven
+/// by the author itself.
+pub trait SlotClaimer<Block: BlockT> {
+	/// Claim the slot on top of `parent`. When `force_authoring` is set, a key is claimed even if
+	/// it is not eligible, so that the block is still produced.
+	fn claim_slot(
+		&self,
+		keystore: &dyn Keystore,
+		parent: &Block::Header,
+		slot: u32,
+		force_authoring: bool,
+	) -> SlotClaim;
+}
+
+// No claimer, for runtimes whose filter is predicted with the `NimbusApi`
+impl<Block: BlockT> SlotClaimer<Block> for () {
+	fn claim_slot(&self, _: &dyn Keystore, _: &Block::Header, _: u32, _: bool) -> SlotClaim {
+		SlotClaim::Unsupported
+	}
+}
+
+/// Claims the slots of `pallet-vrf-slot-filter` with the `SlotVrfApi` of the runtime.
+///
+/// The key is eligible if the ticket of its slot VRF is below the threshold of the runtime, and
+/// the VRF output and proof are included in the `rand` pre-runtime digest. Runtimes that do not
+/// implement the `SlotVrfApi` are left to the `NimbusApi`.
+pub struct VrfSlotClaimer<Client> {
+	client: Arc<Client>,
+}
+
+impl<Client> VrfSlotClaimer<Client> {
+	pub fn new(client: Arc<Client>) -> Self {
+		Self { client }
+	}
+}
+
+impl<Block, Client> SlotClaimer<Block> for VrfSlotClaimer<Client>
+where
+	Block: BlockT,
+	Client: ProvideRuntimeApi<Block>,
+	Client::Api: SlotVrfApi<Block>,
+{
+	fn claim_slot(
+		&self,
+		keystore: &dyn Keystore,
+		parent: &Block::Header,
+		slot: u32,
+		force_authoring: bool,
+	) -> SlotClaim {
+		let runtime_api = self.client.runtime_api();
+		let parent_hash = parent.hash();
+		if !runtime_api
+			.has_api::<dyn SlotVrfApi<Block>>(parent_hash)
+			.unwrap_or_default()
+		{
+			return SlotClaim::Unsupported;
+		}
+
+		let (seed, threshold) = match (
+			runtime_api.slot_vrf_seed(parent_hash),
+			runtime_api.slot_vrf_threshold(parent_hash),
+		) {
+			(Ok(seed), Ok(threshold)) => (seed, threshold),
+			_ => return SlotClaim::NotEligible,
+		};
+		// A forced block still carries a proof, which the runtime accepts if the key is eligible.
+		let threshold = if force_authoring {
+			u128::MAX
+		} else {
+			threshold
+		};
+
+		Keystore::keys(keystore, NIMBUS_KEY_ID)
+			.unwrap_or_default()
+			.into_iter()
+			.find_map(|key| {
+				let nimbus_id = NimbusId::from_slice(&key).ok()?;
+				let vrf_id = runtime_api
+					.slot_vrf_key(parent_hash, nimbus_id.clone())
+					.ok()??;
+				let pre_digest = claim_slot_vrf(keystore, &vrf_id, seed, slot, threshold)?;
+
+				Some(SlotClaim::Claimed(
+					nimbus_id,
+					vec![DigestItem::vrf_pre_digest(pre_digest)],
+				))
+			})
+			.unwrap_or(SlotClaim::NotEligible)
+	}
+}
@@ -17,6 +17,24 @@
 //! A Nimbus filter for the AuRa consensus algorithm. This filter does not use any entropy, it
 //! simply rotates authors in order. A single author is eligible at each slot.
 //!
+//! The first `MaxAuthors` authors are taken from `PotentialAuthors` at the beginning of each
+//! session of `SessionLength` slots, so that changes to the author set do not reshuffle the rotation in the
+//! middle of a session. Each author is given `AuthorSlots` consecutive slots per round, which makes
+//! the rotation a weighted round robin. When there is no author, nobody is eligible.
+//!
+//! So that a single offline author does not lose the whole slot, the `BackupAuthors` authors that
+//! follow the eligible author in the rotation become eligible as well once `BackupDelay` of the
+//! slot has passed. The slot is expected to start `SlotDuration` after the timestamp of the parent
+//! block, since the slot numbers, such as relay chain block numbers, are not tied to a time. The
+//! delay is checked against the timestamp of the block, which must be set before the author is
+//! checked.
+//!
+//! The timestamp is chosen by the author of the block, and the relay chain validators do not check
+//! it. Only the collators importing the block compare it with their clock, within the drift that
+//! `pallet_timestamp` accepts. A backup author can therefore claim the slot early by setting the
+//! timestamp ahead, and a later backup time follows from it. Backup authors are trusted to set the
+//! timestamp honestly, so `BackupAuthors` should be zero where they are not.
+//!
 //! In the Substrate ecosystem, this algorithm is typically known as AuRa (authority round).
 //! There is a well known implementation in the main Substrate repository and published at
 //! https://crates.io/crates/sc-consensus-aura. There are two primary differences between
@@ -42,10 +60,32 @@
 use frame_support::pallet;
 pub use pallet::*;
 
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
+/// Gives each potential author a number of consecutive slots in each round of the rotation.
+pub trait AuthorSlots<AccountId> {
+	/// The number of slots of this author in each round. Authors with zero slots are skipped.
+	fn author_slots(author: &AccountId) -> u32;
+}
+
+/// Every author gets a single slot per round, as in plain Aura.
+impl<AccountId> AuthorSlots<AccountId> for () {
+	fn author_slots(_author: &AccountId) -> u32 {
+		1
+	}
+}
+
 #[pallet]
 pub mod pallet {
 
+	use super::*;
 	use frame_support::pallet_prelude::*;
+	use frame_support::traits::UnixTime;
+	use frame_system::pallet_prelude::*;
+	use sp_runtime::Percent;
 	use sp_std::vec::Vec;
 
 	//TODO Now that the CanAuthor trait takes a slot number, I don't think this even needs to be a pallet.
@@ -60,20 +100,189 @@ pub mod pallet {
 		/// A source for the complete set of potential authors.
 		/// The starting point of the filtering.
 		type PotentialAuthors: Get<Vec<Self::AccountId>>;
+		/// The maximum number of authors in a session. The potential authors beyond it are
+		/// ignored.
+		#[pallet::constant]
+		type MaxAuthors: Get<u32>;
+		/// The number of consecutive slots of each author in a round.
+		/// Use `()` to give a single slot to every author.
+		type AuthorSlots: AuthorSlots<Self::AccountId>;
+		/// The number of slots in a session. The authors are taken from `PotentialAuthors` once
+		/// per session.
+		#[pallet::constant]
+		type SessionLength: Get<u32>;
+		/// The time at which the current block is authored, typically `pallet_timestamp`.
+		/// It is trusted to gate the backup authors, although the author of the block sets it.
+		type Time: UnixTime;
+		/// The duration of a slot, in milliseconds. A slot is expected to start `SlotDuration`
+		/// after the parent block.
+		#[pallet::constant]
+		type SlotDuration: Get<u64>;
+		/// The number of authors, following the eligible author in the rotation, that can
+		/// author once `BackupDelay` of the slot has passed. Use zero to disable backup authors.
+		#[pallet::constant]
+		type BackupAuthors: Get<u32>;
+		/// The part of the slot after which the backup authors become eligible.
+		#[pallet::constant]
+		type BackupDelay: Get<Percent>;
+	}
+
+	/// The session of the current authors.
+	#[pallet::storage]
+	#[pallet::getter(fn current_session)]
+	pub type CurrentSession<T: Config> = StorageValue<_, u32, OptionQuery>;
+
+	/// The authors of the current session in their rotation order, each with the end of its range
+	/// of slots in a round.
+	#[pallet::storage]
+	#[pallet::getter(fn session_authors)]
+	pub type SessionAuthors<T: Config> =
+		StorageValue<_, BoundedVec<(T::AccountId, u32), T::MaxAuthors>, ValueQuery>;
+
+	/// The timestamp of the last block, in milliseconds. While a block is executed, this is the
+	/// timestamp of its parent.
+	#[pallet::storage]
+	#[pallet::getter(fn last_block_time)]
+	pub type LastBlockTime<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_: BlockNumberFor<T>) -> Weight {
+			// on_finalize: 1 write
+			T::DbWeight::get().writes(1)
+		}
+		fn on_finalize(_: BlockNumberFor<T>) {
+			LastBlockTime::<T>::put(T::Time::now().as_millis() as u64);
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The session of this slot.
+		pub fn session_of(slot: u32) -> u32 {
+			slot / T::SessionLength::get().max(1)
+		}
+
+		/// The authors of the session of this slot, with the end of their range of slots in a
+		/// round. Returns whether they were taken from `PotentialAuthors` rather than from the
+		/// snapshot of the current session. An empty snapshot is taken again, so that new authors
+		/// can author without waiting for the next session.
+		fn authors_at(slot: u32) -> (BoundedVec<(T::AccountId, u32), T::MaxAuthors>, bool) {
+			if CurrentSession::<T>::get() == Some(Self::session_of(slot)) {
+				let authors = SessionAuthors::<T>::get();
+				if !authors.is_empty() {
+					return (authors, false);
+				}
+			}
+
+			let mut round_length = 0u32;
+			let authors = T::PotentialAuthors::get()
+				.into_iter()
+				.filter_map(|author| match T::AuthorSlots::author_slots(&author) {
+					0 => None,
+					slots => Some((author, slots)),
+				})
+				.take(T::MaxAuthors::get() as usize)
+				.map(|(author, slots)| {
+					round_length = round_length.saturating_add(slots);
+					(author, round_length)
+				})
+				.collect::<Vec<_>>();
+			(BoundedVec::truncate_from(authors), true)
+		}
+
+		/// The index of the author eligible at this slot, if there is any author.
+		fn index_at(authors: &[(T::AccountId, u32)], slot: u32) -> Option<usize> {
+			// This is the core Aura logic right here.
+			let (_, round_length) = authors.last()?;
+			let position = slot % round_length;
+			Some(authors.partition_point(|(_, end)| *end <= position))
+		}
+
+		/// The author eligible at this slot, if there is any author.
+		fn author_at(authors: &[(T::AccountId, u32)], slot: u32) -> Option<&T::AccountId> {
+			authors
+				.get(Self::index_at(authors, slot)?)
+				.map(|(author, _)| author)
+		}
+
+		/// The backup authors of this slot: the authors that follow the eligible author in the
+		/// rotation, wrapping around at the end of the round.
+		fn backups_at(authors: &[(T::AccountId, u32)], slot: u32) -> Vec<&T::AccountId> {
+			let Some(index) = Self::index_at(authors, slot) else {
+				return Vec::new();
+			};
+			let count = (T::BackupAuthors::get() as usize).min(authors.len() - 1);
+
+			(1..=count)
+				.map(|offset| &authors[(index + offset) % authors.len()].0)
+				.collect()
+		}
+
+		/// The time, in milliseconds since the unix epoch, from which the backup authors of the
+		/// slot of the next block are eligible.
+		pub fn backup_time() -> u64 {
+			let slot_duration = T::SlotDuration::get();
+			LastBlockTime::<T>::get()
+				.saturating_add(slot_duration)
+				.saturating_add(T::BackupDelay::get().mul_floor(slot_duration))
+		}
+
+		/// The time, in milliseconds since the unix epoch, from which this account is eligible as
+		/// a backup author at this slot, or `None` if it is not a backup author of this slot.
+		/// This does not write the snapshot of the authors, so it can be used for prediction.
+		pub fn backup_eligible_from(account: &T::AccountId, slot: u32) -> Option<u64> {
+			let (authors, _) = Self::authors_at(slot);
+
+			Self::backups_at(&authors, slot)
+				.contains(&account)
+				.then(Self::backup_time)
+		}
 	}
 
 	// This code will be called by the author-inherent pallet to check whether the reported author
-	// of this block is eligible at this slot. We calculate that result on demand and do not
-	// record it instorage.
+	// of this block is eligible at this slot. We calculate that result on demand, and record the
+	// authors in storage at the first block of each session.
 	impl<T: Config> nimbus_primitives::CanAuthor<T::AccountId> for Pallet<T> {
 		#[cfg(not(feature = "try-runtime"))]
 		fn can_author(account: &T::AccountId, slot: &u32) -> bool {
-			let active: Vec<T::AccountId> = T::PotentialAuthors::get();
+			let (authors, new_session) = Self::authors_at(*slot);
+			let eligible = Self::author_at(&authors, *slot) == Some(account)
+				|| (Self::backups_at(&authors, *slot).contains(&account)
+					&& T::Time::now().as_millis() >= Self::backup_time() as u128);
 
-			// This is the core Aura logic right here.
-			let active_author = &active[*slot as usize % active.len()];
+			// This runs from the author inherent, so the weight of the snapshot is registered as
+			// mandatory.
+			if new_session {
+				CurrentSession::<T>::put(Self::session_of(*slot));
+				SessionAuthors::<T>::put(authors);
+				frame_system::Pallet::<T>::register_extra_weight_unchecked(
+					T::DbWeight::get().writes(2),
+					DispatchClass::Mandatory,
+				);
+			}
+
+			eligible
+		}
+	}
+
+	// This code will be called by the runtime to tell backup authors when they can author.
+	impl<T: Config> nimbus_primitives::BackupAuthors<T::AccountId> for Pallet<T> {
+		fn backup_eligible_from(account: &T::AccountId, slot: &u32) -> Option<u64> {
+			Self::backup_eligible_from(account, *slot)
+		}
+	}
+
+	// This code will be called by the runtime to predict which author will be eligible in
+	// upcoming slots. The backup authors are not included, since they only author when the
+	// eligible author does not.
+	impl<T: Config> nimbus_primitives::EligibleAuthors<T::AccountId> for Pallet<T> {
+		fn eligible_authors(slot: &u32) -> Vec<T::AccountId> {
+			let (authors, _) = Self::authors_at(*slot);
 
-			account == active_author
+			Self::author_at(&authors, *slot)
+				.cloned()
+				.into_iter()
+				.collect()
 		}
 	}
 }
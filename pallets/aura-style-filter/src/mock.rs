@@ -0,0 +1,108 @@
+// Copyright Moonsong Labs
+// This file is part of Moonkit.
+
+// Moonkit is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Moonkit is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate as pallet_testing;
+use frame_support::parameter_types;
+use frame_support::traits::{ConstU32, ConstU64, UnixTime};
+use sp_core::H256;
+use sp_runtime::{
+	traits::{BlakeTwo256, IdentityLookup},
+	BuildStorage, Percent,
+};
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime!(
+	pub enum Test
+	{
+		System: frame_system,
+		AuraStyleFilter: pallet_testing,
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub storage Authors: Vec<u64> = vec![1, 2, 3];
+	/// Authors that are not listed have a single slot
+	pub storage Slots: Vec<(u64, u32)> = Vec::new();
+	pub storage Backups: u32 = 1;
+	/// The timestamp of the current block, in milliseconds
+	pub storage Now: u64 = 0;
+	pub const BackupDelay: Percent = Percent::from_percent(50);
+}
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type Block = Block;
+	type Hash = H256;
+	type Nonce = u64;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+pub struct TestAuthorSlots;
+impl pallet_testing::AuthorSlots<u64> for TestAuthorSlots {
+	fn author_slots(author: &u64) -> u32 {
+		Slots::get()
+			.into_iter()
+			.find(|(account, _)| account == author)
+			.map(|(_, slots)| slots)
+			.unwrap_or(1)
+	}
+}
+
+pub struct MockTime;
+impl UnixTime for MockTime {
+	fn now() -> core::time::Duration {
+		core::time::Duration::from_millis(Now::get())
+	}
+}
+
+impl pallet_testing::Config for Test {
+	type PotentialAuthors = Authors;
+	type MaxAuthors = ConstU32<5>;
+	type AuthorSlots = TestAuthorSlots;
+	type SessionLength = ConstU32<10>;
+	type Time = MockTime;
+	type SlotDuration = ConstU64<6000>;
+	type BackupAuthors = Backups;
+	type BackupDelay = BackupDelay;
+}
+
+/// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	frame_system::GenesisConfig::<Test>::default()
+		.build_storage()
+		.unwrap()
+		.into()
+}
@@ -0,0 +1,230 @@
+// Copyright Moonsong Labs
+// This file is part of Moonkit.
+
+// Moonkit is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Moonkit is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::mock::*;
+
+use frame_support::traits::Hooks;
+use nimbus_primitives::{CanAuthor, EligibleAuthors};
+
+/// The author eligible at each of the slots, as reported by `can_author`
+fn rotation(slots: core::ops::Range<u32>) -> Vec<Option<u64>> {
+	slots
+		.map(|slot| (1..=10).find(|author| AuraStyleFilter::can_author(author, &slot)))
+		.collect()
+}
+
+#[test]
+fn authors_rotate_in_order() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			rotation(0..7),
+			vec![
+				Some(1),
+				Some(2),
+				Some(3),
+				Some(1),
+				Some(2),
+				Some(3),
+				Some(1)
+			]
+		);
+	});
+}
+
+#[test]
+fn nobody_is_eligible_without_authors() {
+	new_test_ext().execute_with(|| {
+		Authors::set(&vec![]);
+
+		assert!(!AuraStyleFilter::can_author(&1, &0));
+		assert!(AuraStyleFilter::eligible_authors(&0).is_empty());
+		assert!(AuraStyleFilter::session_authors().is_empty());
+	});
+}
+
+#[test]
+fn authors_are_kept_for_the_whole_session() {
+	new_test_ext().execute_with(|| {
+		assert!(AuraStyleFilter::can_author(&1, &0));
+		assert_eq!(AuraStyleFilter::current_session(), Some(0));
+
+		// Changes to the potential authors apply from the next session
+		Authors::set(&vec![4, 5]);
+		assert_eq!(rotation(7..10), vec![Some(2), Some(3), Some(1)]);
+		assert_eq!(AuraStyleFilter::eligible_authors(&9), vec![1]);
+		assert_eq!(AuraStyleFilter::eligible_authors(&10), vec![4]);
+
+		assert_eq!(rotation(10..13), vec![Some(4), Some(5), Some(4)]);
+		assert_eq!(AuraStyleFilter::current_session(), Some(1));
+	});
+}
+
+#[test]
+fn empty_session_is_refreshed_before_its_end() {
+	new_test_ext().execute_with(|| {
+		Authors::set(&vec![]);
+		assert!(!AuraStyleFilter::can_author(&1, &0));
+
+		Authors::set(&vec![7]);
+		assert!(AuraStyleFilter::can_author(&7, &1));
+		assert_eq!(AuraStyleFilter::session_authors(), vec![(7, 1)]);
+	});
+}
+
+#[test]
+fn authors_beyond_the_maximum_are_ignored() {
+	new_test_ext().execute_with(|| {
+		Authors::set(&vec![1, 2, 3, 4, 5, 6]);
+
+		assert_eq!(
+			rotation(0..6),
+			vec![Some(1), Some(2), Some(3), Some(4), Some(5), Some(1)]
+		);
+		assert_eq!(AuraStyleFilter::session_authors().len(), 5);
+	});
+}
+
+#[test]
+fn authors_get_consecutive_slots_according_to_their_weight() {
+	new_test_ext().execute_with(|| {
+		Slots::set(&vec![(1, 2), (2, 0), (3, 1)]);
+
+		assert_eq!(
+			rotation(0..7),
+			vec![
+				Some(1),
+				Some(1),
+				Some(3),
+				Some(1),
+				Some(1),
+				Some(3),
+				Some(1)
+			]
+		);
+	});
+}
+
+#[test]
+fn eligible_authors_agree_with_can_author() {
+	new_test_ext().execute_with(|| {
+		Slots::set(&vec![(1, 3), (3, 2)]);
+
+		for slot in 0..30 {
+			let eligible = AuraStyleFilter::eligible_authors(&slot);
+			assert_eq!(eligible.len(), 1);
+			assert!(AuraStyleFilter::can_author(&eligible[0], &slot));
+		}
+	});
+}
+
+#[test]
+fn backup_author_is_eligible_after_the_delay() {
+	new_test_ext().execute_with(|| {
+		// Slot 4 starts 6_000 after the parent block and author 2 is eligible, so author 3 is the
+		// backup
+		Now::set(&8_999);
+		assert!(AuraStyleFilter::can_author(&2, &4));
+		assert!(!AuraStyleFilter::can_author(&3, &4));
+
+		Now::set(&9_000);
+		assert!(AuraStyleFilter::can_author(&3, &4));
+		assert!(!AuraStyleFilter::can_author(&1, &4));
+		assert_eq!(AuraStyleFilter::eligible_authors(&4), vec![2]);
+	});
+}
+
+#[test]
+fn backup_delay_counts_from_the_parent_block() {
+	new_test_ext().execute_with(|| {
+		// The parent block was authored at a real time, and the slots are relay block numbers
+		Now::set(&1_700_000_000_000);
+		AuraStyleFilter::on_finalize(1);
+		assert_eq!(AuraStyleFilter::last_block_time(), 1_700_000_000_000);
+
+		// Author 1 is eligible at slot 18_000_000, so author 2 is the backup
+		let slot = 18_000_000;
+		assert_eq!(
+			AuraStyleFilter::backup_eligible_from(&2, slot),
+			Some(1_700_000_009_000)
+		);
+
+		Now::set(&1_700_000_008_999);
+		assert!(!AuraStyleFilter::can_author(&2, &slot));
+		Now::set(&1_700_000_009_000);
+		assert!(AuraStyleFilter::can_author(&2, &slot));
+		assert!(AuraStyleFilter::can_author(&1, &slot));
+	});
+}
+
+#[test]
+fn backup_delay_trusts_the_timestamp_of_the_block() {
+	new_test_ext().execute_with(|| {
+		Now::set(&1_000);
+		AuraStyleFilter::on_finalize(1);
+
+		// Only the timestamp set by the author of the block is checked, so a backup author
+		// setting it ahead can author as soon as it is built
+		Now::set(&10_000);
+		assert!(AuraStyleFilter::can_author(&2, &0));
+		AuraStyleFilter::on_finalize(2);
+
+		// And the delay of the next slot counts from that timestamp
+		assert_eq!(AuraStyleFilter::backup_eligible_from(&3, 1), Some(19_000));
+		assert!(!AuraStyleFilter::can_author(&3, &1));
+	});
+}
+
+#[test]
+fn backup_authors_follow_the_rotation() {
+	new_test_ext().execute_with(|| {
+		Authors::set(&vec![1, 2, 3, 4]);
+		Backups::set(&2);
+		Slots::set(&vec![(1, 2)]);
+
+		// Author 1 has slots 0 and 1, so the backups wrap around at the end of the round
+		assert_eq!(AuraStyleFilter::backup_eligible_from(&2, 0), Some(9_000));
+		assert_eq!(AuraStyleFilter::backup_eligible_from(&3, 1), Some(9_000));
+		assert_eq!(AuraStyleFilter::backup_eligible_from(&4, 1), None);
+		assert_eq!(AuraStyleFilter::backup_eligible_from(&1, 4), Some(9_000));
+		assert_eq!(AuraStyleFilter::backup_eligible_from(&2, 4), Some(9_000));
+		assert_eq!(AuraStyleFilter::backup_eligible_from(&4, 4), None);
+	});
+}
+
+#[test]
+fn eligible_author_is_never_its_own_backup() {
+	new_test_ext().execute_with(|| {
+		Authors::set(&vec![1, 2]);
+		Backups::set(&5);
+
+		assert_eq!(AuraStyleFilter::backup_eligible_from(&1, 0), None);
+		assert_eq!(AuraStyleFilter::backup_eligible_from(&2, 0), Some(9_000));
+
+		Authors::set(&vec![1]);
+		assert_eq!(AuraStyleFilter::backup_eligible_from(&1, 10), None);
+	});
+}
+
+#[test]
+fn no_backup_authors_when_disabled() {
+	new_test_ext().execute_with(|| {
+		Backups::set(&0);
+		Now::set(&u64::MAX);
+
+		assert_eq!(AuraStyleFilter::backup_eligible_from(&2, 0), None);
+		assert_eq!(rotation(0..3), vec![Some(1), Some(2), Some(3)]);
+	});
+}
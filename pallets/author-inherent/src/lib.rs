@@ -22,7 +22,8 @@
 
 use frame_support::traits::{FindAuthor, Get};
 use nimbus_primitives::{
-	AccountLookup, CanAuthor, NimbusId, SlotBeacon, INHERENT_IDENTIFIER, NIMBUS_ENGINE_ID,
+	AccountLookup, BackupAuthors, CanAuthor, EventHandler, NimbusId, SlotBeacon,
+	INHERENT_IDENTIFIER, NIMBUS_ENGINE_ID,
 };
 use parity_scale_codec::{Decode, Encode, FullCodec};
 use sp_inherents::{InherentIdentifier, IsFatalError};
@@ -74,6 +75,10 @@ pub mod pallet {
 		/// Some way of determining the current slot for purposes of verifying the author's eligibility
 		type SlotBeacon: SlotBeacon;
 
+		/// Notified of the author of each block, once its eligibility has been verified.
+		/// Use `()` if you do not need to track block authors.
+		type EventHandler: EventHandler<Self::AuthorId>;
+
 		type WeightInfo: WeightInfo;
 	}
 
@@ -140,11 +145,14 @@ pub mod pallet {
 			let new_slot = T::SlotBeacon::slot();
 
 			// Now check that the author is valid in this slot
+			let author = Self::get();
 			assert!(
-				T::CanAuthor::can_author(&Self::get(), &new_slot),
+				T::CanAuthor::can_author(&author, &new_slot),
 				"Block invalid, supplied author is not eligible."
 			);
 
+			T::EventHandler::note_author(author);
+
 			InherentIncluded::<T>::put(true);
 
 			Ok(Pays::No.into())
@@ -205,6 +213,19 @@ pub mod pallet {
 		}
 	}
 
+	impl<T: Config> Pallet<T> {
+		/// The time from which the given NimbusId is eligible as a backup author in this slot,
+		/// according to the filter `F`. It does the mapping to the author id, like `can_author`.
+		/// Runtimes use it to implement `NimbusApi::backup_eligible_from`.
+		pub fn backup_eligible_from<F: BackupAuthors<T::AuthorId>>(
+			author: &NimbusId,
+			slot: u32,
+		) -> Option<u64> {
+			let account = T::AccountLookup::lookup_account(author)?;
+			F::backup_eligible_from(&account, &slot)
+		}
+	}
+
 	/// To learn whether a given NimbusId can author, as opposed to an account id, you
 	/// can ask this pallet directly. It will do the mapping for you.
 	impl<T: Config> CanAuthor<NimbusId> for Pallet<T> {
@@ -38,6 +38,7 @@ frame_support::construct_runtime!(
 parameter_types! {
 	pub const BlockHashCount: u64 = 250;
 	pub Authors: Vec<u64> = vec![1, 2, 3, 4, 5];
+	pub storage NotedAuthors: Vec<u64> = Vec::new();
 	pub const TestDbWeight: RuntimeDbWeight = RuntimeDbWeight {
 		read: 1,
 		write: 10,
@@ -92,11 +93,22 @@ impl AccountLookup<u64> for MockAccountLookup {
 	}
 }
 
+/// Records every author it is notified of in NotedAuthors
+pub struct RecordingEventHandler;
+impl nimbus_primitives::EventHandler<u64> for RecordingEventHandler {
+	fn note_author(author: u64) {
+		let mut noted = NotedAuthors::get();
+		noted.push(author);
+		NotedAuthors::set(&noted);
+	}
+}
+
 impl pallet_testing::Config for Test {
 	type AuthorId = u64;
 	type AccountLookup = MockAccountLookup;
 	type CanAuthor = ();
 	type SlotBeacon = DummyBeacon;
+	type EventHandler = RecordingEventHandler;
 	type WeightInfo = ();
 }
 
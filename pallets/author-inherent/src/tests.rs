@@ -71,3 +71,52 @@ fn test_author_is_still_available_after_on_finalize() {
 		assert_eq!(Some(ALICE), <Author<Test>>::get());
 	});
 }
+
+#[test]
+fn test_author_is_noted_after_validation() {
+	new_test_ext().execute_with(|| {
+		let block_number = 1;
+		System::initialize(
+			&block_number,
+			&H256::default(),
+			&Digest {
+				logs: vec![DigestItem::PreRuntime(
+					NIMBUS_ENGINE_ID,
+					NimbusId::from_slice(&ALICE_NIMBUS).unwrap().encode(),
+				)],
+			},
+		);
+
+		AuthorInherent::on_initialize(block_number);
+		assert!(NotedAuthors::get().is_empty());
+
+		let _ = AuthorInherent::kick_off_authorship_validation(None.into());
+		assert_eq!(NotedAuthors::get(), vec![ALICE]);
+	});
+}
+
+#[test]
+fn backup_eligible_from_maps_the_nimbus_id_to_the_author() {
+	/// Alice is a backup author of every slot, from the slot number in milliseconds
+	struct AliceIsBackup;
+	impl nimbus_primitives::BackupAuthors<u64> for AliceIsBackup {
+		fn backup_eligible_from(author: &u64, slot: &u32) -> Option<u64> {
+			(*author == ALICE).then(|| *slot as u64)
+		}
+	}
+
+	new_test_ext().execute_with(|| {
+		let alice = NimbusId::from_slice(&ALICE_NIMBUS).unwrap();
+		let unmapped = NimbusId::from_slice(&[2; 32]).unwrap();
+
+		assert_eq!(
+			AuthorInherent::backup_eligible_from::<AliceIsBackup>(&alice, 7),
+			Some(7)
+		);
+		assert_eq!(
+			AuthorInherent::backup_eligible_from::<AliceIsBackup>(&unmapped, 7),
+			None
+		);
+		assert_eq!(AuthorInherent::backup_eligible_from::<()>(&alice, 7), None);
+	});
+}
@@ -17,51 +17,93 @@
 #![cfg(feature = "runtime-benchmarks")]
 
 //! Benchmarking
-use crate::{keys_wrapper, BalanceOf, Call, Config, Pallet};
+use crate::{
+	keys_size, keys_wrapper, ownership_payload, BalanceOf, Call, Config, HoldReason, KeysOwnership,
+	KeysProofOf, MappingWithDeposit, Pallet, RotationTime,
+};
 use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite};
 use frame_support::{
 	assert_ok,
-	traits::{Currency, Get},
+	traits::{
+		fungible::{Balanced, Inspect, MutateHold},
+		tokens::Precision,
+		Get, Hooks,
+	},
 };
-use frame_system::RawOrigin;
-use nimbus_primitives::NimbusId;
-use parity_scale_codec::Decode;
+use frame_system::{pallet_prelude::BlockNumberFor, RawOrigin};
+use nimbus_primitives::{EventHandler, NimbusId, NimbusSignature, NIMBUS_ENGINE_ID};
+use parity_scale_codec::Encode;
+use sp_runtime::{traits::Zero, DigestItem, RuntimeAppPublic};
+use sp_std::vec::Vec;
 
-/// Create a funded user.
+/// Create a user funded for one deposit.
 fn create_funded_user<T: Config>() -> T::AccountId {
-	let user = account("account id", 0u32, 0u32);
-	T::DepositCurrency::make_free_balance_be(
-		&user,
-		<<T as Config>::DepositAmount as Get<BalanceOf<T>>>::get(),
-	);
-	T::DepositCurrency::issue(<<T as Config>::DepositAmount as Get<BalanceOf<T>>>::get());
+	create_user_funded_for::<T>(0, 1)
+}
+
+/// Create a user funded for the given number of deposits, on top of the existential deposit.
+fn create_user_funded_for<T: Config>(index: u32, deposits: u32) -> T::AccountId {
+	let user = account("account id", index, 0u32);
+	let amount = <<T as Config>::DepositAmount as Get<BalanceOf<T>>>::get() * deposits.into()
+		+ T::DepositCurrency::minimum_balance();
+	assert_ok!(T::DepositCurrency::deposit(&user, amount, Precision::Exact));
 	user
 }
 
-/// Create a valid nimbus id from a simple u8 seed
-pub fn nimbus_id(seed: u8) -> NimbusId {
-	let id = [seed; 32];
-	NimbusId::decode(&mut &id[..]).expect("valid input")
+/// The payload of the next ownership proof of this account
+fn payload<T: Config>(account: &T::AccountId) -> Vec<u8> {
+	ownership_payload(
+		&frame_system::Pallet::<T>::block_hash(BlockNumberFor::<T>::zero()),
+		account,
+		Pallet::<T>::ownership_nonce(account),
+	)
+}
+
+/// Generate a nimbus id in the keystore, with its ownership proof for this account
+fn nimbus_id<T: Config>(account: &T::AccountId) -> (NimbusId, NimbusSignature) {
+	let id = NimbusId::generate_pair(None);
+	let proof = id
+		.sign(&payload::<T>(account))
+		.expect("the key was just generated");
+	(id, proof)
+}
+
+/// Generate keys, with their ownership proof for this account
+fn keys<T: Config>(account: &T::AccountId) -> (T::Keys, KeysProofOf<T>) {
+	T::KeysOwnership::benchmark_keys(&payload::<T>(account))
+}
+
+/// The `set_keys` input of these keys, which must have the maximum encoded size so that the
+/// decoding of the worst case input is measured
+fn keys_input<T: Config>(nimbus_id: NimbusId, keys: T::Keys) -> Vec<u8> {
+	let input = keys_wrapper::<T>(nimbus_id, keys);
+	assert_eq!(
+		input.len(),
+		keys_size::<T>(),
+		"benchmark keys must have the maximum size"
+	);
+	input
 }
 
 benchmarks! {
 	add_association {
 		let caller = create_funded_user::<T>();
-		let id = nimbus_id(1u8);
-	}: _(RawOrigin::Signed(caller.clone()), id.clone())
+		let (id, proof) = nimbus_id::<T>(&caller);
+	}: _(RawOrigin::Signed(caller.clone()), id.clone(), proof)
 	verify {
 		assert_eq!(Pallet::<T>::account_id_of(&id), Some(caller));
 	}
 
 	update_association {
 		let caller = create_funded_user::<T>();
-		let first_id = nimbus_id(1u8);
-		let second_id = nimbus_id(2u8);
+		let (first_id, first_proof) = nimbus_id::<T>(&caller);
 		assert_ok!(Pallet::<T>::add_association(
 			RawOrigin::Signed(caller.clone()).into(),
-			first_id.clone())
-		);
-	}: _(RawOrigin::Signed(caller.clone()), first_id.clone(), second_id.clone())
+			first_id.clone(),
+			first_proof,
+		));
+		let (second_id, second_proof) = nimbus_id::<T>(&caller);
+	}: _(RawOrigin::Signed(caller.clone()), first_id.clone(), second_id.clone(), second_proof)
 	verify {
 		assert_eq!(Pallet::<T>::account_id_of(&first_id), None);
 		assert_eq!(Pallet::<T>::account_id_of(&second_id), Some(caller));
@@ -69,40 +111,251 @@ benchmarks! {
 
 	clear_association {
 		let caller = create_funded_user::<T>();
-		let first_id = nimbus_id(1u8);
+		let (first_id, first_proof) = nimbus_id::<T>(&caller);
 		assert_ok!(Pallet::<T>::add_association(
 			RawOrigin::Signed(caller.clone()).into(),
-			first_id.clone())
-		);
+			first_id.clone(),
+			first_proof,
+		));
 	}: _(RawOrigin::Signed(caller.clone()), first_id.clone())
 	verify {
 		assert_eq!(Pallet::<T>::account_id_of(&first_id), None);
 	}
 
 	remove_keys {
-		let caller = create_funded_user::<T>();
-		let id = nimbus_id(1u8);
-		let keys: T::Keys = nimbus_id(3u8).into();
-		assert_ok!(Pallet::<T>::set_keys(
+		let x in 1..(<<T as Config>::MaxKeysPerAccount as Get<u32>>::get());
+		let caller = create_user_funded_for::<T>(0, x);
+		let mut first_id = None;
+		for _ in 0..x {
+			let (id, nimbus_proof) = nimbus_id::<T>(&caller);
+			let (keys, keys_proof) = keys::<T>(&caller);
+			assert_ok!(Pallet::<T>::add_keys(
 				RawOrigin::Signed(caller.clone()).into(),
-				keys_wrapper::<T>(id.clone(), keys.clone()),
-			)
-		);
+				keys_wrapper::<T>(id.clone(), keys),
+				nimbus_proof,
+				keys_proof,
+			));
+			first_id.get_or_insert(id);
+		}
 	}: _(RawOrigin::Signed(caller.clone()))
 	verify {
-		assert_eq!(Pallet::<T>::account_id_of(&id), None);
+		assert_eq!(Pallet::<T>::account_id_of(&first_id.expect("x is at least 1")), None);
 		assert_eq!(Pallet::<T>::nimbus_id_of(&caller), None);
 	}
 
 	set_keys {
 		let caller = create_funded_user::<T>();
-		let id = nimbus_id(2u8);
-		let keys: T::Keys = nimbus_id(3u8).into();
-	}: _(RawOrigin::Signed(caller.clone()), keys_wrapper::<T>(id.clone(), keys.clone())
-		) verify {
+		let (id, nimbus_proof) = nimbus_id::<T>(&caller);
+		let (keys, keys_proof) = keys::<T>(&caller);
+		let input = keys_input::<T>(id.clone(), keys.clone());
+	}: _(RawOrigin::Signed(caller.clone()), input, nimbus_proof, keys_proof)
+	verify {
 		assert_eq!(Pallet::<T>::account_id_of(&id), Some(caller));
 		assert_eq!(Pallet::<T>::keys_of(&id), Some(keys));
 	}
+
+	add_keys {
+		let caller = create_user_funded_for::<T>(0, 2);
+		let (first_id, first_proof) = nimbus_id::<T>(&caller);
+		assert_ok!(Pallet::<T>::add_association(
+			RawOrigin::Signed(caller.clone()).into(),
+			first_id.clone(),
+			first_proof,
+		));
+		let (id, nimbus_proof) = nimbus_id::<T>(&caller);
+		let (keys, keys_proof) = keys::<T>(&caller);
+		let input = keys_input::<T>(id.clone(), keys.clone());
+	}: _(RawOrigin::Signed(caller.clone()), input, nimbus_proof, keys_proof)
+	verify {
+		assert_eq!(Pallet::<T>::nimbus_ids_of(&caller), sp_std::vec![first_id, id.clone()]);
+		assert_eq!(Pallet::<T>::keys_of(&id), Some(keys));
+	}
+
+	schedule_keys_rotation {
+		let caller = create_funded_user::<T>();
+		let (first_id, first_proof) = nimbus_id::<T>(&caller);
+		assert_ok!(Pallet::<T>::add_association(
+			RawOrigin::Signed(caller.clone()).into(),
+			first_id.clone(),
+			first_proof,
+		));
+		let (second_id, nimbus_proof) = nimbus_id::<T>(&caller);
+		let (keys, keys_proof) = keys::<T>(&caller);
+		let input = keys_input::<T>(second_id, keys);
+		let at = RotationTime::Block(frame_system::Pallet::<T>::block_number() + 10u32.into());
+	}: _(
+		RawOrigin::Signed(caller.clone()),
+		first_id.clone(),
+		input,
+		at,
+		nimbus_proof,
+		keys_proof
+	) verify {
+		assert!(Pallet::<T>::scheduled_rotation(&first_id).is_some());
+		assert_eq!(Pallet::<T>::account_id_of(&first_id), Some(caller));
+	}
+
+	cancel_keys_rotation {
+		let caller = create_funded_user::<T>();
+		let (first_id, first_proof) = nimbus_id::<T>(&caller);
+		assert_ok!(Pallet::<T>::add_association(
+			RawOrigin::Signed(caller.clone()).into(),
+			first_id.clone(),
+			first_proof,
+		));
+		let (second_id, nimbus_proof) = nimbus_id::<T>(&caller);
+		let (keys, keys_proof) = keys::<T>(&caller);
+		let at = RotationTime::Block(frame_system::Pallet::<T>::block_number() + 10u32.into());
+		assert_ok!(Pallet::<T>::schedule_keys_rotation(
+			RawOrigin::Signed(caller.clone()).into(),
+			first_id.clone(),
+			keys_wrapper::<T>(second_id, keys),
+			at,
+			nimbus_proof,
+			keys_proof,
+		));
+	}: _(RawOrigin::Signed(caller.clone()), first_id.clone())
+	verify {
+		assert!(Pallet::<T>::scheduled_rotation(&first_id).is_none());
+	}
+
+	enact_scheduled_rotations {
+		let x in 0..(<<T as Config>::MaxScheduledRotations as Get<u32>>::get());
+		let at = frame_system::Pallet::<T>::block_number() + 10u32.into();
+		let mut rotations = Vec::new();
+		for i in 0..x {
+			let caller = create_user_funded_for::<T>(i, 1);
+			let (old_id, old_proof) = nimbus_id::<T>(&caller);
+			assert_ok!(Pallet::<T>::add_association(
+				RawOrigin::Signed(caller.clone()).into(),
+				old_id.clone(),
+				old_proof,
+			));
+			let (new_id, nimbus_proof) = nimbus_id::<T>(&caller);
+			let (keys, keys_proof) = keys::<T>(&caller);
+			assert_ok!(Pallet::<T>::schedule_keys_rotation(
+				RawOrigin::Signed(caller.clone()).into(),
+				old_id.clone(),
+				keys_wrapper::<T>(new_id.clone(), keys),
+				RotationTime::Block(at),
+				nimbus_proof,
+				keys_proof,
+			));
+			rotations.push((old_id, new_id));
+		}
+	}: {
+		Pallet::<T>::on_initialize(at);
+	}
+	verify {
+		for (old_id, new_id) in rotations {
+			assert_eq!(Pallet::<T>::account_id_of(&old_id), None);
+			assert!(Pallet::<T>::account_id_of(&new_id).is_some());
+		}
+	}
+
+	force_add_association {
+		let account_id: T::AccountId = account("account id", 0u32, 0u32);
+		let id = NimbusId::generate_pair(None);
+		let keys: T::Keys = id.clone().into();
+	}: _(RawOrigin::Root, id.clone(), account_id.clone(), keys)
+	verify {
+		assert_eq!(Pallet::<T>::account_id_of(&id), Some(account_id));
+	}
+
+	force_remove_association {
+		let caller = create_funded_user::<T>();
+		let (id, proof) = nimbus_id::<T>(&caller);
+		assert_ok!(Pallet::<T>::add_association(
+			RawOrigin::Signed(caller.clone()).into(),
+			id.clone(),
+			proof,
+		));
+	}: _(RawOrigin::Root, id.clone(), true)
+	verify {
+		assert_eq!(Pallet::<T>::account_id_of(&id), None);
+		assert!(Pallet::<T>::deposit_held(&caller).is_zero());
+	}
+
+	update_deposits {
+		let x in 1..100;
+		let deposit = <<T as Config>::DepositAmount as Get<BalanceOf<T>>>::get();
+		let mut ids = Vec::new();
+		for i in 0..x {
+			let user = create_user_funded_for::<T>(i, 1);
+			let (id, proof) = nimbus_id::<T>(&user);
+			assert_ok!(Pallet::<T>::add_association(
+				RawOrigin::Signed(user.clone()).into(),
+				id.clone(),
+				proof,
+			));
+			// Registered when the deposit was lower, so the update has to hold more
+			let old_deposit = deposit / 2u32.into();
+			assert_ok!(T::DepositCurrency::release(
+				&HoldReason::AuthorMappingDeposit.into(),
+				&user,
+				deposit - old_deposit,
+				Precision::Exact,
+			));
+			MappingWithDeposit::<T>::mutate(&id, |info| {
+				if let Some(info) = info {
+					info.deposit = old_deposit;
+				}
+			});
+			ids.push(id);
+		}
+		let caller: T::AccountId = account("caller", 0, 0u32);
+	}: _(RawOrigin::Signed(caller), x)
+	verify {
+		for id in ids {
+			assert_eq!(
+				Pallet::<T>::account_and_deposit_of(&id).map(|info| info.deposit),
+				Some(deposit)
+			);
+		}
+	}
+
+	remove_inactive {
+		let x in 1..(<<T as Config>::MaxKeysPerAccount as Get<u32>>::get());
+		let owner = create_user_funded_for::<T>(0, x);
+		let mut first_id = None;
+		for _ in 0..x {
+			let (id, nimbus_proof) = nimbus_id::<T>(&owner);
+			let (keys, keys_proof) = keys::<T>(&owner);
+			assert_ok!(Pallet::<T>::add_keys(
+				RawOrigin::Signed(owner.clone()).into(),
+				keys_wrapper::<T>(id.clone(), keys),
+				nimbus_proof,
+				keys_proof,
+			));
+			first_id.get_or_insert(id);
+		}
+		let id = first_id.expect("x is at least 1");
+		let now = frame_system::Pallet::<T>::block_number();
+		frame_system::Pallet::<T>::set_block_number(now + T::InactivityPeriod::get() + 1u32.into());
+		let caller = create_user_funded_for::<T>(1, 0);
+	}: _(RawOrigin::Signed(caller), id.clone())
+	verify {
+		assert_eq!(Pallet::<T>::account_id_of(&id), None);
+		assert_eq!(Pallet::<T>::nimbus_ids_of(&owner).len() as u32, x - 1);
+	}
+
+	note_author {
+		let author = create_funded_user::<T>();
+		let (id, proof) = nimbus_id::<T>(&author);
+		assert_ok!(Pallet::<T>::add_association(
+			RawOrigin::Signed(author.clone()).into(),
+			id.clone(),
+			proof,
+		));
+		frame_system::Pallet::<T>::deposit_log(DigestItem::PreRuntime(NIMBUS_ENGINE_ID, id.encode()));
+		let now = frame_system::Pallet::<T>::block_number() + 1u32.into();
+		frame_system::Pallet::<T>::set_block_number(now);
+	}: {
+		Pallet::<T>::note_author(author.clone());
+	}
+	verify {
+		assert_eq!(Pallet::<T>::last_authored(&id), Some(now));
+	}
 }
 
 #[cfg(test)]
@@ -111,13 +364,17 @@ mod tests {
 	use crate::mock::Runtime;
 	use frame_support::assert_ok;
 	use sp_io::TestExternalities;
+	use sp_keystore::{testing::MemoryKeystore, KeystoreExt};
 	use sp_runtime::BuildStorage;
 
 	pub fn new_test_ext() -> TestExternalities {
 		let t = frame_system::GenesisConfig::<Runtime>::default()
 			.build_storage()
 			.unwrap();
-		TestExternalities::new(t)
+		let mut ext = TestExternalities::new(t);
+		// The benchmarks sign the ownership proofs with keys generated in the keystore
+		ext.register_extension(KeystoreExt::new(MemoryKeystore::new()));
+		ext
 	}
 
 	#[test]
@@ -21,6 +21,19 @@
 //! queries. This mapping will likely need to go the other way if using exhaustive authority sets.
 //! That could either be a separate pallet, or this pallet could implement a two-way mapping. But
 //! for now it it one-way
+//!
+//! An account can register up to `MaxKeysPerAccount` NimbusIds, each with its own deposit and
+//! keys, so that an operator can run several collator machines or hot standbys under one account.
+//!
+//! A rotation can also be scheduled to take effect at a future block or session, so that the old
+//! NimbusId stays valid for the blocks that are already in flight.
+//!
+//! To prevent squatting of keys that another operator is about to use, every registration must be
+//! signed by the nimbus key, and optionally by the keys, over the genesis hash of the chain, the
+//! registering account and its nonce. See `ownership_payload`.
+//!
+//! If a key is compromised or squatted, the `ForceOrigin` can insert or remove mappings, and either
+//! slash or return the deposit of a removed mapping.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
@@ -39,28 +52,124 @@ mod tests;
 
 pub mod migrations;
 
+use parity_scale_codec::Encode;
+use sp_runtime::traits::Member;
+use sp_runtime::RuntimeAppPublic;
+use sp_std::vec::Vec;
+
+/// Verifies that the registering account controls the private keys of `Keys`.
+pub trait KeysOwnership<Keys> {
+	/// The proof that is submitted with the keys.
+	type Proof: frame_support::Parameter + Member;
+	/// Whether the proof was made by the private keys of `keys` over this payload.
+	fn verify(keys: &Keys, payload: &[u8], proof: &Self::Proof) -> bool;
+	/// Keys whose private keys are available to the benchmarks, with their proof.
+	#[cfg(feature = "runtime-benchmarks")]
+	fn benchmark_keys(payload: &[u8]) -> (Keys, Self::Proof);
+}
+
+/// No proof is required for the keys, only for the NimbusId.
+impl<Keys: From<nimbus_primitives::NimbusId>> KeysOwnership<Keys> for () {
+	type Proof = ();
+	fn verify(_keys: &Keys, _payload: &[u8], _proof: &()) -> bool {
+		true
+	}
+	#[cfg(feature = "runtime-benchmarks")]
+	fn benchmark_keys(_payload: &[u8]) -> (Keys, ()) {
+		(nimbus_primitives::NimbusId::generate_pair(None).into(), ())
+	}
+}
+
+/// The keys are a single application key, such as a `VrfId`, which signs the payload.
+pub struct AppKeyOwnership;
+impl<Key> KeysOwnership<Key> for AppKeyOwnership
+where
+	Key: RuntimeAppPublic,
+	Key::Signature: frame_support::Parameter + Member,
+{
+	type Proof = Key::Signature;
+	fn verify(key: &Key, payload: &[u8], proof: &Key::Signature) -> bool {
+		key.verify(&payload, proof)
+	}
+	#[cfg(feature = "runtime-benchmarks")]
+	fn benchmark_keys(payload: &[u8]) -> (Key, Key::Signature) {
+		let key = Key::generate_pair(None);
+		let proof = key.sign(&payload).expect("the key was just generated");
+		(key, proof)
+	}
+}
+
+/// The context of the payload that is signed to prove the ownership of the registered keys.
+pub const OWNERSHIP_PROOF_CONTEXT: &[u8] = b"nimbus-author-mapping-ownership";
+
+/// The payload that must be signed by the registered keys, which makes each proof only valid
+/// for one registration of this account, on the chain with this genesis hash.
+pub fn ownership_payload<Hash: Encode, AccountId: Encode>(
+	genesis_hash: &Hash,
+	account_id: &AccountId,
+	nonce: u32,
+) -> Vec<u8> {
+	(OWNERSHIP_PROOF_CONTEXT, genesis_hash, account_id, nonce).encode()
+}
+
 #[pallet]
 pub mod pallet {
-	use crate::WeightInfo;
+	use crate::{ownership_payload, KeysOwnership, WeightInfo};
 	use frame_support::pallet_prelude::*;
-	use frame_support::traits::{Currency, ReservableCurrency};
+	use frame_support::traits::{
+		fungible::{self, BalancedHold, Credit, InspectHold, MutateHold},
+		tokens::{Fortitude, Precision, Restriction},
+		OnUnbalanced, StorageVersion,
+	};
 	use frame_system::pallet_prelude::*;
-	use nimbus_primitives::{AccountLookup, NimbusId};
+	use nimbus_primitives::{
+		AccountLookup, EventHandler, NimbusId, NimbusSignature, NIMBUS_ENGINE_ID,
+	};
 	use session_keys_primitives::KeysLookup;
-	use sp_std::{mem::size_of, vec::Vec};
+	use sp_runtime::traits::{Saturating, Zero};
+	use sp_runtime::{Percent, RuntimeAppPublic};
+	use sp_std::vec::Vec;
 
-	pub type BalanceOf<T> = <<T as Config>::DepositCurrency as Currency<
+	pub type BalanceOf<T> = <<T as Config>::DepositCurrency as fungible::Inspect<
 		<T as frame_system::Config>::AccountId,
 	>>::Balance;
 
-	#[derive(Clone, Encode, Decode, PartialEq, Eq, Debug, scale_info::TypeInfo)]
+	pub type CreditOf<T> =
+		Credit<<T as frame_system::Config>::AccountId, <T as Config>::DepositCurrency>;
+
+	pub type KeysProofOf<T> =
+		<<T as Config>::KeysOwnership as KeysOwnership<<T as Config>::Keys>>::Proof;
+
+	#[derive(Clone, Encode, Decode, PartialEq, Eq, Debug, scale_info::TypeInfo, MaxEncodedLen)]
 	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
 	pub struct RegistrationInfo<T: Config> {
 		pub(crate) account: T::AccountId,
 		pub(crate) deposit: BalanceOf<T>,
 		pub(crate) keys: T::Keys,
 	}
 
+	/// When a scheduled rotation takes effect.
+	#[derive(
+		Clone, Copy, Encode, Decode, PartialEq, Eq, Debug, scale_info::TypeInfo, MaxEncodedLen,
+	)]
+	pub enum RotationTime<BlockNumber> {
+		/// At the beginning of this block.
+		Block(BlockNumber),
+		/// At the beginning of the first block of this session.
+		Session(u32),
+	}
+
+	/// A rotation that will replace a NimbusId and its keys.
+	#[derive(Clone, Encode, Decode, PartialEq, Eq, Debug, scale_info::TypeInfo, MaxEncodedLen)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct ScheduledRotation<T: Config> {
+		pub(crate) new_nimbus_id: NimbusId,
+		pub(crate) new_keys: T::Keys,
+		pub(crate) at: RotationTime<BlockNumberFor<T>>,
+	}
+
 	/// Wrapper to form the input to `set_keys` from NimbusId + keys
 	pub fn keys_wrapper<T: Config>(nimbus_id: NimbusId, keys: T::Keys) -> Vec<u8> {
 		let mut r = nimbus_id.encode();
@@ -68,27 +177,64 @@ pub mod pallet {
 		r
 	}
 
-	/// Size of NimbusId + T::Keys to check size of `set_keys` input before trying to decode
+	/// Maximum encoded size of NimbusId + T::Keys, to bound the `set_keys` input before trying
+	/// to decode it
 	pub fn keys_size<T: Config>() -> usize {
-		size_of::<NimbusId>() as usize + size_of::<T::Keys>() as usize
+		NimbusId::max_encoded_len().saturating_add(T::Keys::max_encoded_len())
 	}
 
+	/// The current storage version.
+	pub(crate) const STORAGE_VERSION: StorageVersion = StorageVersion::new(3);
+
 	#[pallet::pallet]
-	#[pallet::without_storage_info]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(PhantomData<T>);
 
+	/// A reason for the pallet placing a hold on funds.
+	#[pallet::composite_enum]
+	pub enum HoldReason {
+		/// The security deposit of a registered NimbusId.
+		AuthorMappingDeposit,
+	}
+
 	/// Configuration trait of this pallet
 	#[pallet::config]
 	pub trait Config: frame_system::Config {
 		/// Overarching event type
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
-		/// Currency in which the security deposit will be taken.
-		type DepositCurrency: Currency<Self::AccountId> + ReservableCurrency<Self::AccountId>;
+		/// The overarching hold reason.
+		type RuntimeHoldReason: From<HoldReason>;
+		/// Currency in which the security deposit will be held.
+		type DepositCurrency: MutateHold<Self::AccountId, Reason = Self::RuntimeHoldReason>
+			+ BalancedHold<Self::AccountId>;
 		/// The amount that should be taken as a security deposit when registering a NimbusId.
-		type DepositAmount: Get<<Self::DepositCurrency as Currency<Self::AccountId>>::Balance>;
+		type DepositAmount: Get<BalanceOf<Self>>;
 		/// Additional keys
 		/// Convertible From<NimbusId> to get default keys for each mapping (for the migration)
-		type Keys: Parameter + Member + MaybeSerializeDeserialize + From<NimbusId>;
+		type Keys: Parameter + Member + MaybeSerializeDeserialize + MaxEncodedLen + From<NimbusId>;
+		/// How the registering account proves that it controls the private keys of `Keys`.
+		/// Use `()` to only require a proof for the NimbusId.
+		type KeysOwnership: KeysOwnership<Self::Keys>;
+		/// The maximum number of NimbusIds that can be registered by a single account.
+		#[pallet::constant]
+		type MaxKeysPerAccount: Get<u32>;
+		/// The current session, at which rotations can be scheduled.
+		/// Use `()` if the chain has no sessions, rotations can then only be scheduled at a block.
+		type CurrentSession: Get<Option<u32>>;
+		/// The maximum number of rotations that can be scheduled at the same block or session.
+		#[pallet::constant]
+		type MaxScheduledRotations: Get<u32>;
+		/// The origin that can insert or remove any mapping.
+		type ForceOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+		/// Handler for the deposits that are slashed when the `ForceOrigin` removes a mapping.
+		type Slashed: OnUnbalanced<CreditOf<Self>>;
+		/// The number of blocks after which a NimbusId can be removed by anyone, if none of the
+		/// NimbusIds of its account authored in the meantime.
+		#[pallet::constant]
+		type InactivityPeriod: Get<BlockNumberFor<Self>>;
+		/// The part of the deposit of an inactive NimbusId paid to the account that removes it.
+		#[pallet::constant]
+		type InactivityReward: Get<Percent>;
 		/// Weight information for extrinsics in this pallet.
 		type WeightInfo: WeightInfo;
 	}
@@ -112,6 +258,27 @@ pub mod pallet {
 		DecodeNimbusFailed,
 		/// Failed to decode T::Keys for `set_keys`
 		DecodeKeysFailed,
+		/// The account has already registered `MaxKeysPerAccount` NimbusIds
+		TooManyKeys,
+		/// A rotation is already scheduled for this NimbusId
+		RotationAlreadyScheduled,
+		/// No rotation is scheduled for this NimbusId
+		RotationNotFound,
+		/// A rotation can only be scheduled at a future block or session
+		RotationNotInFuture,
+		/// `MaxScheduledRotations` rotations are already scheduled at this block or session
+		TooManyScheduledRotations,
+		/// The NimbusId did not sign the ownership payload of this account
+		InvalidNimbusProof,
+		/// The keys did not sign the ownership payload of this account
+		InvalidKeysProof,
+		/// A NimbusId of the account authored or was registered less than `InactivityPeriod`
+		/// blocks ago
+		StillActive,
+		/// Rotations cannot be scheduled at a session, because the chain has no sessions
+		SessionsNotSupported,
+		/// `update_deposits` must visit at least one registration
+		ZeroLimit,
 	}
 
 	#[pallet::event]
@@ -135,6 +302,96 @@ pub mod pallet {
 			account_id: T::AccountId,
 			new_keys: T::Keys,
 		},
+		/// A rotation of a NimbusId has been scheduled. The NimbusId stays valid until then.
+		KeysRotationScheduled {
+			nimbus_id: NimbusId,
+			new_nimbus_id: NimbusId,
+			account_id: T::AccountId,
+			new_keys: T::Keys,
+			at: RotationTime<BlockNumberFor<T>>,
+		},
+		/// A scheduled rotation of a NimbusId has been cancelled.
+		KeysRotationCancelled {
+			nimbus_id: NimbusId,
+			account_id: T::AccountId,
+		},
+		/// A scheduled rotation could not take effect, because the new NimbusId has been
+		/// registered in the meantime.
+		KeysRotationFailed {
+			nimbus_id: NimbusId,
+			new_nimbus_id: NimbusId,
+			account_id: T::AccountId,
+		},
+		/// A NimbusId has been registered by the `ForceOrigin`, without a deposit.
+		KeysForceRegistered {
+			nimbus_id: NimbusId,
+			account_id: T::AccountId,
+			keys: T::Keys,
+		},
+		/// A NimbusId has been de-registered by the `ForceOrigin`. The part of its deposit that
+		/// was not slashed has been returned.
+		KeysForceRemoved {
+			nimbus_id: NimbusId,
+			account_id: T::AccountId,
+			keys: T::Keys,
+			slashed: BalanceOf<T>,
+		},
+		/// The deposit of a NimbusId has been brought in line with the current `DepositAmount`.
+		DepositUpdated {
+			nimbus_id: NimbusId,
+			account_id: T::AccountId,
+			old_deposit: BalanceOf<T>,
+			new_deposit: BalanceOf<T>,
+		},
+		/// A NimbusId that did not author for `InactivityPeriod` blocks has been de-registered.
+		/// The reward has been paid from its deposit, and the rest returned.
+		KeysExpired {
+			nimbus_id: NimbusId,
+			account_id: T::AccountId,
+			keys: T::Keys,
+			reward: BalanceOf<T>,
+		},
+		/// The deposit of a NimbusId could not be increased to the current `DepositAmount`
+		/// because the account cannot afford it. The previous deposit is kept.
+		DepositUpdateFailed {
+			nimbus_id: NimbusId,
+			account_id: T::AccountId,
+			deposit: BalanceOf<T>,
+		},
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			let mut due = RotationsDue::<T>::take(RotationTime::Block(n)).into_inner();
+
+			// The current session and LastSession are read at every block
+			let mut weight = T::DbWeight::get().reads(2);
+			if let Some(session) = T::CurrentSession::get() {
+				let last_session = LastSession::<T>::get();
+				if last_session != Some(session) {
+					// Sessions are not expected to be skipped, but rotations must not be lost if
+					// they are. Skipped sessions are caught up one per block, so that the
+					// rotations enacted in a block stay bounded.
+					let next_session = match last_session {
+						Some(last_session) if last_session < session => last_session + 1,
+						_ => session,
+					};
+					due.extend(RotationsDue::<T>::take(RotationTime::Session(next_session)));
+					LastSession::<T>::put(next_session);
+					weight.saturating_accrue(T::DbWeight::get().reads_writes(1, 2));
+				}
+			}
+
+			let rotations = due.len() as u32;
+			for nimbus_id in due {
+				Self::enact_scheduled_rotation(nimbus_id);
+			}
+
+			weight.saturating_add(<T as Config>::WeightInfo::enact_scheduled_rotations(
+				rotations,
+			))
+		}
 	}
 
 	#[pallet::call]
@@ -143,10 +400,17 @@ pub mod pallet {
 		///
 		/// Users who have been (or will soon be) elected active collators in staking,
 		/// should submit this extrinsic to have their blocks accepted and earn rewards.
+		/// The NimbusId must sign the `ownership_payload` of your account at its current nonce, on
+		/// this chain.
 		#[pallet::call_index(0)]
 		#[pallet::weight(<T as Config>::WeightInfo::add_association())]
-		pub fn add_association(origin: OriginFor<T>, nimbus_id: NimbusId) -> DispatchResult {
+		pub fn add_association(
+			origin: OriginFor<T>,
+			nimbus_id: NimbusId,
+			nimbus_proof: NimbusSignature,
+		) -> DispatchResult {
 			let account_id = ensure_signed(origin)?;
+			Self::verify_ownership(&account_id, &nimbus_id, &nimbus_proof, None)?;
 
 			Self::register_keys(nimbus_id.clone(), account_id, nimbus_id.into())
 		}
@@ -156,14 +420,17 @@ pub mod pallet {
 		/// This is useful for normal key rotation or for when switching from one physical collator
 		/// machine to another. No new security deposit is required.
 		/// This sets keys to new_nimbus_id.into() by default.
+		/// The new NimbusId must sign the ownership payload of your account.
 		#[pallet::call_index(1)]
 		#[pallet::weight(<T as Config>::WeightInfo::update_association())]
 		pub fn update_association(
 			origin: OriginFor<T>,
 			old_nimbus_id: NimbusId,
 			new_nimbus_id: NimbusId,
+			nimbus_proof: NimbusSignature,
 		) -> DispatchResult {
 			let account_id = ensure_signed(origin)?;
+			Self::verify_ownership(&account_id, &new_nimbus_id, &nimbus_proof, None)?;
 
 			Self::rotate_keys(
 				old_nimbus_id,
@@ -185,18 +452,25 @@ pub mod pallet {
 			Self::rm_keys(nimbus_id, account_id)
 		}
 
-		/// Remove your Mapping.
+		/// Remove all your Mappings.
 		///
 		/// This is useful when you are no longer an author and would like to re-claim your security
-		/// deposit.
+		/// deposits.
 		#[pallet::call_index(3)]
-		#[pallet::weight(<T as Config>::WeightInfo::remove_keys())]
-		pub fn remove_keys(origin: OriginFor<T>) -> DispatchResult {
+		#[pallet::weight(<T as Config>::WeightInfo::remove_keys(T::MaxKeysPerAccount::get()))]
+		pub fn remove_keys(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
 			let account_id = ensure_signed(origin)?;
-			let nimbus_id =
-				Self::nimbus_id_of(&account_id).ok_or(Error::<T>::OldAuthorIdNotFound)?;
+			let nimbus_ids = Self::nimbus_ids_of(&account_id);
+			ensure!(!nimbus_ids.is_empty(), Error::<T>::OldAuthorIdNotFound);
 
-			Self::rm_keys(nimbus_id, account_id)
+			for nimbus_id in &nimbus_ids {
+				Self::rm_keys(nimbus_id.clone(), account_id.clone())?;
+			}
+
+			Ok(Some(<T as Config>::WeightInfo::remove_keys(
+				nimbus_ids.len() as u32
+			))
+			.into())
 		}
 
 		/// Set association and session keys at once.
@@ -204,25 +478,451 @@ pub mod pallet {
 		/// This is useful for key rotation to update Nimbus and VRF keys in one call.
 		/// No new security deposit is required. Will replace `update_association` which is kept
 		/// now for backwards compatibility reasons.
+		/// If the NimbusId is already yours, only its keys are updated. Otherwise it replaces your
+		/// first NimbusId, or is registered if you have none.
+		/// Both the NimbusId and the keys must sign the `ownership_payload` of your account at its
+		/// current nonce.
 		#[pallet::call_index(4)]
 		#[pallet::weight(<T as Config>::WeightInfo::set_keys())]
-		pub fn set_keys(origin: OriginFor<T>, keys: Vec<u8>) -> DispatchResult {
+		pub fn set_keys(
+			origin: OriginFor<T>,
+			keys: Vec<u8>,
+			nimbus_proof: NimbusSignature,
+			keys_proof: KeysProofOf<T>,
+		) -> DispatchResult {
 			let account_id = ensure_signed(origin)?;
-			ensure!(keys.len() == keys_size::<T>(), Error::<T>::WrongKeySize);
-			let encoded = &mut keys.as_slice();
-			let new_nimbus_id =
-				NimbusId::decode(encoded).map_err(|_| Error::<T>::DecodeNimbusFailed)?;
-			let keys = T::Keys::decode(encoded).map_err(|_| Error::<T>::DecodeKeysFailed)?;
+			let (new_nimbus_id, keys) = Self::decode_keys(keys)?;
+			Self::verify_ownership(
+				&account_id,
+				&new_nimbus_id,
+				&nimbus_proof,
+				Some((&keys, &keys_proof)),
+			)?;
 
-			if let Some(old_nimbus_id) = Self::nimbus_id_of(&account_id) {
+			if Self::nimbus_ids_of(&account_id).contains(&new_nimbus_id) {
+				Self::rotate_keys(new_nimbus_id.clone(), new_nimbus_id, account_id, keys)
+			} else if let Some(old_nimbus_id) = Self::nimbus_id_of(&account_id) {
 				Self::rotate_keys(old_nimbus_id, new_nimbus_id, account_id, keys)
 			} else {
 				Self::register_keys(new_nimbus_id, account_id, keys)
 			}
 		}
+
+		/// Register an additional NimbusId with its session keys.
+		///
+		/// This is useful to run several collator machines, or hot standbys, under one account.
+		/// A security deposit is taken for each NimbusId.
+		#[pallet::call_index(5)]
+		#[pallet::weight(<T as Config>::WeightInfo::add_keys())]
+		pub fn add_keys(
+			origin: OriginFor<T>,
+			keys: Vec<u8>,
+			nimbus_proof: NimbusSignature,
+			keys_proof: KeysProofOf<T>,
+		) -> DispatchResult {
+			let account_id = ensure_signed(origin)?;
+			let (nimbus_id, keys) = Self::decode_keys(keys)?;
+			Self::verify_ownership(
+				&account_id,
+				&nimbus_id,
+				&nimbus_proof,
+				Some((&keys, &keys_proof)),
+			)?;
+
+			Self::register_keys(nimbus_id, account_id, keys)
+		}
+
+		/// Schedule a rotation of one of your NimbusIds and its keys.
+		///
+		/// This is useful to coordinate a switch from one physical collator machine to another.
+		/// The NimbusId stays valid until the rotation takes effect at the given block or session.
+		/// No new security deposit is required.
+		#[pallet::call_index(6)]
+		#[pallet::weight(<T as Config>::WeightInfo::schedule_keys_rotation())]
+		pub fn schedule_keys_rotation(
+			origin: OriginFor<T>,
+			nimbus_id: NimbusId,
+			keys: Vec<u8>,
+			at: RotationTime<BlockNumberFor<T>>,
+			nimbus_proof: NimbusSignature,
+			keys_proof: KeysProofOf<T>,
+		) -> DispatchResult {
+			let account_id = ensure_signed(origin)?;
+			let (new_nimbus_id, new_keys) = Self::decode_keys(keys)?;
+			Self::verify_ownership(
+				&account_id,
+				&new_nimbus_id,
+				&nimbus_proof,
+				Some((&new_keys, &keys_proof)),
+			)?;
+
+			let stored_info = MappingWithDeposit::<T>::try_get(&nimbus_id)
+				.map_err(|_| Error::<T>::AssociationNotFound)?;
+			ensure!(
+				account_id == stored_info.account,
+				Error::<T>::NotYourAssociation
+			);
+			if nimbus_id != new_nimbus_id {
+				ensure!(
+					MappingWithDeposit::<T>::get(&new_nimbus_id).is_none(),
+					Error::<T>::AlreadyAssociated
+				);
+			}
+			ensure!(
+				!ScheduledRotations::<T>::contains_key(&nimbus_id),
+				Error::<T>::RotationAlreadyScheduled
+			);
+			let in_future = match at {
+				RotationTime::Block(block) => block > frame_system::Pallet::<T>::block_number(),
+				RotationTime::Session(session) => {
+					let current_session =
+						T::CurrentSession::get().ok_or(Error::<T>::SessionsNotSupported)?;
+					session > current_session
+				}
+			};
+			ensure!(in_future, Error::<T>::RotationNotInFuture);
+
+			RotationsDue::<T>::try_mutate(at, |due| due.try_push(nimbus_id.clone()))
+				.map_err(|_| Error::<T>::TooManyScheduledRotations)?;
+			ScheduledRotations::<T>::insert(
+				&nimbus_id,
+				ScheduledRotation {
+					new_nimbus_id: new_nimbus_id.clone(),
+					new_keys: new_keys.clone(),
+					at,
+				},
+			);
+
+			<Pallet<T>>::deposit_event(Event::KeysRotationScheduled {
+				nimbus_id,
+				new_nimbus_id,
+				account_id,
+				new_keys,
+				at,
+			});
+			Ok(())
+		}
+
+		/// Cancel the scheduled rotation of one of your NimbusIds.
+		#[pallet::call_index(7)]
+		#[pallet::weight(<T as Config>::WeightInfo::cancel_keys_rotation())]
+		pub fn cancel_keys_rotation(origin: OriginFor<T>, nimbus_id: NimbusId) -> DispatchResult {
+			let account_id = ensure_signed(origin)?;
+			ensure!(
+				Self::account_id_of(&nimbus_id) == Some(account_id.clone()),
+				Error::<T>::NotYourAssociation
+			);
+			Self::unschedule_rotation(&nimbus_id).ok_or(Error::<T>::RotationNotFound)?;
+
+			<Pallet<T>>::deposit_event(Event::KeysRotationCancelled {
+				nimbus_id,
+				account_id,
+			});
+			Ok(())
+		}
+
+		/// Register a NimbusId and its keys for any account, without a deposit or a proof.
+		///
+		/// This is useful to restore a mapping after its key has been squatted.
+		#[pallet::call_index(8)]
+		#[pallet::weight(<T as Config>::WeightInfo::force_add_association())]
+		pub fn force_add_association(
+			origin: OriginFor<T>,
+			nimbus_id: NimbusId,
+			account_id: T::AccountId,
+			keys: T::Keys,
+		) -> DispatchResult {
+			T::ForceOrigin::ensure_origin(origin)?;
+			ensure!(
+				MappingWithDeposit::<T>::get(&nimbus_id).is_none(),
+				Error::<T>::AlreadyAssociated
+			);
+
+			Self::enact_registration_with_deposit(
+				&nimbus_id,
+				&account_id,
+				keys.clone(),
+				Zero::zero(),
+			)?;
+
+			<Pallet<T>>::deposit_event(Event::KeysForceRegistered {
+				nimbus_id,
+				account_id,
+				keys,
+			});
+			Ok(())
+		}
+
+		/// Remove the mapping of any NimbusId, and either slash or return its deposit.
+		///
+		/// This is useful when a key is compromised or squatted.
+		#[pallet::call_index(9)]
+		#[pallet::weight(<T as Config>::WeightInfo::force_remove_association())]
+		pub fn force_remove_association(
+			origin: OriginFor<T>,
+			nimbus_id: NimbusId,
+			slash: bool,
+		) -> DispatchResult {
+			T::ForceOrigin::ensure_origin(origin)?;
+			let stored_info = MappingWithDeposit::<T>::try_get(&nimbus_id)
+				.map_err(|_| Error::<T>::AssociationNotFound)?;
+			let account_id = stored_info.account;
+
+			Self::remove_mapping(&nimbus_id, &account_id);
+
+			let slashed = if slash {
+				let (credit, not_slashed) = T::DepositCurrency::slash(
+					&HoldReason::AuthorMappingDeposit.into(),
+					&account_id,
+					stored_info.deposit,
+				);
+				T::Slashed::on_unbalanced(credit);
+				stored_info.deposit.saturating_sub(not_slashed)
+			} else {
+				Self::release_deposit(&account_id, stored_info.deposit);
+				Zero::zero()
+			};
+
+			<Pallet<T>>::deposit_event(Event::KeysForceRemoved {
+				nimbus_id,
+				account_id,
+				keys: stored_info.keys,
+				slashed,
+			});
+			Ok(())
+		}
+
+		/// Bring the deposits of up to `limit` registrations in line with the current
+		/// `DepositAmount`, holding more or releasing the excess. Anyone can call it.
+		///
+		/// Each call continues where the previous one stopped, and the next pass starts over once
+		/// every registration has been visited. Registrations without a deposit, such as the ones
+		/// added by the `ForceOrigin`, are left untouched.
+		#[pallet::call_index(10)]
+		#[pallet::weight(<T as Config>::WeightInfo::update_deposits(*limit))]
+		pub fn update_deposits(origin: OriginFor<T>, limit: u32) -> DispatchResultWithPostInfo {
+			ensure_signed(origin)?;
+			ensure!(limit > 0, Error::<T>::ZeroLimit);
+
+			let registrations = match DepositUpdateCursor::<T>::get() {
+				Some(cursor) => MappingWithDeposit::<T>::iter_from(
+					MappingWithDeposit::<T>::hashed_key_for(cursor),
+				),
+				None => MappingWithDeposit::<T>::iter(),
+			};
+			let page: Vec<_> = registrations.take(limit as usize).collect();
+			let visited = page.len() as u32;
+
+			match page.last() {
+				Some((nimbus_id, _)) if visited == limit => {
+					DepositUpdateCursor::<T>::put(nimbus_id)
+				}
+				_ => DepositUpdateCursor::<T>::kill(),
+			}
+			for (nimbus_id, info) in page {
+				Self::update_deposit(nimbus_id, info);
+			}
+
+			Ok(Some(<T as Config>::WeightInfo::update_deposits(visited)).into())
+		}
+
+		/// Remove a NimbusId whose account did not author for more than `InactivityPeriod`
+		/// blocks, and receive `InactivityReward` of its deposit. Anyone can call it.
+		///
+		/// A registration or rotation counts as activity. An account is active as long as any of
+		/// its NimbusIds is, so the standby keys of an authoring account cannot be removed.
+		#[pallet::call_index(11)]
+		#[pallet::weight(<T as Config>::WeightInfo::remove_inactive(T::MaxKeysPerAccount::get()))]
+		pub fn remove_inactive(
+			origin: OriginFor<T>,
+			nimbus_id: NimbusId,
+		) -> DispatchResultWithPostInfo {
+			let caller = ensure_signed(origin)?;
+			let stored_info = MappingWithDeposit::<T>::try_get(&nimbus_id)
+				.map_err(|_| Error::<T>::AssociationNotFound)?;
+			let account_id = stored_info.account;
+
+			let mut nimbus_ids = Self::nimbus_ids_of(&account_id);
+			if !nimbus_ids.contains(&nimbus_id) {
+				nimbus_ids.push(nimbus_id.clone());
+			}
+			let inactive_since = frame_system::Pallet::<T>::block_number()
+				.saturating_sub(T::InactivityPeriod::get());
+			for id in &nimbus_ids {
+				let last_authored = LastAuthored::<T>::get(id).ok_or(Error::<T>::StillActive)?;
+				ensure!(last_authored < inactive_since, Error::<T>::StillActive);
+			}
+
+			Self::remove_mapping(&nimbus_id, &account_id);
+
+			// The reward is skipped if it cannot be paid, e.g. when it is below the existential
+			// deposit of a new account.
+			let reward = T::DepositCurrency::transfer_on_hold(
+				&HoldReason::AuthorMappingDeposit.into(),
+				&account_id,
+				&caller,
+				T::InactivityReward::get() * stored_info.deposit,
+				Precision::BestEffort,
+				Restriction::Free,
+				Fortitude::Polite,
+			)
+			.unwrap_or_else(|_| Zero::zero());
+			Self::release_deposit(&account_id, stored_info.deposit.saturating_sub(reward));
+
+			<Pallet<T>>::deposit_event(Event::KeysExpired {
+				nimbus_id,
+				account_id,
+				keys: stored_info.keys,
+				reward,
+			});
+			Ok(Some(<T as Config>::WeightInfo::remove_inactive(
+				nimbus_ids.len() as u32
+			))
+			.into())
+		}
 	}
 
 	impl<T: Config> Pallet<T> {
+		/// Check that the NimbusId, and the keys if they are not derived from it, signed the
+		/// ownership payload of this account, and move to the next nonce so the proofs cannot be
+		/// replayed.
+		fn verify_ownership(
+			account_id: &T::AccountId,
+			nimbus_id: &NimbusId,
+			nimbus_proof: &NimbusSignature,
+			keys: Option<(&T::Keys, &KeysProofOf<T>)>,
+		) -> DispatchResult {
+			let nonce = OwnershipNonce::<T>::get(account_id);
+			let genesis_hash = frame_system::Pallet::<T>::block_hash(BlockNumberFor::<T>::zero());
+			let payload = ownership_payload(&genesis_hash, account_id, nonce);
+
+			ensure!(
+				nimbus_id.verify(&payload, nimbus_proof),
+				Error::<T>::InvalidNimbusProof
+			);
+			if let Some((keys, keys_proof)) = keys {
+				ensure!(
+					T::KeysOwnership::verify(keys, &payload, keys_proof),
+					Error::<T>::InvalidKeysProof
+				);
+			}
+
+			OwnershipNonce::<T>::insert(account_id, nonce.wrapping_add(1));
+			Ok(())
+		}
+		fn decode_keys(keys: Vec<u8>) -> Result<(NimbusId, T::Keys), DispatchError> {
+			ensure!(keys.len() <= keys_size::<T>(), Error::<T>::WrongKeySize);
+			let encoded = &mut keys.as_slice();
+			let nimbus_id =
+				NimbusId::decode(encoded).map_err(|_| Error::<T>::DecodeNimbusFailed)?;
+			let keys = T::Keys::decode(encoded).map_err(|_| Error::<T>::DecodeKeysFailed)?;
+			ensure!(encoded.is_empty(), Error::<T>::WrongKeySize);
+
+			Ok((nimbus_id, keys))
+		}
+		/// Enact the rotation scheduled for this NimbusId, if it is still scheduled.
+		fn enact_scheduled_rotation(nimbus_id: NimbusId) {
+			let Some(rotation) = ScheduledRotations::<T>::take(&nimbus_id) else {
+				return;
+			};
+			let Some(account_id) = Self::account_id_of(&nimbus_id) else {
+				return;
+			};
+
+			// Everything is checked before any change, so a failed rotation leaves no trace.
+			if Self::rotate_keys(
+				nimbus_id.clone(),
+				rotation.new_nimbus_id.clone(),
+				account_id.clone(),
+				rotation.new_keys,
+			)
+			.is_err()
+			{
+				<Pallet<T>>::deposit_event(Event::KeysRotationFailed {
+					nimbus_id,
+					new_nimbus_id: rotation.new_nimbus_id,
+					account_id,
+				});
+			}
+		}
+		/// Remove the rotation scheduled for this NimbusId, if any, so that it is no longer due.
+		fn unschedule_rotation(nimbus_id: &NimbusId) -> Option<ScheduledRotation<T>> {
+			let rotation = ScheduledRotations::<T>::take(nimbus_id)?;
+			RotationsDue::<T>::mutate_exists(rotation.at, |maybe_due| {
+				if let Some(due) = maybe_due {
+					due.retain(|id| id != nimbus_id);
+					if due.is_empty() {
+						*maybe_due = None;
+					}
+				}
+			});
+			Some(rotation)
+		}
+		/// Remove the mapping of this NimbusId, which belongs to this account.
+		fn remove_mapping(nimbus_id: &NimbusId, account_id: &T::AccountId) {
+			MappingWithDeposit::<T>::remove(nimbus_id);
+			LastAuthored::<T>::remove(nimbus_id);
+			Self::unschedule_rotation(nimbus_id);
+			NimbusLookup::<T>::mutate_exists(account_id, |maybe_ids| {
+				if let Some(ids) = maybe_ids {
+					ids.retain(|id| id != nimbus_id);
+					if ids.is_empty() {
+						*maybe_ids = None;
+					}
+				}
+			});
+		}
+		fn release_deposit(account_id: &T::AccountId, deposit: BalanceOf<T>) {
+			let _ = T::DepositCurrency::release(
+				&HoldReason::AuthorMappingDeposit.into(),
+				account_id,
+				deposit,
+				Precision::BestEffort,
+			);
+		}
+		fn update_deposit(nimbus_id: NimbusId, mut info: RegistrationInfo<T>) {
+			let old_deposit = info.deposit;
+			let new_deposit = T::DepositAmount::get();
+			if old_deposit.is_zero() || old_deposit == new_deposit {
+				return;
+			}
+
+			if new_deposit > old_deposit {
+				if T::DepositCurrency::hold(
+					&HoldReason::AuthorMappingDeposit.into(),
+					&info.account,
+					new_deposit.saturating_sub(old_deposit),
+				)
+				.is_err()
+				{
+					<Pallet<T>>::deposit_event(Event::DepositUpdateFailed {
+						nimbus_id,
+						account_id: info.account,
+						deposit: old_deposit,
+					});
+					return;
+				}
+			} else {
+				Self::release_deposit(&info.account, old_deposit.saturating_sub(new_deposit));
+			}
+
+			info.deposit = new_deposit;
+			MappingWithDeposit::<T>::insert(&nimbus_id, &info);
+
+			<Pallet<T>>::deposit_event(Event::DepositUpdated {
+				nimbus_id,
+				account_id: info.account,
+				old_deposit,
+				new_deposit,
+			});
+		}
+		/// The deposits held by this pallet on the account.
+		pub fn deposit_held(account_id: &T::AccountId) -> BalanceOf<T> {
+			T::DepositCurrency::balance_on_hold(
+				&HoldReason::AuthorMappingDeposit.into(),
+				account_id,
+			)
+		}
 		fn rm_keys(nimbus_id: NimbusId, account_id: T::AccountId) -> DispatchResult {
 			let stored_info = MappingWithDeposit::<T>::try_get(&nimbus_id)
 				.map_err(|_| Error::<T>::AssociationNotFound)?;
@@ -232,10 +932,9 @@ pub mod pallet {
 				Error::<T>::NotYourAssociation
 			);
 
-			MappingWithDeposit::<T>::remove(&nimbus_id);
-			NimbusLookup::<T>::remove(&account_id);
+			Self::remove_mapping(&nimbus_id, &account_id);
 
-			T::DepositCurrency::unreserve(&account_id, stored_info.deposit);
+			Self::release_deposit(&account_id, stored_info.deposit);
 
 			<Pallet<T>>::deposit_event(Event::KeysRemoved {
 				nimbus_id,
@@ -284,12 +983,22 @@ pub mod pallet {
 			}
 
 			MappingWithDeposit::<T>::remove(&old_nimbus_id);
+			if old_nimbus_id != new_nimbus_id {
+				// A rotation scheduled for the old NimbusId no longer applies.
+				Self::unschedule_rotation(&old_nimbus_id);
+				LastAuthored::<T>::remove(&old_nimbus_id);
+			}
+			LastAuthored::<T>::insert(&new_nimbus_id, frame_system::Pallet::<T>::block_number());
 			let new_stored_info = RegistrationInfo {
 				keys: new_keys.clone(),
 				..stored_info
 			};
 			MappingWithDeposit::<T>::insert(&new_nimbus_id, &new_stored_info);
-			NimbusLookup::<T>::insert(&account_id, &new_nimbus_id);
+			NimbusLookup::<T>::mutate(&account_id, |ids| {
+				if let Some(id) = ids.iter_mut().find(|id| **id == old_nimbus_id) {
+					*id = new_nimbus_id.clone();
+				}
+			});
 
 			<Pallet<T>>::deposit_event(Event::KeysRotated {
 				new_nimbus_id,
@@ -303,10 +1012,33 @@ pub mod pallet {
 			account_id: &T::AccountId,
 			keys: T::Keys,
 		) -> DispatchResult {
-			let deposit = T::DepositAmount::get();
+			Self::enact_registration_with_deposit(
+				nimbus_id,
+				account_id,
+				keys,
+				T::DepositAmount::get(),
+			)
+		}
+		/// Register a NimbusId and hold the given deposit, which can be zero.
+		fn enact_registration_with_deposit(
+			nimbus_id: &NimbusId,
+			account_id: &T::AccountId,
+			keys: T::Keys,
+			deposit: BalanceOf<T>,
+		) -> DispatchResult {
+			let mut nimbus_ids = NimbusLookup::<T>::get(account_id);
+			nimbus_ids
+				.try_push(nimbus_id.clone())
+				.map_err(|_| Error::<T>::TooManyKeys)?;
 
-			T::DepositCurrency::reserve(account_id, deposit)
+			if !deposit.is_zero() {
+				T::DepositCurrency::hold(
+					&HoldReason::AuthorMappingDeposit.into(),
+					account_id,
+					deposit,
+				)
 				.map_err(|_| Error::<T>::CannotAffordSecurityDeposit)?;
+			}
 
 			let info = RegistrationInfo {
 				account: account_id.clone(),
@@ -315,7 +1047,8 @@ pub mod pallet {
 			};
 
 			MappingWithDeposit::<T>::insert(nimbus_id, info);
-			NimbusLookup::<T>::insert(account_id, nimbus_id);
+			NimbusLookup::<T>::insert(account_id, nimbus_ids);
+			LastAuthored::<T>::insert(nimbus_id, frame_system::Pallet::<T>::block_number());
 
 			Ok(())
 		}
@@ -330,16 +1063,62 @@ pub mod pallet {
 
 	#[pallet::storage]
 	#[pallet::getter(fn nimbus_lookup)]
-	/// We maintain a reverse mapping from AccountIds to NimbusIDS
-	pub type NimbusLookup<T: Config> =
-		StorageMap<_, Blake2_128Concat, T::AccountId, NimbusId, OptionQuery>;
+	/// We maintain a reverse mapping from AccountIds to the NimbusIds they registered, in order of
+	/// registration
+	pub type NimbusLookup<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<NimbusId, T::MaxKeysPerAccount>,
+		ValueQuery,
+	>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn scheduled_rotation)]
+	/// The rotations that are scheduled for each NimbusId
+	pub type ScheduledRotations<T: Config> =
+		StorageMap<_, Blake2_128Concat, NimbusId, ScheduledRotation<T>, OptionQuery>;
+
+	#[pallet::storage]
+	/// The NimbusIds whose scheduled rotation takes effect at each block or session
+	pub type RotationsDue<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		RotationTime<BlockNumberFor<T>>,
+		BoundedVec<NimbusId, T::MaxScheduledRotations>,
+		ValueQuery,
+	>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn ownership_nonce)]
+	/// The nonce that the next ownership proof of each account must sign
+	pub type OwnershipNonce<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	#[pallet::storage]
+	/// The session at which the scheduled rotations were last enacted
+	pub type LastSession<T: Config> = StorageValue<_, u32, OptionQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn last_authored)]
+	/// The last block authored by each NimbusId, or at which it was registered or rotated if it
+	/// did not author since
+	pub type LastAuthored<T: Config> =
+		StorageMap<_, Blake2_128Concat, NimbusId, BlockNumberFor<T>, OptionQuery>;
+
+	#[pallet::storage]
+	/// The last registration visited by `update_deposits`, the next call continues after it
+	pub type DepositUpdateCursor<T: Config> = StorageValue<_, NimbusId, OptionQuery>;
 
 	#[pallet::genesis_config]
 	#[derive(frame_support::DefaultNoBound)]
 	/// Genesis config for author mapping pallet
 	pub struct GenesisConfig<T: Config> {
-		/// The associations that should exist at chain genesis
+		/// The associations that should exist at chain genesis, with keys derived from the NimbusId
 		pub mappings: Vec<(NimbusId, T::AccountId)>,
+		/// The associations that should exist at chain genesis, with explicit keys. The deposit
+		/// is only taken if the last element is `true`.
+		pub mappings_with_keys: Vec<(NimbusId, T::AccountId, T::Keys, bool)>,
 	}
 
 	#[pallet::genesis_build]
@@ -354,6 +1133,25 @@ pub mod pallet {
 					log::warn!("Error with genesis author mapping registration: {:?}", e);
 				}
 			}
+			for (nimbus_id, account_id, keys, take_deposit) in &self.mappings_with_keys {
+				if MappingWithDeposit::<T>::contains_key(nimbus_id) {
+					log::warn!("Genesis author mapping registered twice: {:?}", nimbus_id);
+					continue;
+				}
+				let deposit = if *take_deposit {
+					T::DepositAmount::get()
+				} else {
+					Zero::zero()
+				};
+				if let Err(e) = Pallet::<T>::enact_registration_with_deposit(
+					nimbus_id,
+					account_id,
+					keys.clone(),
+					deposit,
+				) {
+					log::warn!("Error with genesis author mapping registration: {:?}", e);
+				}
+			}
 		}
 	}
 
@@ -363,6 +1161,39 @@ pub mod pallet {
 		}
 	}
 
+	// This code will be called by the author-inherent pallet with the account of each block
+	// author. The NimbusId that authored is read from the pre-runtime digest.
+	impl<T: Config> EventHandler<T::AccountId> for Pallet<T> {
+		fn note_author(author: T::AccountId) {
+			let digest = frame_system::Pallet::<T>::digest();
+			let nimbus_id = digest
+				.logs
+				.iter()
+				.filter_map(|d| d.as_pre_runtime())
+				.find_map(|(engine_id, mut data)| {
+					if engine_id == NIMBUS_ENGINE_ID {
+						NimbusId::decode(&mut data).ok()
+					} else {
+						None
+					}
+				});
+
+			if let Some(nimbus_id) = nimbus_id {
+				if Self::account_id_of(&nimbus_id) == Some(author) {
+					LastAuthored::<T>::insert(
+						&nimbus_id,
+						frame_system::Pallet::<T>::block_number(),
+					);
+				}
+			}
+
+			frame_system::Pallet::<T>::register_extra_weight_unchecked(
+				<T as Config>::WeightInfo::note_author(),
+				DispatchClass::Mandatory,
+			);
+		}
+	}
+
 	impl<T: Config> KeysLookup<NimbusId, T::Keys> for Pallet<T> {
 		#[cfg(feature = "runtime-benchmarks")]
 		type Account = T::AccountId;
@@ -372,7 +1203,6 @@ pub mod pallet {
 		/// Sets keys WITHOUT reserving deposit, for benchmarking purposes only
 		#[cfg(feature = "runtime-benchmarks")]
 		fn set_keys(id: NimbusId, account: T::AccountId, keys: T::Keys) {
-			use sp_runtime::traits::Zero;
 			MappingWithDeposit::<T>::insert(
 				id,
 				RegistrationInfo {
@@ -394,9 +1224,13 @@ pub mod pallet {
 		pub fn keys_of(nimbus_id: &NimbusId) -> Option<T::Keys> {
 			Self::account_and_deposit_of(nimbus_id).map(|info| info.keys)
 		}
-		/// A helper function to lookup NimbusId associated with a given AccountId
+		/// A helper function to lookup the first NimbusId registered by a given AccountId
 		pub fn nimbus_id_of(account_id: &T::AccountId) -> Option<NimbusId> {
-			NimbusLookup::<T>::get(account_id)
+			NimbusLookup::<T>::get(account_id).first().cloned()
+		}
+		/// A helper function to lookup all the NimbusIds registered by a given AccountId
+		pub fn nimbus_ids_of(account_id: &T::AccountId) -> Vec<NimbusId> {
+			NimbusLookup::<T>::get(account_id).into_inner()
 		}
 	}
 }
@@ -13,3 +13,265 @@
 
 // You should have received a copy of the GNU General Public License
 // along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::pallet::{
+	BalanceOf, Config, HoldReason, LastAuthored, MappingWithDeposit, NimbusLookup, Pallet,
+};
+use core::marker::PhantomData;
+use frame_support::traits::{
+	fungible::MutateHold, Get, OnRuntimeUpgrade, ReservableCurrency, StorageVersion,
+};
+use frame_support::weights::Weight;
+use frame_support::BoundedVec;
+use nimbus_primitives::NimbusId;
+use sp_runtime::traits::{Saturating, Zero};
+use sp_std::vec::Vec;
+
+#[cfg(feature = "try-runtime")]
+use {
+	parity_scale_codec::{Decode, Encode},
+	sp_std::collections::btree_set::BTreeSet,
+};
+
+/// Moves `NimbusLookup` from a single NimbusId per account to a list of NimbusIds.
+///
+/// The previous NimbusId of each account is kept first. Registrations whose reverse mapping was
+/// overwritten by a later registration of the same account are added back after it, up to
+/// `MaxKeysPerAccount`. The migration only runs once, on storage version 0.
+pub struct MultipleKeysPerAccount<T>(PhantomData<T>);
+
+impl<T> OnRuntimeUpgrade for MultipleKeysPerAccount<T>
+where
+	T: Config,
+{
+	fn on_runtime_upgrade() -> Weight {
+		if StorageVersion::get::<Pallet<T>>() >= 1 {
+			log::info!(target: "MultipleKeysPerAccount", "already migrated, skipping");
+			return T::DbWeight::get().reads(1);
+		}
+		log::info!(target: "MultipleKeysPerAccount", "starting migration");
+
+		let mut reads = 1u64;
+		let mut writes = 1u64;
+
+		NimbusLookup::<T>::translate::<NimbusId, _>(|_, nimbus_id| {
+			reads += 1;
+			writes += 1;
+			Some(BoundedVec::truncate_from(sp_std::vec![nimbus_id]))
+		});
+
+		for (nimbus_id, info) in MappingWithDeposit::<T>::iter() {
+			reads += 2;
+			writes += 1;
+			NimbusLookup::<T>::mutate(&info.account, |nimbus_ids| {
+				if nimbus_ids.contains(&nimbus_id) {
+					return;
+				}
+				if nimbus_ids.try_push(nimbus_id).is_err() {
+					log::warn!(
+						target: "MultipleKeysPerAccount",
+						"too many NimbusIds for an account, the extra ones are only mapped one-way"
+					);
+				}
+			});
+		}
+
+		StorageVersion::new(1).put::<Pallet<T>>();
+
+		T::DbWeight::get().reads_writes(reads, writes)
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn pre_upgrade() -> Result<Vec<u8>, sp_runtime::DispatchError> {
+		let mappings = MappingWithDeposit::<T>::iter().count() as u32;
+
+		Ok(mappings.encode())
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn post_upgrade(state: Vec<u8>) -> Result<(), sp_runtime::DispatchError> {
+		let expected: u32 =
+			Decode::decode(&mut &state[..]).expect("pre_upgrade provides a valid state; qed");
+
+		let mappings = MappingWithDeposit::<T>::iter().count() as u32;
+		assert_eq!(expected, mappings);
+
+		for (nimbus_id, info) in MappingWithDeposit::<T>::iter() {
+			let nimbus_ids = NimbusLookup::<T>::get(&info.account);
+			assert!(
+				nimbus_ids.contains(&nimbus_id) || nimbus_ids.is_full(),
+				"every registration is mapped back to its account"
+			);
+		}
+		assert!(StorageVersion::get::<Pallet<T>>() >= 1);
+
+		Ok(())
+	}
+}
+
+/// Moves the deposit of every registration from an anonymous reserve of `OldCurrency` to a hold
+/// of `DepositCurrency` with the `AuthorMappingDeposit` reason.
+///
+/// `OldCurrency` is the `DepositCurrency` that the runtime used before the pallet switched to
+/// holds, usually `Balances`. The migration only runs once, on storage version 1, so it must come
+/// after `MultipleKeysPerAccount`.
+pub struct ReservesToHolds<T, OldCurrency>(PhantomData<(T, OldCurrency)>);
+
+impl<T, OldCurrency> OnRuntimeUpgrade for ReservesToHolds<T, OldCurrency>
+where
+	T: Config,
+	OldCurrency: ReservableCurrency<T::AccountId, Balance = BalanceOf<T>>,
+{
+	fn on_runtime_upgrade() -> Weight {
+		if StorageVersion::get::<Pallet<T>>() != 1 {
+			log::info!(target: "ReservesToHolds", "not on storage version 1, skipping");
+			return T::DbWeight::get().reads(1);
+		}
+		log::info!(target: "ReservesToHolds", "starting migration");
+
+		let mut reads = 1u64;
+		let mut writes = 1u64;
+
+		let mappings: Vec<_> = MappingWithDeposit::<T>::iter().collect();
+		for (nimbus_id, info) in mappings {
+			reads += 3;
+			writes += 2;
+			let not_unreserved = OldCurrency::unreserve(&info.account, info.deposit);
+			let unreserved = info.deposit.saturating_sub(not_unreserved);
+			if let Err(e) = T::DepositCurrency::hold(
+				&HoldReason::AuthorMappingDeposit.into(),
+				&info.account,
+				unreserved,
+			) {
+				log::error!(
+					target: "ReservesToHolds",
+					"failed to hold the deposit of {:?}: {:?}, it stays reserved",
+					nimbus_id,
+					e
+				);
+				let _ = OldCurrency::reserve(&info.account, unreserved);
+				continue;
+			}
+			if !not_unreserved.is_zero() {
+				// Only what was actually reserved is now held, it is all that can be released.
+				log::warn!(
+					target: "ReservesToHolds",
+					"the deposit of {:?} was only partially reserved",
+					nimbus_id
+				);
+				writes += 1;
+				MappingWithDeposit::<T>::mutate(&nimbus_id, |maybe_info| {
+					if let Some(info) = maybe_info {
+						info.deposit = unreserved;
+					}
+				});
+			}
+		}
+
+		StorageVersion::new(2).put::<Pallet<T>>();
+
+		T::DbWeight::get().reads_writes(reads, writes)
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn pre_upgrade() -> Result<Vec<u8>, sp_runtime::DispatchError> {
+		let mut deposits = BalanceOf::<T>::zero();
+		let mut accounts = BTreeSet::new();
+		for info in MappingWithDeposit::<T>::iter_values() {
+			deposits = deposits.saturating_add(info.deposit);
+			accounts.insert(info.account);
+		}
+		let reserved = accounts
+			.iter()
+			.fold(BalanceOf::<T>::zero(), |total, account| {
+				total.saturating_add(OldCurrency::reserved_balance(account))
+			});
+
+		Ok((deposits, reserved, OldCurrency::total_issuance()).encode())
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn post_upgrade(state: Vec<u8>) -> Result<(), sp_runtime::DispatchError> {
+		let (deposits, reserved, issuance): (BalanceOf<T>, BalanceOf<T>, BalanceOf<T>) =
+			Decode::decode(&mut &state[..]).expect("pre_upgrade provides a valid state; qed");
+
+		let mut accounts = BTreeSet::new();
+		for info in MappingWithDeposit::<T>::iter_values() {
+			accounts.insert(info.account);
+		}
+		let (held, reserved_after) = accounts.iter().fold(
+			(BalanceOf::<T>::zero(), BalanceOf::<T>::zero()),
+			|(held, reserved), account| {
+				(
+					held.saturating_add(Pallet::<T>::deposit_held(account)),
+					reserved.saturating_add(OldCurrency::reserved_balance(account)),
+				)
+			},
+		);
+
+		assert_eq!(deposits, held, "every deposit is now held");
+		assert_eq!(
+			reserved, reserved_after,
+			"the reserved balances are unchanged"
+		);
+		assert_eq!(
+			issuance,
+			OldCurrency::total_issuance(),
+			"no funds were minted or burnt"
+		);
+		assert_eq!(StorageVersion::get::<Pallet<T>>(), 2);
+
+		Ok(())
+	}
+}
+
+/// Starts tracking the inactivity of the registrations made before `LastAuthored` existed, as if
+/// they were registered at the block of the upgrade.
+///
+/// Until it runs, `remove_inactive` considers these registrations active. The migration only runs
+/// once, on storage version 2, so it must come after `ReservesToHolds`. Chains that include the
+/// pallet from genesis, such as the template runtime, start on storage version 3 and do not need
+/// it.
+pub struct InitializeLastAuthored<T>(PhantomData<T>);
+
+impl<T> OnRuntimeUpgrade for InitializeLastAuthored<T>
+where
+	T: Config,
+{
+	fn on_runtime_upgrade() -> Weight {
+		if StorageVersion::get::<Pallet<T>>() != 2 {
+			log::info!(target: "InitializeLastAuthored", "not on storage version 2, skipping");
+			return T::DbWeight::get().reads(1);
+		}
+		log::info!(target: "InitializeLastAuthored", "starting migration");
+
+		let now = frame_system::Pallet::<T>::block_number();
+		let mut reads = 2u64;
+		let mut writes = 1u64;
+
+		for nimbus_id in MappingWithDeposit::<T>::iter_keys() {
+			reads += 2;
+			if !LastAuthored::<T>::contains_key(&nimbus_id) {
+				writes += 1;
+				LastAuthored::<T>::insert(&nimbus_id, now);
+			}
+		}
+
+		StorageVersion::new(3).put::<Pallet<T>>();
+
+		T::DbWeight::get().reads_writes(reads, writes)
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn post_upgrade(_state: Vec<u8>) -> Result<(), sp_runtime::DispatchError> {
+		for nimbus_id in MappingWithDeposit::<T>::iter_keys() {
+			assert!(
+				LastAuthored::<T>::contains_key(&nimbus_id),
+				"every registration is tracked"
+			);
+		}
+		assert_eq!(StorageVersion::get::<Pallet<T>>(), 3);
+
+		Ok(())
+	}
+}
@@ -17,12 +17,12 @@
 //! A minimal runtime including the author-mapping pallet
 use crate as pallet_author_mapping;
 use frame_support::{construct_runtime, parameter_types, traits::Everything, weights::Weight};
-use nimbus_primitives::NimbusId;
+use nimbus_primitives::{NimbusId, NimbusPair, NimbusSignature};
 use parity_scale_codec::{Decode, Encode};
 use scale_info::TypeInfo;
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
-use sp_core::{ByteArray, H256};
+use sp_core::{Pair, H256};
 use sp_io;
 use sp_runtime::{
 	traits::{BlakeTwo256, IdentityLookup},
@@ -41,14 +41,14 @@ impl Default for TestAuthor {
 		TestAuthor::Alice
 	}
 }
+impl TestAuthor {
+	pub(crate) fn pair(self) -> NimbusPair {
+		NimbusPair::from_seed(&[self as u8; 32])
+	}
+}
 impl Into<NimbusId> for TestAuthor {
 	fn into(self) -> NimbusId {
-		match self {
-			Self::Alice => NimbusId::from_slice(&[0u8; 32]),
-			Self::Bob => NimbusId::from_slice(&[1u8; 32]),
-			Self::Charlie => NimbusId::from_slice(&[2u8; 32]),
-		}
-		.expect("valid ids")
+		self.pair().public()
 	}
 }
 
@@ -63,7 +63,7 @@ construct_runtime!(
 	{
 		System: frame_system::{Pallet, Call, Config<T>, Storage, Event<T>},
 		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
-		AuthorMapping: pallet_author_mapping::{Pallet, Call, Storage, Config<T>, Event<T>},
+		AuthorMapping: pallet_author_mapping::{Pallet, Call, Storage, Config<T>, Event<T>, HoldReason},
 	}
 );
 
@@ -111,21 +111,32 @@ impl pallet_balances::Config for Runtime {
 	type ExistentialDeposit = ExistentialDeposit;
 	type AccountStore = System;
 	type WeightInfo = ();
-	type RuntimeHoldReason = ();
+	type RuntimeHoldReason = RuntimeHoldReason;
 	type FreezeIdentifier = ();
-	type MaxHolds = ();
+	type MaxHolds = frame_support::traits::ConstU32<1>;
 	type MaxFreezes = ();
 	type RuntimeFreezeReason = ();
 }
 
 parameter_types! {
-	pub const DepositAmount: Balance = 100;
+	pub storage DepositAmount: Balance = 100;
+	pub const InactivityReward: sp_runtime::Percent = sp_runtime::Percent::from_percent(10);
+	pub storage Session: Option<u32> = Some(0);
 }
 impl pallet_author_mapping::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
+	type RuntimeHoldReason = RuntimeHoldReason;
 	type DepositCurrency = Balances;
 	type DepositAmount = DepositAmount;
 	type Keys = NimbusId;
+	type KeysOwnership = pallet_author_mapping::AppKeyOwnership;
+	type MaxKeysPerAccount = frame_support::traits::ConstU32<2>;
+	type CurrentSession = Session;
+	type MaxScheduledRotations = frame_support::traits::ConstU32<2>;
+	type ForceOrigin = frame_system::EnsureRoot<AccountId>;
+	type Slashed = ();
+	type InactivityPeriod = frame_support::traits::ConstU64<10>;
+	type InactivityReward = InactivityReward;
 	type WeightInfo = ();
 }
 
@@ -136,6 +147,8 @@ pub(crate) struct ExtBuilder {
 	balances: Vec<(AccountId, Balance)>,
 	/// AuthorId -> AccoutId mappings
 	mappings: Vec<(NimbusId, AccountId)>,
+	/// AuthorId -> (AccountId, Keys) mappings, and whether they take a deposit
+	mappings_with_keys: Vec<(NimbusId, AccountId, NimbusId, bool)>,
 }
 
 impl Default for ExtBuilder {
@@ -143,6 +156,7 @@ impl Default for ExtBuilder {
 		ExtBuilder {
 			balances: vec![],
 			mappings: vec![],
+			mappings_with_keys: vec![],
 		}
 	}
 }
@@ -158,6 +172,14 @@ impl ExtBuilder {
 		self
 	}
 
+	pub(crate) fn with_mappings_with_keys(
+		mut self,
+		mappings_with_keys: Vec<(NimbusId, AccountId, NimbusId, bool)>,
+	) -> Self {
+		self.mappings_with_keys = mappings_with_keys;
+		self
+	}
+
 	pub(crate) fn build(self) -> sp_io::TestExternalities {
 		let mut t = frame_system::GenesisConfig::<Runtime>::default()
 			.build_storage()
@@ -171,6 +193,7 @@ impl ExtBuilder {
 
 		pallet_author_mapping::GenesisConfig::<Runtime> {
 			mappings: self.mappings,
+			mappings_with_keys: self.mappings_with_keys,
 		}
 		.assimilate_storage(&mut t)
 		.expect("Pallet author mapping's storage can be assimilated");
@@ -181,6 +204,27 @@ impl ExtBuilder {
 	}
 }
 
+/// Run the hooks of the author mapping pallet up to the given block
+pub(crate) fn roll_to(n: u64) {
+	use frame_support::traits::Hooks;
+	while System::block_number() < n {
+		System::set_block_number(System::block_number() + 1);
+		AuthorMapping::on_initialize(System::block_number());
+	}
+}
+
+/// Sign the ownership payload of the account, at its current nonce, with the key of the author
+pub(crate) fn proof(author: TestAuthor, account: AccountId) -> NimbusSignature {
+	let nonce = AuthorMapping::ownership_nonce(account);
+	author
+		.pair()
+		.sign(&pallet_author_mapping::ownership_payload(
+			&System::block_hash(0u64),
+			&account,
+			nonce,
+		))
+}
+
 pub(crate) fn last_event() -> RuntimeEvent {
 	System::events().pop().expect("Event expected").event
 }
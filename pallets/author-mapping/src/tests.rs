@@ -16,11 +16,18 @@
 
 //! Unit testing
 use crate::mock::{
-	last_event, AuthorMapping, Balances, ExtBuilder, Runtime, RuntimeEvent as MetaEvent,
-	RuntimeOrigin, System, TestAuthor,
+	last_event, proof, roll_to, AuthorMapping, Balances, DepositAmount, ExtBuilder, Runtime,
+	RuntimeEvent as MetaEvent, RuntimeOrigin, Session, System, TestAuthor,
+};
+use crate::{
+	keys_size, keys_wrapper, DepositUpdateCursor, Error, Event, RotationTime, RotationsDue,
 };
-use crate::{keys_size, keys_wrapper, Error, Event};
 use frame_support::{assert_noop, assert_ok};
+use sp_core::{Pair, H256};
+
+fn nimbus_id(author: TestAuthor) -> nimbus_primitives::NimbusId {
+	author.into()
+}
 
 #[test]
 fn check_key_size() {
@@ -38,6 +45,7 @@ fn genesis_builder_works() {
 			assert!(System::events().is_empty());
 			assert_eq!(Balances::free_balance(&1), 900);
 			assert_eq!(Balances::reserved_balance(&1), 100);
+			assert_eq!(AuthorMapping::deposit_held(&1), 100);
 			assert_eq!(
 				AuthorMapping::account_id_of(&TestAuthor::Alice.into()),
 				Some(1)
@@ -46,6 +54,57 @@ fn genesis_builder_works() {
 		})
 }
 
+#[test]
+fn genesis_builder_registers_explicit_keys() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000), (2, 1000)])
+		.with_mappings_with_keys(vec![
+			(TestAuthor::Alice.into(), 1, TestAuthor::Bob.into(), true),
+			(
+				TestAuthor::Charlie.into(),
+				2,
+				TestAuthor::Alice.into(),
+				false,
+			),
+		])
+		.build()
+		.execute_with(|| {
+			assert_eq!(
+				AuthorMapping::keys_of(&TestAuthor::Alice.into()),
+				Some(TestAuthor::Bob.into())
+			);
+			assert_eq!(AuthorMapping::deposit_held(&1), 100);
+			assert_eq!(
+				AuthorMapping::keys_of(&TestAuthor::Charlie.into()),
+				Some(TestAuthor::Alice.into())
+			);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Charlie.into()),
+				Some(2)
+			);
+			assert_eq!(AuthorMapping::deposit_held(&2), 0);
+			assert_eq!(Balances::free_balance(&2), 1000);
+		})
+}
+
+#[test]
+fn genesis_builder_registers_without_deposit_for_unfunded_accounts() {
+	ExtBuilder::default()
+		.with_mappings_with_keys(vec![(
+			TestAuthor::Alice.into(),
+			1,
+			TestAuthor::Alice.into(),
+			false,
+		)])
+		.build()
+		.execute_with(|| {
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Alice.into()),
+				Some(1)
+			);
+		})
+}
+
 #[test]
 fn eligible_account_can_register() {
 	ExtBuilder::default()
@@ -54,7 +113,8 @@ fn eligible_account_can_register() {
 		.execute_with(|| {
 			assert_ok!(AuthorMapping::add_association(
 				RuntimeOrigin::signed(2),
-				TestAuthor::Bob.into()
+				TestAuthor::Bob.into(),
+				proof(TestAuthor::Bob, 2)
 			));
 
 			assert_eq!(Balances::free_balance(&2), 900);
@@ -82,7 +142,11 @@ fn cannot_add_association_without_deposit() {
 		.build()
 		.execute_with(|| {
 			assert_noop!(
-				AuthorMapping::add_association(RuntimeOrigin::signed(2), TestAuthor::Alice.into()),
+				AuthorMapping::add_association(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Alice.into(),
+					proof(TestAuthor::Alice, 2)
+				),
 				Error::<Runtime>::CannotAffordSecurityDeposit
 			);
 
@@ -103,7 +167,8 @@ fn double_registration_costs_twice_as_much() {
 			// Register once as Bob
 			assert_ok!(AuthorMapping::add_association(
 				RuntimeOrigin::signed(2),
-				TestAuthor::Bob.into()
+				TestAuthor::Bob.into(),
+				proof(TestAuthor::Bob, 2)
 			));
 
 			assert_eq!(Balances::free_balance(&2), 900);
@@ -125,7 +190,8 @@ fn double_registration_costs_twice_as_much() {
 			// Register again as Alice
 			assert_ok!(AuthorMapping::add_association(
 				RuntimeOrigin::signed(2),
-				TestAuthor::Alice.into()
+				TestAuthor::Alice.into(),
+				proof(TestAuthor::Alice, 2)
 			));
 
 			assert_eq!(Balances::free_balance(&2), 800);
@@ -214,7 +280,11 @@ fn registered_author_cannot_be_overwritten() {
 		.build()
 		.execute_with(|| {
 			assert_noop!(
-				AuthorMapping::add_association(RuntimeOrigin::signed(2), TestAuthor::Alice.into()),
+				AuthorMapping::add_association(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Alice.into(),
+					proof(TestAuthor::Alice, 2)
+				),
 				Error::<Runtime>::AlreadyAssociated
 			);
 		})
@@ -230,7 +300,8 @@ fn registered_can_rotate() {
 			assert_ok!(AuthorMapping::update_association(
 				RuntimeOrigin::signed(2),
 				TestAuthor::Bob.into(),
-				TestAuthor::Charlie.into()
+				TestAuthor::Charlie.into(),
+				proof(TestAuthor::Charlie, 2)
 			));
 
 			assert_eq!(AuthorMapping::account_id_of(&TestAuthor::Bob.into()), None);
@@ -252,7 +323,8 @@ fn unregistered_author_cannot_be_rotated() {
 			AuthorMapping::update_association(
 				RuntimeOrigin::signed(2),
 				TestAuthor::Alice.into(),
-				TestAuthor::Bob.into()
+				TestAuthor::Bob.into(),
+				proof(TestAuthor::Bob, 2)
 			),
 			Error::<Runtime>::AssociationNotFound
 		);
@@ -270,7 +342,8 @@ fn registered_author_cannot_be_rotated_by_non_owner() {
 				AuthorMapping::update_association(
 					RuntimeOrigin::signed(2),
 					TestAuthor::Alice.into(),
-					TestAuthor::Bob.into()
+					TestAuthor::Bob.into(),
+					proof(TestAuthor::Bob, 2)
 				),
 				Error::<Runtime>::NotYourAssociation
 			);
@@ -287,7 +360,8 @@ fn rotating_to_the_same_nimbus_id_leaves_registration_in_tact() {
 			assert_ok!(AuthorMapping::update_association(
 				RuntimeOrigin::signed(1),
 				TestAuthor::Alice.into(),
-				TestAuthor::Alice.into()
+				TestAuthor::Alice.into(),
+				proof(TestAuthor::Alice, 1)
 			));
 		})
 }
@@ -301,6 +375,8 @@ fn eligible_account_can_full_register() {
 			assert_ok!(AuthorMapping::set_keys(
 				RuntimeOrigin::signed(2),
 				keys_wrapper::<Runtime>(TestAuthor::Bob.into(), TestAuthor::Alice.into()),
+				proof(TestAuthor::Bob, 2),
+				proof(TestAuthor::Alice, 2)
 			));
 
 			assert_eq!(Balances::free_balance(&2), 900);
@@ -331,6 +407,8 @@ fn cannot_set_keys_without_deposit() {
 				AuthorMapping::set_keys(
 					RuntimeOrigin::signed(2),
 					keys_wrapper::<Runtime>(TestAuthor::Alice.into(), TestAuthor::Bob.into()),
+					proof(TestAuthor::Alice, 2),
+					proof(TestAuthor::Bob, 2)
 				),
 				Error::<Runtime>::CannotAffordSecurityDeposit
 			);
@@ -340,6 +418,48 @@ fn cannot_set_keys_without_deposit() {
 		})
 }
 
+#[test]
+fn set_keys_rejects_input_longer_than_the_encoded_keys() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.build()
+		.execute_with(|| {
+			let mut keys =
+				keys_wrapper::<Runtime>(TestAuthor::Alice.into(), TestAuthor::Bob.into());
+			keys.push(0);
+			assert_noop!(
+				AuthorMapping::set_keys(
+					RuntimeOrigin::signed(2),
+					keys,
+					proof(TestAuthor::Alice, 2),
+					proof(TestAuthor::Bob, 2)
+				),
+				Error::<Runtime>::WrongKeySize
+			);
+		})
+}
+
+#[test]
+fn set_keys_rejects_truncated_keys() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.build()
+		.execute_with(|| {
+			let mut keys =
+				keys_wrapper::<Runtime>(TestAuthor::Alice.into(), TestAuthor::Bob.into());
+			keys.pop();
+			assert_noop!(
+				AuthorMapping::set_keys(
+					RuntimeOrigin::signed(2),
+					keys,
+					proof(TestAuthor::Alice, 2),
+					proof(TestAuthor::Bob, 2)
+				),
+				Error::<Runtime>::DecodeKeysFailed
+			);
+		})
+}
+
 #[test]
 fn full_registered_author_cannot_be_overwritten() {
 	ExtBuilder::default()
@@ -351,6 +471,8 @@ fn full_registered_author_cannot_be_overwritten() {
 				AuthorMapping::set_keys(
 					RuntimeOrigin::signed(2),
 					keys_wrapper::<Runtime>(TestAuthor::Alice.into(), TestAuthor::Bob.into()),
+					proof(TestAuthor::Alice, 2),
+					proof(TestAuthor::Bob, 2)
 				),
 				Error::<Runtime>::AlreadyAssociated
 			);
@@ -366,7 +488,9 @@ fn registered_can_full_rotate() {
 		.execute_with(|| {
 			assert_ok!(AuthorMapping::set_keys(
 				RuntimeOrigin::signed(2),
-				keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into())
+				keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into()),
+				proof(TestAuthor::Charlie, 2),
+				proof(TestAuthor::Charlie, 2)
 			));
 
 			assert_eq!(AuthorMapping::account_id_of(&TestAuthor::Bob.into()), None);
@@ -394,6 +518,8 @@ fn unregistered_author_can_be_full_rotated() {
 			assert_ok!(AuthorMapping::set_keys(
 				RuntimeOrigin::signed(2),
 				keys_wrapper::<Runtime>(TestAuthor::Bob.into(), TestAuthor::Bob.into()),
+				proof(TestAuthor::Bob, 2),
+				proof(TestAuthor::Bob, 2)
 			));
 		})
 }
@@ -408,7 +534,9 @@ fn registered_author_cannot_be_full_rotated_by_non_owner() {
 			assert_noop!(
 				AuthorMapping::set_keys(
 					RuntimeOrigin::signed(2),
-					keys_wrapper::<Runtime>(TestAuthor::Alice.into(), TestAuthor::Bob.into())
+					keys_wrapper::<Runtime>(TestAuthor::Alice.into(), TestAuthor::Bob.into()),
+					proof(TestAuthor::Alice, 2),
+					proof(TestAuthor::Bob, 2)
 				),
 				Error::<Runtime>::AlreadyAssociated
 			);
@@ -424,7 +552,1343 @@ fn full_rotating_to_the_same_nimbus_id_leaves_registration_in_tact() {
 		.execute_with(|| {
 			assert_ok!(AuthorMapping::set_keys(
 				RuntimeOrigin::signed(1),
-				keys_wrapper::<Runtime>(TestAuthor::Alice.into(), TestAuthor::Alice.into())
+				keys_wrapper::<Runtime>(TestAuthor::Alice.into(), TestAuthor::Alice.into()),
+				proof(TestAuthor::Alice, 1),
+				proof(TestAuthor::Alice, 1)
+			));
+		})
+}
+
+#[test]
+fn account_can_register_several_keys() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 2)])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::add_keys(
+				RuntimeOrigin::signed(2),
+				keys_wrapper::<Runtime>(TestAuthor::Bob.into(), TestAuthor::Charlie.into()),
+				proof(TestAuthor::Bob, 2),
+				proof(TestAuthor::Charlie, 2)
+			));
+
+			assert_eq!(
+				last_event(),
+				MetaEvent::AuthorMapping(Event::KeysRegistered {
+					nimbus_id: TestAuthor::Bob.into(),
+					account_id: 2,
+					keys: TestAuthor::Charlie.into(),
+				})
+			);
+			assert_eq!(Balances::reserved_balance(&2), 200);
+			assert_eq!(
+				AuthorMapping::nimbus_ids_of(&2),
+				vec![TestAuthor::Alice.into(), TestAuthor::Bob.into()]
+			);
+			assert_eq!(
+				AuthorMapping::nimbus_id_of(&2),
+				Some(TestAuthor::Alice.into())
+			);
+			assert_eq!(
+				AuthorMapping::keys_of(&TestAuthor::Bob.into()),
+				Some(TestAuthor::Charlie.into())
+			);
+		})
+}
+
+#[test]
+fn cannot_register_more_than_max_keys_per_account() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![
+			(TestAuthor::Alice.into(), 2),
+			(TestAuthor::Bob.into(), 2),
+		])
+		.build()
+		.execute_with(|| {
+			assert_noop!(
+				AuthorMapping::add_association(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Charlie.into(),
+					proof(TestAuthor::Charlie, 2)
+				),
+				Error::<Runtime>::TooManyKeys
+			);
+			assert_noop!(
+				AuthorMapping::add_keys(
+					RuntimeOrigin::signed(2),
+					keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into()),
+					proof(TestAuthor::Charlie, 2),
+					proof(TestAuthor::Charlie, 2)
+				),
+				Error::<Runtime>::TooManyKeys
+			);
+		})
+}
+
+#[test]
+fn clearing_one_key_keeps_the_others() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![
+			(TestAuthor::Alice.into(), 2),
+			(TestAuthor::Bob.into(), 2),
+		])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::clear_association(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Alice.into()
+			));
+
+			assert_eq!(Balances::reserved_balance(&2), 100);
+			assert_eq!(
+				AuthorMapping::nimbus_ids_of(&2),
+				vec![TestAuthor::Bob.into()]
+			);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Bob.into()),
+				Some(2)
+			);
+		})
+}
+
+#[test]
+fn remove_keys_removes_every_key_of_the_account() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![
+			(TestAuthor::Alice.into(), 2),
+			(TestAuthor::Bob.into(), 2),
+		])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::remove_keys(RuntimeOrigin::signed(2)));
+
+			assert_eq!(Balances::free_balance(&2), 1000);
+			assert_eq!(Balances::reserved_balance(&2), 0);
+			assert!(AuthorMapping::nimbus_ids_of(&2).is_empty());
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Alice.into()),
+				None
+			);
+			assert_eq!(AuthorMapping::account_id_of(&TestAuthor::Bob.into()), None);
+
+			assert_eq!(
+				last_event(),
+				MetaEvent::AuthorMapping(Event::KeysRemoved {
+					nimbus_id: TestAuthor::Bob.into(),
+					account_id: 2,
+					keys: TestAuthor::Bob.into(),
+				})
+			);
+		})
+}
+
+#[test]
+fn rotating_a_key_keeps_its_position() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![
+			(TestAuthor::Alice.into(), 2),
+			(TestAuthor::Bob.into(), 2),
+		])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::update_association(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Alice.into(),
+				TestAuthor::Charlie.into(),
+				proof(TestAuthor::Charlie, 2)
+			));
+
+			assert_eq!(
+				AuthorMapping::nimbus_ids_of(&2),
+				vec![TestAuthor::Charlie.into(), TestAuthor::Bob.into()]
+			);
+			assert_eq!(Balances::reserved_balance(&2), 200);
+		})
+}
+
+#[test]
+fn set_keys_updates_the_keys_of_a_registered_nimbus_id() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![
+			(TestAuthor::Alice.into(), 2),
+			(TestAuthor::Bob.into(), 2),
+		])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::set_keys(
+				RuntimeOrigin::signed(2),
+				keys_wrapper::<Runtime>(TestAuthor::Bob.into(), TestAuthor::Charlie.into()),
+				proof(TestAuthor::Bob, 2),
+				proof(TestAuthor::Charlie, 2)
+			));
+
+			assert_eq!(
+				AuthorMapping::nimbus_ids_of(&2),
+				vec![TestAuthor::Alice.into(), TestAuthor::Bob.into()]
+			);
+			assert_eq!(
+				AuthorMapping::keys_of(&TestAuthor::Bob.into()),
+				Some(TestAuthor::Charlie.into())
+			);
+			assert_eq!(
+				AuthorMapping::keys_of(&TestAuthor::Alice.into()),
+				Some(TestAuthor::Alice.into())
+			);
+		})
+}
+
+#[test]
+fn migration_keeps_the_previous_key_first_and_restores_the_others() {
+	use crate::migrations::MultipleKeysPerAccount;
+	use crate::NimbusLookup;
+	use frame_support::{
+		storage::unhashed,
+		traits::{OnRuntimeUpgrade, StorageVersion},
+	};
+	use nimbus_primitives::NimbusId;
+
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![
+			(TestAuthor::Alice.into(), 2),
+			(TestAuthor::Bob.into(), 2),
+		])
+		.build()
+		.execute_with(|| {
+			// Before the migration, the last registration overwrote the reverse mapping
+			let old_value: NimbusId = TestAuthor::Bob.into();
+			unhashed::put(&NimbusLookup::<Runtime>::hashed_key_for(&2), &old_value);
+			StorageVersion::new(0).put::<AuthorMapping>();
+
+			MultipleKeysPerAccount::<Runtime>::on_runtime_upgrade();
+			// Running it again must not translate the new values
+			MultipleKeysPerAccount::<Runtime>::on_runtime_upgrade();
+
+			assert_eq!(
+				AuthorMapping::nimbus_ids_of(&2),
+				vec![TestAuthor::Bob.into(), TestAuthor::Alice.into()]
+			);
+			assert_eq!(StorageVersion::get::<AuthorMapping>(), 1);
+		})
+}
+
+#[test]
+fn migration_moves_reserved_deposits_to_holds() {
+	use crate::migrations::ReservesToHolds;
+	use crate::HoldReason;
+	use frame_support::traits::{
+		fungible::MutateHold, tokens::Precision, OnRuntimeUpgrade, ReservableCurrency,
+		StorageVersion,
+	};
+
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000), (2, 1000)])
+		.with_mappings(vec![
+			(TestAuthor::Alice.into(), 1),
+			(TestAuthor::Bob.into(), 2),
+			(TestAuthor::Charlie.into(), 2),
+		])
+		.build()
+		.execute_with(|| {
+			// Before the migration, the deposits were anonymous reserves
+			for (account, deposit) in [(1, 100), (2, 200)] {
+				assert_ok!(Balances::release(
+					&HoldReason::AuthorMappingDeposit.into(),
+					&account,
+					deposit,
+					Precision::Exact
+				));
+				assert_ok!(Balances::reserve(&account, deposit));
+			}
+			StorageVersion::new(1).put::<AuthorMapping>();
+			assert_eq!(AuthorMapping::deposit_held(&2), 0);
+
+			ReservesToHolds::<Runtime, Balances>::on_runtime_upgrade();
+			// Running it again must not move the deposits twice
+			ReservesToHolds::<Runtime, Balances>::on_runtime_upgrade();
+
+			assert_eq!(AuthorMapping::deposit_held(&1), 100);
+			assert_eq!(AuthorMapping::deposit_held(&2), 200);
+			assert_eq!(Balances::reserved_balance(&2), 200);
+			assert_eq!(Balances::free_balance(&2), 800);
+			assert_eq!(Balances::total_issuance(), 2000);
+			assert_eq!(StorageVersion::get::<AuthorMapping>(), 2);
+
+			assert_ok!(AuthorMapping::remove_keys(RuntimeOrigin::signed(2)));
+			assert_eq!(Balances::reserved_balance(&2), 0);
+			assert_eq!(Balances::free_balance(&2), 1000);
+		})
+}
+
+#[test]
+fn scheduled_rotation_takes_effect_at_the_given_block() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![(TestAuthor::Bob.into(), 2)])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::schedule_keys_rotation(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Alice.into()),
+				RotationTime::Block(5),
+				proof(TestAuthor::Charlie, 2),
+				proof(TestAuthor::Alice, 2)
 			));
+			assert_eq!(
+				last_event(),
+				MetaEvent::AuthorMapping(Event::KeysRotationScheduled {
+					nimbus_id: TestAuthor::Bob.into(),
+					new_nimbus_id: TestAuthor::Charlie.into(),
+					account_id: 2,
+					new_keys: TestAuthor::Alice.into(),
+					at: RotationTime::Block(5),
+				})
+			);
+
+			// The old NimbusId stays valid until the rotation takes effect
+			roll_to(4);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Bob.into()),
+				Some(2)
+			);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Charlie.into()),
+				None
+			);
+
+			roll_to(5);
+			assert_eq!(AuthorMapping::account_id_of(&TestAuthor::Bob.into()), None);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Charlie.into()),
+				Some(2)
+			);
+			assert_eq!(
+				AuthorMapping::keys_of(&TestAuthor::Charlie.into()),
+				Some(TestAuthor::Alice.into())
+			);
+			assert_eq!(
+				last_event(),
+				MetaEvent::AuthorMapping(Event::KeysRotated {
+					new_nimbus_id: TestAuthor::Charlie.into(),
+					account_id: 2,
+					new_keys: TestAuthor::Alice.into(),
+				})
+			);
+			assert!(AuthorMapping::scheduled_rotation(nimbus_id(TestAuthor::Bob)).is_none());
+			assert_eq!(Balances::reserved_balance(&2), 100);
+		})
+}
+
+#[test]
+fn scheduled_rotation_takes_effect_at_the_given_session() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![(TestAuthor::Bob.into(), 2)])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::schedule_keys_rotation(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into()),
+				RotationTime::Session(1),
+				proof(TestAuthor::Charlie, 2),
+				proof(TestAuthor::Charlie, 2)
+			));
+
+			roll_to(10);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Bob.into()),
+				Some(2)
+			);
+
+			Session::set(&Some(1));
+			roll_to(11);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Charlie.into()),
+				Some(2)
+			);
+			assert_eq!(
+				AuthorMapping::nimbus_ids_of(&2),
+				vec![TestAuthor::Charlie.into()]
+			);
+		})
+}
+
+#[test]
+fn skipped_sessions_do_not_lose_scheduled_rotations() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![(TestAuthor::Bob.into(), 2)])
+		.build()
+		.execute_with(|| {
+			roll_to(2);
+			assert_ok!(AuthorMapping::schedule_keys_rotation(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into()),
+				RotationTime::Session(1),
+				proof(TestAuthor::Charlie, 2),
+				proof(TestAuthor::Charlie, 2)
+			));
+
+			Session::set(&Some(3));
+			roll_to(3);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Charlie.into()),
+				Some(2)
+			);
+		})
+}
+
+#[test]
+fn skipped_sessions_are_caught_up_one_per_block() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![(TestAuthor::Bob.into(), 2)])
+		.build()
+		.execute_with(|| {
+			roll_to(2);
+			assert_ok!(AuthorMapping::schedule_keys_rotation(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into()),
+				RotationTime::Session(2),
+				proof(TestAuthor::Charlie, 2),
+				proof(TestAuthor::Charlie, 2)
+			));
+
+			Session::set(&Some(3));
+			roll_to(3);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Bob.into()),
+				Some(2)
+			);
+
+			roll_to(4);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Charlie.into()),
+				Some(2)
+			);
+		})
+}
+
+#[test]
+fn rotation_must_be_scheduled_in_the_future() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![(TestAuthor::Bob.into(), 2)])
+		.build()
+		.execute_with(|| {
+			for at in [RotationTime::Block(1), RotationTime::Session(0)] {
+				assert_noop!(
+					AuthorMapping::schedule_keys_rotation(
+						RuntimeOrigin::signed(2),
+						TestAuthor::Bob.into(),
+						keys_wrapper::<Runtime>(
+							TestAuthor::Charlie.into(),
+							TestAuthor::Charlie.into()
+						),
+						at,
+						proof(TestAuthor::Charlie, 2),
+						proof(TestAuthor::Charlie, 2)
+					),
+					Error::<Runtime>::RotationNotInFuture
+				);
+			}
+		})
+}
+
+#[test]
+fn rotation_cannot_be_scheduled_at_a_session_without_sessions() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![(TestAuthor::Bob.into(), 2)])
+		.build()
+		.execute_with(|| {
+			Session::set(&None);
+
+			assert_noop!(
+				AuthorMapping::schedule_keys_rotation(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Bob.into(),
+					keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into()),
+					RotationTime::Session(1),
+					proof(TestAuthor::Charlie, 2),
+					proof(TestAuthor::Charlie, 2)
+				),
+				Error::<Runtime>::SessionsNotSupported
+			);
+		})
+}
+
+#[test]
+fn only_the_owner_can_schedule_a_rotation_once() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000), (2, 1000)])
+		.with_mappings(vec![
+			(TestAuthor::Alice.into(), 1),
+			(TestAuthor::Bob.into(), 2),
+		])
+		.build()
+		.execute_with(|| {
+			let rotation =
+				|| keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into());
+			assert_noop!(
+				AuthorMapping::schedule_keys_rotation(
+					RuntimeOrigin::signed(1),
+					TestAuthor::Bob.into(),
+					rotation(),
+					RotationTime::Block(5),
+					proof(TestAuthor::Charlie, 1),
+					proof(TestAuthor::Charlie, 1),
+				),
+				Error::<Runtime>::NotYourAssociation
+			);
+			assert_noop!(
+				AuthorMapping::schedule_keys_rotation(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Bob.into(),
+					keys_wrapper::<Runtime>(TestAuthor::Alice.into(), TestAuthor::Alice.into()),
+					RotationTime::Block(5),
+					proof(TestAuthor::Alice, 2),
+					proof(TestAuthor::Alice, 2)
+				),
+				Error::<Runtime>::AlreadyAssociated
+			);
+
+			assert_ok!(AuthorMapping::schedule_keys_rotation(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				rotation(),
+				RotationTime::Block(5),
+				proof(TestAuthor::Charlie, 2),
+				proof(TestAuthor::Charlie, 2),
+			));
+			assert_noop!(
+				AuthorMapping::schedule_keys_rotation(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Bob.into(),
+					rotation(),
+					RotationTime::Block(6),
+					proof(TestAuthor::Charlie, 2),
+					proof(TestAuthor::Charlie, 2),
+				),
+				Error::<Runtime>::RotationAlreadyScheduled
+			);
+		})
+}
+
+#[test]
+fn too_many_rotations_cannot_be_scheduled_at_once() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000), (2, 1000)])
+		.with_mappings(vec![
+			(TestAuthor::Alice.into(), 1),
+			(TestAuthor::Bob.into(), 1),
+			(TestAuthor::Charlie.into(), 2),
+		])
+		.build()
+		.execute_with(|| {
+			for (account, nimbus_id) in [(1, TestAuthor::Alice), (1, TestAuthor::Bob)] {
+				assert_ok!(AuthorMapping::schedule_keys_rotation(
+					RuntimeOrigin::signed(account),
+					nimbus_id.into(),
+					keys_wrapper::<Runtime>(nimbus_id.into(), TestAuthor::Charlie.into()),
+					RotationTime::Block(5),
+					proof(nimbus_id, account),
+					proof(TestAuthor::Charlie, account),
+				));
+			}
+			assert_noop!(
+				AuthorMapping::schedule_keys_rotation(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Charlie.into(),
+					keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Alice.into()),
+					RotationTime::Block(5),
+					proof(TestAuthor::Charlie, 2),
+					proof(TestAuthor::Alice, 2)
+				),
+				Error::<Runtime>::TooManyScheduledRotations
+			);
+		})
+}
+
+#[test]
+fn cancelled_rotation_does_not_take_effect() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![(TestAuthor::Bob.into(), 2)])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::schedule_keys_rotation(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into()),
+				RotationTime::Block(5),
+				proof(TestAuthor::Charlie, 2),
+				proof(TestAuthor::Charlie, 2)
+			));
+			assert_ok!(AuthorMapping::cancel_keys_rotation(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+			));
+			assert_eq!(
+				last_event(),
+				MetaEvent::AuthorMapping(Event::KeysRotationCancelled {
+					nimbus_id: TestAuthor::Bob.into(),
+					account_id: 2,
+				})
+			);
+
+			roll_to(5);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Bob.into()),
+				Some(2)
+			);
+			assert_noop!(
+				AuthorMapping::cancel_keys_rotation(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Bob.into()
+				),
+				Error::<Runtime>::RotationNotFound
+			);
+		})
+}
+
+#[test]
+fn scheduled_rotation_fails_if_the_new_nimbus_id_is_taken() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000), (2, 1000)])
+		.with_mappings(vec![(TestAuthor::Bob.into(), 2)])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::schedule_keys_rotation(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into()),
+				RotationTime::Block(5),
+				proof(TestAuthor::Charlie, 2),
+				proof(TestAuthor::Charlie, 2)
+			));
+			assert_ok!(AuthorMapping::add_association(
+				RuntimeOrigin::signed(1),
+				TestAuthor::Charlie.into(),
+				proof(TestAuthor::Charlie, 1)
+			));
+
+			roll_to(5);
+			assert_eq!(
+				last_event(),
+				MetaEvent::AuthorMapping(Event::KeysRotationFailed {
+					nimbus_id: TestAuthor::Bob.into(),
+					new_nimbus_id: TestAuthor::Charlie.into(),
+					account_id: 2,
+				})
+			);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Bob.into()),
+				Some(2)
+			);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Charlie.into()),
+				Some(1)
+			);
+		})
+}
+
+#[test]
+fn clearing_a_nimbus_id_drops_its_scheduled_rotation() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![(TestAuthor::Bob.into(), 2)])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::schedule_keys_rotation(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into()),
+				RotationTime::Block(5),
+				proof(TestAuthor::Charlie, 2),
+				proof(TestAuthor::Charlie, 2)
+			));
+			assert_ok!(AuthorMapping::remove_keys(RuntimeOrigin::signed(2)));
+
+			roll_to(5);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Charlie.into()),
+				None
+			);
+			assert!(AuthorMapping::scheduled_rotation(nimbus_id(TestAuthor::Bob)).is_none());
+		})
+}
+
+#[test]
+fn rotation_of_a_removed_nimbus_id_is_no_longer_due() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![(TestAuthor::Bob.into(), 2)])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::schedule_keys_rotation(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				keys_wrapper::<Runtime>(TestAuthor::Charlie.into(), TestAuthor::Charlie.into()),
+				RotationTime::Block(5),
+				proof(TestAuthor::Charlie, 2),
+				proof(TestAuthor::Charlie, 2)
+			));
+			assert_ok!(AuthorMapping::remove_keys(RuntimeOrigin::signed(2)));
+			assert!(RotationsDue::<Runtime>::get(RotationTime::Block(5)).is_empty());
+
+			// The NimbusId is registered again, with a later rotation
+			assert_ok!(AuthorMapping::add_association(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				proof(TestAuthor::Bob, 2)
+			));
+			assert_ok!(AuthorMapping::schedule_keys_rotation(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				keys_wrapper::<Runtime>(TestAuthor::Alice.into(), TestAuthor::Alice.into()),
+				RotationTime::Block(8),
+				proof(TestAuthor::Alice, 2),
+				proof(TestAuthor::Alice, 2)
+			));
+
+			roll_to(5);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Bob.into()),
+				Some(2)
+			);
+
+			roll_to(8);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Alice.into()),
+				Some(2)
+			);
+			assert_eq!(AuthorMapping::account_id_of(&TestAuthor::Bob.into()), None);
+		})
+}
+
+#[test]
+fn registration_requires_a_proof_by_the_nimbus_key() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.build()
+		.execute_with(|| {
+			assert_noop!(
+				AuthorMapping::add_association(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Bob.into(),
+					proof(TestAuthor::Alice, 2)
+				),
+				Error::<Runtime>::InvalidNimbusProof
+			);
+			// The proof is only valid for the account that it was made for
+			assert_noop!(
+				AuthorMapping::add_association(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Bob.into(),
+					proof(TestAuthor::Bob, 1)
+				),
+				Error::<Runtime>::InvalidNimbusProof
+			);
+			// and on the chain that it was made for
+			let other_chain_payload = crate::ownership_payload(&H256::repeat_byte(1), &2u64, 0);
+			assert_noop!(
+				AuthorMapping::add_association(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Bob.into(),
+					TestAuthor::Bob.pair().sign(&other_chain_payload)
+				),
+				Error::<Runtime>::InvalidNimbusProof
+			);
+			assert_noop!(
+				AuthorMapping::set_keys(
+					RuntimeOrigin::signed(2),
+					keys_wrapper::<Runtime>(TestAuthor::Bob.into(), TestAuthor::Alice.into()),
+					proof(TestAuthor::Charlie, 2),
+					proof(TestAuthor::Alice, 2),
+				),
+				Error::<Runtime>::InvalidNimbusProof
+			);
+		})
+}
+
+#[test]
+fn registration_requires_a_proof_by_the_keys() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.build()
+		.execute_with(|| {
+			assert_noop!(
+				AuthorMapping::set_keys(
+					RuntimeOrigin::signed(2),
+					keys_wrapper::<Runtime>(TestAuthor::Bob.into(), TestAuthor::Alice.into()),
+					proof(TestAuthor::Bob, 2),
+					proof(TestAuthor::Bob, 2),
+				),
+				Error::<Runtime>::InvalidKeysProof
+			);
+			assert_noop!(
+				AuthorMapping::add_keys(
+					RuntimeOrigin::signed(2),
+					keys_wrapper::<Runtime>(TestAuthor::Bob.into(), TestAuthor::Alice.into()),
+					proof(TestAuthor::Bob, 2),
+					proof(TestAuthor::Charlie, 2),
+				),
+				Error::<Runtime>::InvalidKeysProof
+			);
+		})
+}
+
+#[test]
+fn ownership_proof_cannot_be_replayed() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.build()
+		.execute_with(|| {
+			let bob_proof = proof(TestAuthor::Bob, 2);
+			assert_ok!(AuthorMapping::add_association(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				bob_proof.clone()
+			));
+			assert_eq!(AuthorMapping::ownership_nonce(2), 1);
+			assert_ok!(AuthorMapping::clear_association(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into()
+			));
+
+			assert_noop!(
+				AuthorMapping::add_association(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Bob.into(),
+					bob_proof
+				),
+				Error::<Runtime>::InvalidNimbusProof
+			);
+			assert_ok!(AuthorMapping::add_association(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				proof(TestAuthor::Bob, 2)
+			));
+		})
+}
+
+#[test]
+fn failed_registration_does_not_use_the_nonce() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 10)])
+		.build()
+		.execute_with(|| {
+			assert_noop!(
+				AuthorMapping::add_association(
+					RuntimeOrigin::signed(2),
+					TestAuthor::Bob.into(),
+					proof(TestAuthor::Bob, 2)
+				),
+				Error::<Runtime>::CannotAffordSecurityDeposit
+			);
+			assert_eq!(AuthorMapping::ownership_nonce(2), 0);
+		})
+}
+
+#[test]
+fn force_origin_can_register_without_deposit() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			AuthorMapping::force_add_association(
+				RuntimeOrigin::signed(1),
+				TestAuthor::Alice.into(),
+				1,
+				TestAuthor::Bob.into()
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+
+		assert_ok!(AuthorMapping::force_add_association(
+			RuntimeOrigin::root(),
+			TestAuthor::Alice.into(),
+			1,
+			TestAuthor::Bob.into()
+		));
+
+		assert_eq!(
+			last_event(),
+			MetaEvent::AuthorMapping(Event::KeysForceRegistered {
+				nimbus_id: TestAuthor::Alice.into(),
+				account_id: 1,
+				keys: TestAuthor::Bob.into(),
+			})
+		);
+		assert_eq!(
+			AuthorMapping::account_id_of(&TestAuthor::Alice.into()),
+			Some(1)
+		);
+		assert_eq!(
+			AuthorMapping::keys_of(&TestAuthor::Alice.into()),
+			Some(TestAuthor::Bob.into())
+		);
+		assert_eq!(
+			AuthorMapping::nimbus_ids_of(&1),
+			vec![TestAuthor::Alice.into()]
+		);
+
+		// The account can clear it, there is just no deposit to return
+		assert_ok!(AuthorMapping::remove_keys(RuntimeOrigin::signed(1)));
+	})
+}
+
+#[test]
+fn force_origin_cannot_overwrite_a_mapping() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 1)])
+		.build()
+		.execute_with(|| {
+			assert_noop!(
+				AuthorMapping::force_add_association(
+					RuntimeOrigin::root(),
+					TestAuthor::Alice.into(),
+					2,
+					TestAuthor::Alice.into()
+				),
+				Error::<Runtime>::AlreadyAssociated
+			);
+		})
+}
+
+#[test]
+fn force_origin_can_remove_and_return_deposit() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 1)])
+		.build()
+		.execute_with(|| {
+			assert_noop!(
+				AuthorMapping::force_remove_association(
+					RuntimeOrigin::signed(1),
+					TestAuthor::Alice.into(),
+					false
+				),
+				sp_runtime::DispatchError::BadOrigin
+			);
+
+			assert_ok!(AuthorMapping::force_remove_association(
+				RuntimeOrigin::root(),
+				TestAuthor::Alice.into(),
+				false
+			));
+
+			assert_eq!(
+				last_event(),
+				MetaEvent::AuthorMapping(Event::KeysForceRemoved {
+					nimbus_id: TestAuthor::Alice.into(),
+					account_id: 1,
+					keys: TestAuthor::Alice.into(),
+					slashed: 0,
+				})
+			);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Alice.into()),
+				None
+			);
+			assert!(AuthorMapping::nimbus_ids_of(&1).is_empty());
+			assert_eq!(Balances::free_balance(&1), 1000);
+			assert_eq!(Balances::reserved_balance(&1), 0);
+		})
+}
+
+#[test]
+fn force_origin_can_remove_and_slash_deposit() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 1)])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::force_remove_association(
+				RuntimeOrigin::root(),
+				TestAuthor::Alice.into(),
+				true
+			));
+
+			assert_eq!(
+				last_event(),
+				MetaEvent::AuthorMapping(Event::KeysForceRemoved {
+					nimbus_id: TestAuthor::Alice.into(),
+					account_id: 1,
+					keys: TestAuthor::Alice.into(),
+					slashed: 100,
+				})
+			);
+			assert_eq!(Balances::free_balance(&1), 900);
+			assert_eq!(Balances::reserved_balance(&1), 0);
+			assert_eq!(Balances::total_issuance(), 900);
+		})
+}
+
+#[test]
+fn force_removing_an_unknown_mapping_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			AuthorMapping::force_remove_association(
+				RuntimeOrigin::root(),
+				TestAuthor::Alice.into(),
+				true
+			),
+			Error::<Runtime>::AssociationNotFound
+		);
+	})
+}
+
+#[test]
+fn update_deposits_holds_the_increase() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 1)])
+		.build()
+		.execute_with(|| {
+			DepositAmount::set(&150);
+
+			assert_ok!(AuthorMapping::update_deposits(RuntimeOrigin::signed(3), 10));
+
+			assert_eq!(
+				last_event(),
+				MetaEvent::AuthorMapping(Event::DepositUpdated {
+					nimbus_id: TestAuthor::Alice.into(),
+					account_id: 1,
+					old_deposit: 100,
+					new_deposit: 150,
+				})
+			);
+			assert_eq!(AuthorMapping::deposit_held(&1), 150);
+			assert_eq!(Balances::free_balance(&1), 850);
+			assert_eq!(DepositUpdateCursor::<Runtime>::get(), None);
+
+			assert_ok!(AuthorMapping::remove_keys(RuntimeOrigin::signed(1)));
+			assert_eq!(Balances::free_balance(&1), 1000);
+		})
+}
+
+#[test]
+fn update_deposits_releases_the_excess() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 1)])
+		.build()
+		.execute_with(|| {
+			DepositAmount::set(&40);
+
+			assert_ok!(AuthorMapping::update_deposits(RuntimeOrigin::signed(3), 10));
+
+			assert_eq!(AuthorMapping::deposit_held(&1), 40);
+			assert_eq!(Balances::free_balance(&1), 960);
+			assert_eq!(
+				AuthorMapping::account_and_deposit_of(nimbus_id(TestAuthor::Alice))
+					.map(|info| info.deposit),
+				Some(40)
+			);
+		})
+}
+
+#[test]
+fn update_deposits_keeps_the_old_deposit_if_the_increase_is_unaffordable() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 120)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 1)])
+		.build()
+		.execute_with(|| {
+			DepositAmount::set(&150);
+
+			assert_ok!(AuthorMapping::update_deposits(RuntimeOrigin::signed(3), 10));
+
+			assert_eq!(
+				last_event(),
+				MetaEvent::AuthorMapping(Event::DepositUpdateFailed {
+					nimbus_id: TestAuthor::Alice.into(),
+					account_id: 1,
+					deposit: 100,
+				})
+			);
+			assert_eq!(AuthorMapping::deposit_held(&1), 100);
+			assert_eq!(
+				AuthorMapping::account_and_deposit_of(nimbus_id(TestAuthor::Alice))
+					.map(|info| info.deposit),
+				Some(100)
+			);
+		})
+}
+
+#[test]
+fn update_deposits_continues_where_the_previous_call_stopped() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000), (2, 1000)])
+		.with_mappings(vec![
+			(TestAuthor::Alice.into(), 1),
+			(TestAuthor::Bob.into(), 2),
+			(TestAuthor::Charlie.into(), 2),
+		])
+		.build()
+		.execute_with(|| {
+			DepositAmount::set(&150);
+
+			assert_ok!(AuthorMapping::update_deposits(RuntimeOrigin::signed(3), 2));
+			assert!(DepositUpdateCursor::<Runtime>::get().is_some());
+			assert_eq!(
+				AuthorMapping::deposit_held(&1) + AuthorMapping::deposit_held(&2),
+				400
+			);
+			// A call that visits nothing would lose the cursor
+			assert_noop!(
+				AuthorMapping::update_deposits(RuntimeOrigin::signed(3), 0),
+				Error::<Runtime>::ZeroLimit
+			);
+
+			assert_ok!(AuthorMapping::update_deposits(RuntimeOrigin::signed(3), 2));
+			assert_eq!(DepositUpdateCursor::<Runtime>::get(), None);
+			assert_eq!(AuthorMapping::deposit_held(&1), 150);
+			assert_eq!(AuthorMapping::deposit_held(&2), 300);
+		})
+}
+
+#[test]
+fn update_deposits_leaves_force_registered_mappings_untouched() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.build()
+		.execute_with(|| {
+			assert_ok!(AuthorMapping::force_add_association(
+				RuntimeOrigin::root(),
+				TestAuthor::Alice.into(),
+				1,
+				TestAuthor::Alice.into()
+			));
+			DepositAmount::set(&150);
+
+			assert_ok!(AuthorMapping::update_deposits(RuntimeOrigin::signed(3), 10));
+
+			assert_eq!(AuthorMapping::deposit_held(&1), 0);
+			assert_eq!(Balances::free_balance(&1), 1000);
+		})
+}
+
+/// Put the NimbusId of the block author in the digest, as the author-inherent pallet expects
+fn set_block_author(author: TestAuthor) {
+	use nimbus_primitives::NIMBUS_ENGINE_ID;
+	use parity_scale_codec::Encode;
+	use sp_runtime::DigestItem;
+
+	System::deposit_log(DigestItem::PreRuntime(
+		NIMBUS_ENGINE_ID,
+		nimbus_id(author).encode(),
+	));
+}
+
+#[test]
+fn note_author_records_the_block_of_the_nimbus_id() {
+	use nimbus_primitives::EventHandler;
+
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 1)])
+		.build()
+		.execute_with(|| {
+			assert_eq!(
+				AuthorMapping::last_authored(nimbus_id(TestAuthor::Alice)),
+				Some(0)
+			);
+
+			System::set_block_number(5);
+			set_block_author(TestAuthor::Alice);
+			AuthorMapping::note_author(1);
+
+			assert_eq!(
+				AuthorMapping::last_authored(nimbus_id(TestAuthor::Alice)),
+				Some(5)
+			);
+		})
+}
+
+#[test]
+fn note_author_ignores_a_nimbus_id_of_another_account() {
+	use nimbus_primitives::EventHandler;
+
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 1)])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(5);
+			set_block_author(TestAuthor::Alice);
+			AuthorMapping::note_author(2);
+
+			assert_eq!(
+				AuthorMapping::last_authored(nimbus_id(TestAuthor::Alice)),
+				Some(0)
+			);
+		})
+}
+
+#[test]
+fn inactive_registration_can_be_removed_by_anyone() {
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 1)])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(10);
+			assert_noop!(
+				AuthorMapping::remove_inactive(RuntimeOrigin::signed(3), TestAuthor::Alice.into()),
+				Error::<Runtime>::StillActive
+			);
+
+			System::set_block_number(11);
+			assert_ok!(AuthorMapping::remove_inactive(
+				RuntimeOrigin::signed(3),
+				TestAuthor::Alice.into()
+			));
+
+			assert_eq!(
+				last_event(),
+				MetaEvent::AuthorMapping(Event::KeysExpired {
+					nimbus_id: TestAuthor::Alice.into(),
+					account_id: 1,
+					keys: TestAuthor::Alice.into(),
+					reward: 10,
+				})
+			);
+			assert_eq!(
+				AuthorMapping::account_id_of(&TestAuthor::Alice.into()),
+				None
+			);
+			assert_eq!(
+				AuthorMapping::last_authored(nimbus_id(TestAuthor::Alice)),
+				None
+			);
+			assert_eq!(Balances::free_balance(&1), 990);
+			assert_eq!(Balances::reserved_balance(&1), 0);
+			assert_eq!(Balances::free_balance(&3), 10);
+		})
+}
+
+#[test]
+fn authoring_postpones_the_expiry() {
+	use nimbus_primitives::EventHandler;
+
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 1)])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(8);
+			set_block_author(TestAuthor::Alice);
+			AuthorMapping::note_author(1);
+
+			System::set_block_number(11);
+			assert_noop!(
+				AuthorMapping::remove_inactive(RuntimeOrigin::signed(3), TestAuthor::Alice.into()),
+				Error::<Runtime>::StillActive
+			);
+		})
+}
+
+#[test]
+fn standby_key_of_an_authoring_account_is_active() {
+	use nimbus_primitives::EventHandler;
+
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.with_mappings(vec![
+			(TestAuthor::Alice.into(), 1),
+			(TestAuthor::Bob.into(), 1),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(8);
+			set_block_author(TestAuthor::Alice);
+			AuthorMapping::note_author(1);
+
+			// Bob never authored, but Alice is registered by the same account
+			System::set_block_number(11);
+			assert_noop!(
+				AuthorMapping::remove_inactive(RuntimeOrigin::signed(3), TestAuthor::Bob.into()),
+				Error::<Runtime>::StillActive
+			);
+
+			System::set_block_number(19);
+			assert_ok!(AuthorMapping::remove_inactive(
+				RuntimeOrigin::signed(3),
+				TestAuthor::Bob.into()
+			));
+			assert_eq!(
+				AuthorMapping::nimbus_ids_of(&1),
+				vec![nimbus_id(TestAuthor::Alice)]
+			);
+		})
+}
+
+#[test]
+fn rotation_counts_as_activity() {
+	ExtBuilder::default()
+		.with_balances(vec![(2, 1000)])
+		.with_mappings(vec![(TestAuthor::Bob.into(), 2)])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(8);
+			assert_ok!(AuthorMapping::update_association(
+				RuntimeOrigin::signed(2),
+				TestAuthor::Bob.into(),
+				TestAuthor::Charlie.into(),
+				proof(TestAuthor::Charlie, 2)
+			));
+
+			assert_eq!(
+				AuthorMapping::last_authored(nimbus_id(TestAuthor::Bob)),
+				None
+			);
+			assert_eq!(
+				AuthorMapping::last_authored(nimbus_id(TestAuthor::Charlie)),
+				Some(8)
+			);
+		})
+}
+
+#[test]
+fn migration_starts_tracking_existing_registrations() {
+	use crate::migrations::InitializeLastAuthored;
+	use crate::LastAuthored;
+	use frame_support::traits::{OnRuntimeUpgrade, StorageVersion};
+
+	ExtBuilder::default()
+		.with_balances(vec![(1, 1000)])
+		.with_mappings(vec![(TestAuthor::Alice.into(), 1)])
+		.build()
+		.execute_with(|| {
+			LastAuthored::<Runtime>::remove(nimbus_id(TestAuthor::Alice));
+			StorageVersion::new(2).put::<AuthorMapping>();
+			System::set_block_number(7);
+
+			InitializeLastAuthored::<Runtime>::on_runtime_upgrade();
+			assert_eq!(
+				AuthorMapping::last_authored(nimbus_id(TestAuthor::Alice)),
+				Some(7)
+			);
+			assert_eq!(StorageVersion::get::<AuthorMapping>(), 3);
+
+			// Running it again must not reset the tracking
+			System::set_block_number(9);
+			InitializeLastAuthored::<Runtime>::on_runtime_upgrade();
+			assert_eq!(
+				AuthorMapping::last_authored(nimbus_id(TestAuthor::Alice)),
+				Some(7)
+			);
 		})
 }
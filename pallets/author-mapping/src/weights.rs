@@ -15,33 +15,17 @@
 // along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
 
 
-//! Autogenerated weights for pallet_author_mapping
+//! Weights for pallet_author_mapping
 //!
-//! THIS FILE WAS AUTO-GENERATED USING THE SUBSTRATE BENCHMARK CLI VERSION 4.0.0-dev
-//! DATE: 2023-04-28, STEPS: `50`, REPEAT: `20`, LOW RANGE: `[]`, HIGH RANGE: `[]`
-//! WORST CASE MAP SIZE: `1000000`
-//! HOSTNAME: `benchmarker`, CPU: `Intel(R) Core(TM) i7-7700K CPU @ 4.20GHz`
-//! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: None, DB CACHE: 1024
-
-// Executed Command:
-// ./target/release/moonbeam
-// benchmark
-// pallet
-// --execution=wasm
-// --wasm-execution=compiled
-// --pallet
-// *
-// --extrinsic
-// *
-// --steps
-// 50
-// --repeat
-// 20
-// --template=./benchmarking/frame-weight-template.hbs
-// --json-file
-// raw.json
-// --output
-// weights/
+//! These weights have not been measured by the benchmark CLI since the pallet gained ownership
+//! proofs, holds, multiple keys per account and scheduled rotations. They are estimates that
+//! follow the storage accesses of the benchmarks in `benchmarks.rs`, as noted on each entry, and
+//! must be replaced by the output of the benchmark CLI, run against a runtime that includes the
+//! pallet, such as the template runtime:
+//!
+//! ./target/release/<node> benchmark pallet --execution=wasm --wasm-execution=compiled
+//! --pallet pallet_author_mapping --extrinsic '*' --steps 50 --repeat 20
+//! --template=./benchmarking/frame-weight-template.hbs --output weights.rs
 
 #![cfg_attr(rustfmt, rustfmt_skip)]
 #![allow(unused_parens)]
@@ -55,157 +39,238 @@ pub trait WeightInfo {
 	fn add_association() -> Weight;
 	fn update_association() -> Weight;
 	fn clear_association() -> Weight;
-	fn remove_keys() -> Weight;
+	fn remove_keys(x: u32, ) -> Weight;
 	fn set_keys() -> Weight;
+	fn add_keys() -> Weight;
+	fn schedule_keys_rotation() -> Weight;
+	fn cancel_keys_rotation() -> Weight;
+	fn enact_scheduled_rotations(x: u32, ) -> Weight;
+	fn force_add_association() -> Weight;
+	fn force_remove_association() -> Weight;
+	fn update_deposits(x: u32, ) -> Weight;
+	fn remove_inactive(x: u32, ) -> Weight;
+	fn note_author() -> Weight;
 }
 
 /// Weights for pallet_author_mapping using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
-	/// Storage: AuthorMapping MappingWithDeposit (r:1 w:1)
-	/// Proof Skipped: AuthorMapping MappingWithDeposit (max_values: None, max_size: None, mode: Measured)
-	/// Storage: System Account (r:1 w:1)
-	/// Proof: System Account (max_values: None, max_size: Some(116), added: 2591, mode: MaxEncodedLen)
-	/// Storage: AuthorMapping NimbusLookup (r:0 w:1)
-	/// Proof Skipped: AuthorMapping NimbusLookup (max_values: None, max_size: None, mode: Measured)
+	/// Not measured: verifies the ownership proof of the NimbusId and holds its deposit.
 	fn add_association() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `376`
-		//  Estimated: `7798`
-		// Minimum execution time: 39_301_000 picoseconds.
-		Weight::from_parts(39_892_000, 7798)
-			.saturating_add(T::DbWeight::get().reads(2_u64))
-			.saturating_add(T::DbWeight::get().writes(3_u64))
+		Weight::from_parts(92_892_000, 10263)
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().writes(6_u64))
 	}
-	/// Storage: AuthorMapping MappingWithDeposit (r:2 w:2)
-	/// Proof Skipped: AuthorMapping MappingWithDeposit (max_values: None, max_size: None, mode: Measured)
-	/// Storage: AuthorMapping NimbusLookup (r:0 w:1)
-	/// Proof Skipped: AuthorMapping NimbusLookup (max_values: None, max_size: None, mode: Measured)
+	/// Not measured: verifies the ownership proof of the new NimbusId and moves the registration to it.
 	fn update_association() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `325`
-		//  Estimated: `6590`
-		// Minimum execution time: 30_021_000 picoseconds.
-		Weight::from_parts(30_445_000, 6590)
-			.saturating_add(T::DbWeight::get().reads(2_u64))
-			.saturating_add(T::DbWeight::get().writes(3_u64))
+		Weight::from_parts(77_445_000, 7733)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(6_u64))
 	}
-	/// Storage: AuthorMapping MappingWithDeposit (r:1 w:1)
-	/// Proof Skipped: AuthorMapping MappingWithDeposit (max_values: None, max_size: None, mode: Measured)
-	/// Storage: System Account (r:1 w:1)
-	/// Proof: System Account (max_values: None, max_size: Some(116), added: 2591, mode: MaxEncodedLen)
-	/// Storage: AuthorMapping NimbusLookup (r:0 w:1)
-	/// Proof Skipped: AuthorMapping NimbusLookup (max_values: None, max_size: None, mode: Measured)
+	/// Not measured: removes the registration and releases its deposit.
 	fn clear_association() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `453`
-		//  Estimated: `7952`
-		// Minimum execution time: 40_393_000 picoseconds.
-		Weight::from_parts(40_798_000, 7952)
-			.saturating_add(T::DbWeight::get().reads(2_u64))
-			.saturating_add(T::DbWeight::get().writes(3_u64))
+		Weight::from_parts(46_798_000, 7736)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(5_u64))
 	}
-	/// Storage: AuthorMapping NimbusLookup (r:1 w:1)
-	/// Proof Skipped: AuthorMapping NimbusLookup (max_values: None, max_size: None, mode: Measured)
-	/// Storage: AuthorMapping MappingWithDeposit (r:1 w:1)
-	/// Proof Skipped: AuthorMapping MappingWithDeposit (max_values: None, max_size: None, mode: Measured)
-	/// Storage: System Account (r:1 w:1)
-	/// Proof: System Account (max_values: None, max_size: Some(116), added: 2591, mode: MaxEncodedLen)
-	fn remove_keys() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `547`
-		//  Estimated: `11605`
-		// Minimum execution time: 44_302_000 picoseconds.
-		Weight::from_parts(44_830_000, 11605)
+	/// Not measured: releases the deposit and removes the registration of each of the `x` NimbusIds
+	/// of the account.
+	/// The range of component `x` is `[1, 8]`.
+	fn remove_keys(x: u32, ) -> Weight {
+		Weight::from_parts(36_125_000, 7913)
+			.saturating_add(Weight::from_parts(14_705_000, 0).saturating_mul(x.into()))
 			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(x.into())))
 			.saturating_add(T::DbWeight::get().writes(3_u64))
+			.saturating_add(T::DbWeight::get().writes((2_u64).saturating_mul(x.into())))
+			.saturating_add(Weight::from_parts(0, 2603).saturating_mul(x.into()))
 	}
-	/// Storage: AuthorMapping NimbusLookup (r:1 w:1)
-	/// Proof Skipped: AuthorMapping NimbusLookup (max_values: None, max_size: None, mode: Measured)
-	/// Storage: AuthorMapping MappingWithDeposit (r:2 w:2)
-	/// Proof Skipped: AuthorMapping MappingWithDeposit (max_values: None, max_size: None, mode: Measured)
+	/// Not measured: decodes an input of the maximum size, verifies both ownership proofs and
+	/// replaces the first NimbusId of the account.
 	fn set_keys() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `419`
-		//  Estimated: `10243`
-		// Minimum execution time: 33_636_000 picoseconds.
-		Weight::from_parts(34_125_000, 10243)
+		Weight::from_parts(128_125_000, 10513)
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().writes(6_u64))
+	}
+	/// Not measured: verifies both ownership proofs and holds a deposit for the additional NimbusId.
+	fn add_keys() -> Weight {
+		Weight::from_parts(141_602_000, 13043)
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().writes(6_u64))
+	}
+	/// Not measured: verifies both ownership proofs and adds the NimbusId to the rotations due.
+	fn schedule_keys_rotation() -> Weight {
+		Weight::from_parts(125_755_000, 13326)
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	/// Not measured: removes the NimbusId from the rotations due.
+	fn cancel_keys_rotation() -> Weight {
+		Weight::from_parts(27_903_000, 8196)
 			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Not measured: replaces the registration of each of the `x` NimbusIds whose rotation is due.
+	/// The range of component `x` is `[0, 16]`.
+	fn enact_scheduled_rotations(x: u32, ) -> Weight {
+		Weight::from_parts(7_305_000, 6501)
+			.saturating_add(Weight::from_parts(28_614_000, 0).saturating_mul(x.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().reads((4_u64).saturating_mul(x.into())))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+			.saturating_add(T::DbWeight::get().writes((6_u64).saturating_mul(x.into())))
+			.saturating_add(Weight::from_parts(0, 10578).saturating_mul(x.into()))
+	}
+	/// Not measured: registers the NimbusId without a proof or a deposit.
+	fn force_add_association() -> Weight {
+		Weight::from_parts(15_702_000, 5383)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
 			.saturating_add(T::DbWeight::get().writes(3_u64))
 	}
+	/// Not measured: removes the registration and slashes or releases its deposit.
+	fn force_remove_association() -> Weight {
+		Weight::from_parts(49_410_000, 10516)
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().writes(6_u64))
+	}
+	/// Not measured: holds or releases the difference with the current deposit for each of the `x`
+	/// registrations visited.
+	/// The range of component `x` is `[1, 100]`.
+	fn update_deposits(x: u32, ) -> Weight {
+		Weight::from_parts(12_348_000, 3130)
+			.saturating_add(Weight::from_parts(29_486_000, 0).saturating_mul(x.into()))
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().reads((3_u64).saturating_mul(x.into())))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+			.saturating_add(T::DbWeight::get().writes((3_u64).saturating_mul(x.into())))
+			.saturating_add(Weight::from_parts(0, 7736).saturating_mul(x.into()))
+	}
+	/// Not measured: checks when each of the `x` NimbusIds of the account last authored, removes the
+	/// registration and pays the reward from its deposit before releasing the rest.
+	/// The range of component `x` is `[1, 8]`.
+	fn remove_inactive(x: u32, ) -> Weight {
+		Weight::from_parts(70_131_000, 13107)
+			.saturating_add(Weight::from_parts(2_117_000, 0).saturating_mul(x.into()))
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(x.into())))
+			.saturating_add(T::DbWeight::get().writes(7_u64))
+			.saturating_add(Weight::from_parts(0, 2527).saturating_mul(x.into()))
+	}
+	/// Not measured: reads the NimbusId from the pre-runtime digest and records the block.
+	fn note_author() -> Weight {
+		Weight::from_parts(14_182_000, 4206)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
-	/// Storage: AuthorMapping MappingWithDeposit (r:1 w:1)
-	/// Proof Skipped: AuthorMapping MappingWithDeposit (max_values: None, max_size: None, mode: Measured)
-	/// Storage: System Account (r:1 w:1)
-	/// Proof: System Account (max_values: None, max_size: Some(116), added: 2591, mode: MaxEncodedLen)
-	/// Storage: AuthorMapping NimbusLookup (r:0 w:1)
-	/// Proof Skipped: AuthorMapping NimbusLookup (max_values: None, max_size: None, mode: Measured)
+	/// Not measured: verifies the ownership proof of the NimbusId and holds its deposit.
 	fn add_association() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `376`
-		//  Estimated: `7798`
-		// Minimum execution time: 39_301_000 picoseconds.
-		Weight::from_parts(39_892_000, 7798)
-			.saturating_add(RocksDbWeight::get().reads(2_u64))
-			.saturating_add(RocksDbWeight::get().writes(3_u64))
+		Weight::from_parts(92_892_000, 10263)
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
 	}
-	/// Storage: AuthorMapping MappingWithDeposit (r:2 w:2)
-	/// Proof Skipped: AuthorMapping MappingWithDeposit (max_values: None, max_size: None, mode: Measured)
-	/// Storage: AuthorMapping NimbusLookup (r:0 w:1)
-	/// Proof Skipped: AuthorMapping NimbusLookup (max_values: None, max_size: None, mode: Measured)
+	/// Not measured: verifies the ownership proof of the new NimbusId and moves the registration to it.
 	fn update_association() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `325`
-		//  Estimated: `6590`
-		// Minimum execution time: 30_021_000 picoseconds.
-		Weight::from_parts(30_445_000, 6590)
-			.saturating_add(RocksDbWeight::get().reads(2_u64))
-			.saturating_add(RocksDbWeight::get().writes(3_u64))
+		Weight::from_parts(77_445_000, 7733)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
 	}
-	/// Storage: AuthorMapping MappingWithDeposit (r:1 w:1)
-	/// Proof Skipped: AuthorMapping MappingWithDeposit (max_values: None, max_size: None, mode: Measured)
-	/// Storage: System Account (r:1 w:1)
-	/// Proof: System Account (max_values: None, max_size: Some(116), added: 2591, mode: MaxEncodedLen)
-	/// Storage: AuthorMapping NimbusLookup (r:0 w:1)
-	/// Proof Skipped: AuthorMapping NimbusLookup (max_values: None, max_size: None, mode: Measured)
+	/// Not measured: removes the registration and releases its deposit.
 	fn clear_association() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `453`
-		//  Estimated: `7952`
-		// Minimum execution time: 40_393_000 picoseconds.
-		Weight::from_parts(40_798_000, 7952)
-			.saturating_add(RocksDbWeight::get().reads(2_u64))
-			.saturating_add(RocksDbWeight::get().writes(3_u64))
+		Weight::from_parts(46_798_000, 7736)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
 	}
-	/// Storage: AuthorMapping NimbusLookup (r:1 w:1)
-	/// Proof Skipped: AuthorMapping NimbusLookup (max_values: None, max_size: None, mode: Measured)
-	/// Storage: AuthorMapping MappingWithDeposit (r:1 w:1)
-	/// Proof Skipped: AuthorMapping MappingWithDeposit (max_values: None, max_size: None, mode: Measured)
-	/// Storage: System Account (r:1 w:1)
-	/// Proof: System Account (max_values: None, max_size: Some(116), added: 2591, mode: MaxEncodedLen)
-	fn remove_keys() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `547`
-		//  Estimated: `11605`
-		// Minimum execution time: 44_302_000 picoseconds.
-		Weight::from_parts(44_830_000, 11605)
+	/// Not measured: releases the deposit and removes the registration of each of the `x` NimbusIds
+	/// of the account.
+	/// The range of component `x` is `[1, 8]`.
+	fn remove_keys(x: u32, ) -> Weight {
+		Weight::from_parts(36_125_000, 7913)
+			.saturating_add(Weight::from_parts(14_705_000, 0).saturating_mul(x.into()))
 			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(x.into())))
 			.saturating_add(RocksDbWeight::get().writes(3_u64))
+			.saturating_add(RocksDbWeight::get().writes((2_u64).saturating_mul(x.into())))
+			.saturating_add(Weight::from_parts(0, 2603).saturating_mul(x.into()))
 	}
-	/// Storage: AuthorMapping NimbusLookup (r:1 w:1)
-	/// Proof Skipped: AuthorMapping NimbusLookup (max_values: None, max_size: None, mode: Measured)
-	/// Storage: AuthorMapping MappingWithDeposit (r:2 w:2)
-	/// Proof Skipped: AuthorMapping MappingWithDeposit (max_values: None, max_size: None, mode: Measured)
+	/// Not measured: decodes an input of the maximum size, verifies both ownership proofs and
+	/// replaces the first NimbusId of the account.
 	fn set_keys() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `419`
-		//  Estimated: `10243`
-		// Minimum execution time: 33_636_000 picoseconds.
-		Weight::from_parts(34_125_000, 10243)
+		Weight::from_parts(128_125_000, 10513)
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
+	}
+	/// Not measured: verifies both ownership proofs and holds a deposit for the additional NimbusId.
+	fn add_keys() -> Weight {
+		Weight::from_parts(141_602_000, 13043)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
+	}
+	/// Not measured: verifies both ownership proofs and adds the NimbusId to the rotations due.
+	fn schedule_keys_rotation() -> Weight {
+		Weight::from_parts(125_755_000, 13326)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	/// Not measured: removes the NimbusId from the rotations due.
+	fn cancel_keys_rotation() -> Weight {
+		Weight::from_parts(27_903_000, 8196)
 			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Not measured: replaces the registration of each of the `x` NimbusIds whose rotation is due.
+	/// The range of component `x` is `[0, 16]`.
+	fn enact_scheduled_rotations(x: u32, ) -> Weight {
+		Weight::from_parts(7_305_000, 6501)
+			.saturating_add(Weight::from_parts(28_614_000, 0).saturating_mul(x.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((4_u64).saturating_mul(x.into())))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+			.saturating_add(RocksDbWeight::get().writes((6_u64).saturating_mul(x.into())))
+			.saturating_add(Weight::from_parts(0, 10578).saturating_mul(x.into()))
+	}
+	/// Not measured: registers the NimbusId without a proof or a deposit.
+	fn force_add_association() -> Weight {
+		Weight::from_parts(15_702_000, 5383)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
 			.saturating_add(RocksDbWeight::get().writes(3_u64))
 	}
+	/// Not measured: removes the registration and slashes or releases its deposit.
+	fn force_remove_association() -> Weight {
+		Weight::from_parts(49_410_000, 10516)
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
+	}
+	/// Not measured: holds or releases the difference with the current deposit for each of the `x`
+	/// registrations visited.
+	/// The range of component `x` is `[1, 100]`.
+	fn update_deposits(x: u32, ) -> Weight {
+		Weight::from_parts(12_348_000, 3130)
+			.saturating_add(Weight::from_parts(29_486_000, 0).saturating_mul(x.into()))
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().reads((3_u64).saturating_mul(x.into())))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+			.saturating_add(RocksDbWeight::get().writes((3_u64).saturating_mul(x.into())))
+			.saturating_add(Weight::from_parts(0, 7736).saturating_mul(x.into()))
+	}
+	/// Not measured: checks when each of the `x` NimbusIds of the account last authored, removes the
+	/// registration and pays the reward from its deposit before releasing the rest.
+	/// The range of component `x` is `[1, 8]`.
+	fn remove_inactive(x: u32, ) -> Weight {
+		Weight::from_parts(70_131_000, 13107)
+			.saturating_add(Weight::from_parts(2_117_000, 0).saturating_mul(x.into()))
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(x.into())))
+			.saturating_add(RocksDbWeight::get().writes(7_u64))
+			.saturating_add(Weight::from_parts(0, 2527).saturating_mul(x.into()))
+	}
+	/// Not measured: reads the NimbusId from the pre-runtime digest and records the block.
+	fn note_author() -> Weight {
+		Weight::from_parts(14_182_000, 4206)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
 }
\ No newline at end of file
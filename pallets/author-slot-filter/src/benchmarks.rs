@@ -17,16 +17,82 @@
 #![cfg(feature = "runtime-benchmarks")]
 
 use crate::num::NonZeroU32;
-use crate::{Call, Config, Pallet};
-use frame_benchmarking::{benchmarks, impl_benchmark_test_suite};
+use crate::{
+	cached_eligible_authors, compute_pseudo_random_subset, AuthoredBlocks, Call, Config,
+	EligibilityMode, EligibleAuthorsCache, EligibleMode, FairnessStrength, Pallet, RecentAuthors,
+};
+use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite};
+use frame_support::{traits::Get, BoundedVec};
 use frame_system::RawOrigin;
+use nimbus_primitives::EventHandler;
+use sp_runtime::Percent;
+use sp_std::vec::Vec;
 
 benchmarks! {
 	set_eligible {
-		let count = NonZeroU32::new_unchecked(34);
-	}: _(RawOrigin::Root, count.clone())
+		let mode = EligibilityMode::Count(NonZeroU32::new_unchecked(34));
+	}: _(RawOrigin::Root, mode.clone())
 	verify {
-		assert_eq!(Pallet::<T>::eligible_count(), count);
+		assert_eq!(Pallet::<T>::eligible_mode(), mode);
+	}
+
+	// Worst case: as many potential authors as possible are eligible, so that they are drawn
+	compute_pseudo_random_subset {
+		let x in 1..1000;
+		let authors: Vec<T::AccountId> = (0..x).map(|i| account("author", i, 0)).collect();
+		EligibleMode::<T>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(x)));
+		// Fairness makes the filter read the recent blocks of every author, and different blocks
+		// give different weights, so that the weighted selection is used
+		FairnessStrength::<T>::put(Percent::from_percent(50));
+		for (i, author) in authors.iter().enumerate() {
+			AuthoredBlocks::<T>::insert(author, (i % 4) as u32 + 1);
+		}
+	}: {
+		let (eligible, _) = compute_pseudo_random_subset::<T>(authors, &0u32);
+		assert_eq!(eligible.len(), x.min(T::MaxEligibleAuthors::get()) as usize);
+	}
+
+	// Picks x authors out of 1000 authors without fairness. It is only used by runtimes whose
+	// `AuthorWeight` is uniform, for which it does not read anything for each author.
+	compute_uniform_subset {
+		let x in 1..T::MaxEligibleAuthors::get();
+		let authors: Vec<T::AccountId> = (0..1000).map(|i| account("author", i, 0)).collect();
+		EligibleMode::<T>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(x)));
+	}: {
+		let (eligible, _) = compute_pseudo_random_subset::<T>(authors, &0u32);
+		assert_eq!(eligible.len(), x.min(1000) as usize);
+	}
+
+	// The next blocks of a slot read the x eligible authors from the cache
+	cached_eligible_authors {
+		let x in 1..T::MaxEligibleAuthors::get();
+		let authors: Vec<T::AccountId> = (0..x).map(|i| account("author", i, 0)).collect();
+		let authors = BoundedVec::try_from(authors).expect("x is within the bound");
+		EligibleAuthorsCache::<T>::put((0u32, authors));
+	}: {
+		let eligible = cached_eligible_authors::<T>(0u32);
+		assert_eq!(eligible.len(), x as usize);
+	}
+
+	set_fairness_strength {
+		let strength = Percent::from_percent(20);
+	}: _(RawOrigin::Root, strength)
+	verify {
+		assert_eq!(Pallet::<T>::fairness_strength(), strength);
+	}
+
+	// Worst case: the oldest author of the window is replaced
+	note_author {
+		let oldest_author: T::AccountId = account("oldest_author", 0, 0);
+		let author: T::AccountId = account("author", 0, 0);
+		RecentAuthors::<T>::insert(0, &oldest_author);
+		AuthoredBlocks::<T>::insert(&oldest_author, 2);
+	}: {
+		Pallet::<T>::note_author(author.clone());
+	}
+	verify {
+		assert_eq!(Pallet::<T>::authored_blocks(&oldest_author), 1);
+		assert_eq!(Pallet::<T>::authored_blocks(&author), 1);
 	}
 }
 
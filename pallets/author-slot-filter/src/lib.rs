@@ -19,9 +19,29 @@
 //!
 //! Using a randomness beacon supplied by the `Randomness` trait, this pallet takes the set of
 //! currently active accounts from an upstream source, and filters them down to a pseudorandom subset.
-//! The current technique gives no preference to any particular author. In the future, we could
-//! disfavor authors who are authoring a disproportionate amount of the time in an attempt to
-//! "even the playing field".
+//! The size of the subset is given by the `EligibilityMode`, either as a fixed count or as a
+//! percentage of the potential authors, optionally clamped, so that it follows the size of a
+//! changing author set.
+//! Each author is picked with a probability proportional to its weight, as given by the
+//! `AuthorWeight` config type (for instance its stake). With the unit implementation, every author
+//! has the same weight and no preference is given to any particular author.
+//!
+//! Optionally, the filter disfavors authors who are authoring a disproportionate amount of the time
+//! in an attempt to "even the playing field". It counts the blocks of each author over a rolling
+//! window of `FairnessWindow` blocks, and divides the weight of each author by
+//! `1 + FairnessStrength * authored_blocks`. The strength is set by governance and the effect is
+//! disabled when it is zero, which is the default.
+//!
+//! If the selected authors are all offline, the chain would stall until a later slot picks another
+//! subset. To avoid this, the pallet records the slot of each block, and once more than
+//! `UnfilledSlotsTolerance` consecutive slots have passed without a block, the eligible count is
+//! widened by `EligibilityWideningStep` authors for each further slot without a block. The widened
+//! set always contains the authors selected with the regular count, and the regular count applies
+//! again as soon as a block is produced. Only the slot being authored is widened, so the authors
+//! predicted for upcoming slots through `EligibleAuthors` use the regular count.
+//!
+//! The eligible authors are computed once per slot and cached, so that the next blocks of the same
+//! slot keep the same eligible authors, even if the inputs of the selection change in between.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
@@ -35,6 +55,7 @@ mod benchmarks;
 
 pub mod migration;
 pub mod num;
+pub mod selection;
 pub mod weights;
 
 #[cfg(test)]
@@ -42,6 +63,32 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+/// Gives each potential author a weight that is used to pick the eligible authors, so that an
+/// author with twice the weight is twice as likely to be eligible at a given slot.
+pub trait AuthorWeight<AccountId> {
+	/// The weight of this author, for instance its stake. Authors with a zero weight are only
+	/// picked once no author with a positive weight is left. The sum of all the weights is
+	/// expected to fit in a `u128`.
+	fn author_weight(author: &AccountId) -> u128;
+
+	/// Whether every author has the same weight. The uniform selection then picks the eligible
+	/// authors without reading the weight of every potential author.
+	fn is_uniform() -> bool {
+		false
+	}
+}
+
+/// Every author has the same weight, which makes the selection uniform.
+impl<AccountId> AuthorWeight<AccountId> for () {
+	fn author_weight(_author: &AccountId) -> u128 {
+		1
+	}
+
+	fn is_uniform() -> bool {
+		true
+	}
+}
+
 #[allow(deprecated)]
 #[pallet]
 pub mod pallet {
@@ -51,14 +98,18 @@ pub mod pallet {
 	use frame_support::{pallet_prelude::*, traits::Randomness};
 	use frame_system::pallet_prelude::*;
 	use log::debug;
-	use nimbus_primitives::CanAuthor;
+	use nimbus_primitives::{CanAuthor, EligibleAuthors, EventHandler, SlotBeacon};
+	use serde::{Deserialize, Serialize};
 	use sp_core::H256;
 	use sp_runtime::Percent;
 	use sp_std::vec::Vec;
 
+	/// The in-code storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	/// The Author Filter pallet
 	#[pallet::pallet]
-	#[pallet::without_storage_info]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(PhantomData<T>);
 
 	/// Configuration trait of this pallet.
@@ -75,67 +126,203 @@ pub mod pallet {
 		/// A source for the complete set of potential authors.
 		/// The starting point of the filtering.
 		type PotentialAuthors: Get<Vec<Self::AccountId>>;
+		/// The weight of each potential author in the selection.
+		/// Use `()` to give every author the same chance to be eligible.
+		type AuthorWeight: AuthorWeight<Self::AccountId>;
+		/// The number of recent blocks over which the blocks of each author are counted.
+		/// Changing it requires clearing `RecentAuthors` and `AuthoredBlocks`.
+		#[pallet::constant]
+		type FairnessWindow: Get<u32>;
+		/// A way to know the current slot, which is recorded at the end of each block to count the
+		/// slots without a block.
+		type SlotBeacon: SlotBeacon;
+		/// The number of consecutive slots without a block that are tolerated before the eligible
+		/// count is widened. It should cover the slots that are normally skipped between two
+		/// blocks, such as every other relay block when relay block numbers are used as slots.
+		#[pallet::constant]
+		type UnfilledSlotsTolerance: Get<u32>;
+		/// The number of authors added to the eligible count for each further slot without a block.
+		/// Zero disables the widening.
+		#[pallet::constant]
+		type EligibilityWideningStep: Get<u32>;
+		/// The maximum number of authors eligible at a slot, so that they can be cached. Larger
+		/// eligible counts are clamped to it.
+		#[pallet::constant]
+		type MaxEligibleAuthors: Get<u32>;
 		type WeightInfo: WeightInfo;
 	}
 
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_: BlockNumberFor<T>) -> Weight {
+			// on_finalize: 5 reads + 2 writes
+			T::DbWeight::get().reads_writes(5, 2)
+		}
+		fn on_finalize(_: BlockNumberFor<T>) {
+			let slot = T::SlotBeacon::slot();
+			let potential_authors = T::PotentialAuthors::get().len() as u32;
+			let eligible_count = effective_eligible_count::<T>(slot, potential_authors);
+			let widened_count =
+				if eligible_count > EligibleMode::<T>::get().eligible_count(potential_authors) {
+					Some(eligible_count)
+				} else {
+					None
+				};
+
+			if widened_count != WidenedEligibleCount::<T>::get() {
+				WidenedEligibleCount::<T>::set(widened_count);
+				<Pallet<T>>::deposit_event(Event::EffectiveEligibleUpdated(eligible_count));
+			}
+			LastAuthoredSlot::<T>::put(slot);
+		}
+	}
+
+	/// The number of authors that are eligible at this slot among `potential_authors`, when it is
+	/// the slot being authored. It is the count given by the `EligibleMode`, widened by
+	/// `EligibilityWideningStep` for each slot without a block beyond `UnfilledSlotsTolerance`
+	/// since the last block.
+	pub fn effective_eligible_count<T: Config>(slot: u32, potential_authors: u32) -> u32 {
+		let eligible_count = EligibleMode::<T>::get().eligible_count(potential_authors);
+		let unfilled_slots = match LastAuthoredSlot::<T>::get() {
+			Some(last_slot) => slot.saturating_sub(last_slot).saturating_sub(1),
+			None => 0,
+		};
+		let widening_slots = unfilled_slots.saturating_sub(T::UnfilledSlotsTolerance::get());
+
+		eligible_count
+			.saturating_add(widening_slots.saturating_mul(T::EligibilityWideningStep::get()))
+	}
+
 	/// Compute a pseudo-random subset of the input accounts by using Pallet's
-	/// source of randomness, `Config::RandomnessSource`.
+	/// source of randomness, `Config::RandomnessSource`. The seed is the slot being authored,
+	/// which determines the number of eligible authors.
+	/// Accounts are picked without replacement, proportionally to their `Config::AuthorWeight`,
+	/// which is scaled down for recent authors according to `FairnessStrength`. The randomness
+	/// source is queried once per slot, and the accounts are picked with a partial Fisher-Yates
+	/// shuffle. Without fairness and with a uniform `Config::AuthorWeight`, picking `k` accounts
+	/// takes `k` draws and reads nothing for each account.
 	/// Returns (Eligible, Ineligible), each is a set of accounts
 	pub fn compute_pseudo_random_subset<T: Config>(
+		active: Vec<T::AccountId>,
+		seed: &u32,
+	) -> (Vec<T::AccountId>, Vec<T::AccountId>) {
+		let eligible_count = effective_eligible_count::<T>(*seed, active.len() as u32);
+		select_pseudo_random_subset::<T>(active, seed, eligible_count)
+	}
+
+	/// Predicts the pseudo-random subset of the input accounts at an upcoming slot. Unlike
+	/// `compute_pseudo_random_subset`, the eligible count is not widened, since the slots before
+	/// the upcoming one may still get a block. The prediction is included in the authors that are
+	/// eligible once the slot is authored.
+	pub fn predict_pseudo_random_subset<T: Config>(
+		active: Vec<T::AccountId>,
+		seed: &u32,
+	) -> (Vec<T::AccountId>, Vec<T::AccountId>) {
+		let eligible_count = EligibleMode::<T>::get().eligible_count(active.len() as u32);
+		select_pseudo_random_subset::<T>(active, seed, eligible_count)
+	}
+
+	/// Picks `eligible_count` of the input accounts at the slot given by `seed`.
+	fn select_pseudo_random_subset<T: Config>(
 		mut active: Vec<T::AccountId>,
 		seed: &u32,
+		eligible_count: u32,
 	) -> (Vec<T::AccountId>, Vec<T::AccountId>) {
-		let mut num_eligible = EligibleCount::<T>::get().get() as usize;
-		if num_eligible > active.len() {
-			num_eligible = active.len();
-		}
-
-		let mut eligible = Vec::with_capacity(num_eligible);
-
-		for i in 0..num_eligible {
-			// A context identifier for grabbing the randomness. Consists of three parts
-			// 1. Constant string *b"filter" - to identify this pallet
-			// 2. First 2 bytes of index.to_le_bytes when selecting the ith eligible author
-			// 3. First 4 bytes of seed.to_be_bytes
-			let mut first_two_bytes_of_index = &i.to_le_bytes()[..2];
-			let mut first_four_bytes_of_seed = &seed.to_be_bytes()[..4];
-			let mut constant_string: [u8; 6] = [b'f', b'i', b'l', b't', b'e', b'r'];
-			let mut subject: [u8; 12] = [0u8; 12];
-			subject[..6].copy_from_slice(&mut constant_string);
-			subject[6..8].copy_from_slice(&mut first_two_bytes_of_index);
-			subject[8..].copy_from_slice(&mut first_four_bytes_of_seed);
-			let (randomness, _) = T::RandomnessSource::random(&subject);
-			debug!(target: "author-filter", "🎲Randomness sample {}: {:?}", i, &randomness);
-
-			// Cast to u32 first so we get consistent results on 32- and 64-bit platforms.
-			let bytes: [u8; 4] = randomness.to_fixed_bytes()[0..4]
-				.try_into()
-				.expect("H256 has at least 4 bytes; qed");
-			let randomness = u32::from_le_bytes(bytes) as usize;
-
-			// Move the selected author from the original vector into the eligible vector
-			// TODO we could short-circuit this check by returning early when the claimed
-			// author is selected. For now I'll leave it like this because:
-			// 1. it is easier to understand what our core filtering logic is
-			// 2. we currently show the entire filtered set in the debug event
-			eligible.push(active.remove(randomness % active.len()));
-		}
-		(eligible, active)
+		let num_eligible = eligible_count
+			.min(T::MaxEligibleAuthors::get())
+			.min(active.len() as u32) as usize;
+
+		// A context identifier for grabbing the randomness. Consists of two parts
+		// 1. Constant string *b"filter" - to identify this pallet
+		// 2. The 4 bytes of seed.to_be_bytes
+		let mut subject: [u8; 10] = [0u8; 10];
+		subject[..6].copy_from_slice(b"filter");
+		subject[6..].copy_from_slice(&seed.to_be_bytes());
+		let (randomness, _) = T::RandomnessSource::random(&subject);
+		debug!(target: "author-filter", "🎲Randomness seed: {:?}", &randomness);
+
+		let fairness_strength = FairnessStrength::<T>::get();
+		if fairness_strength.is_zero() && T::AuthorWeight::is_uniform() {
+			// Every author has the same chance to be picked, which does not need the weights
+			selection::partial_shuffle(&mut active, num_eligible, &randomness);
+		} else {
+			let weights: Vec<u128> = active
+				.iter()
+				.map(|author| {
+					let weight = T::AuthorWeight::author_weight(author);
+					if fairness_strength.is_zero() {
+						weight
+					} else {
+						fairness_adjusted_weight(
+							weight,
+							AuthoredBlocks::<T>::get(author),
+							fairness_strength,
+						)
+					}
+				})
+				.collect();
+			selection::weighted_partial_shuffle(&mut active, weights, num_eligible, &randomness);
+		}
+
+		let ineligible = active.split_off(num_eligible);
+		(active, ineligible)
+	}
+
+	/// The authors eligible at this slot. They are computed once per slot and cached, so that
+	/// several blocks in the same slot see the same eligible authors.
+	/// This runs from the author inherent, so its weight is registered as mandatory.
+	pub fn cached_eligible_authors<T: Config>(slot: u32) -> Vec<T::AccountId> {
+		if let Some((cached_slot, eligible)) = EligibleAuthorsCache::<T>::get() {
+			if cached_slot == slot {
+				frame_system::Pallet::<T>::register_extra_weight_unchecked(
+					T::WeightInfo::cached_eligible_authors(eligible.len() as u32),
+					DispatchClass::Mandatory,
+				);
+				return eligible.into_inner();
+			}
+		}
+
+		let potential_authors = T::PotentialAuthors::get();
+		let potential_count = potential_authors.len() as u32;
+		let uniform = FairnessStrength::<T>::get().is_zero() && T::AuthorWeight::is_uniform();
+		let (eligible, _) = compute_pseudo_random_subset::<T>(potential_authors, &slot);
+		let selection_weight = if uniform {
+			T::WeightInfo::compute_uniform_subset(eligible.len() as u32)
+		} else {
+			T::WeightInfo::compute_pseudo_random_subset(potential_count)
+		};
+		let eligible = BoundedVec::<_, T::MaxEligibleAuthors>::truncate_from(eligible);
+		EligibleAuthorsCache::<T>::put((slot, eligible.clone()));
+
+		frame_system::Pallet::<T>::register_extra_weight_unchecked(
+			T::WeightInfo::cached_eligible_authors(0)
+				.saturating_add(selection_weight)
+				.saturating_add(T::DbWeight::get().writes(1)),
+			DispatchClass::Mandatory,
+		);
+		eligible.into_inner()
+	}
+
+	/// Scales down the weight of an author who authored `authored_blocks` blocks in the rolling
+	/// window, to `weight / (1 + strength * authored_blocks)`. The result is multiplied by 100, so
+	/// that small weights such as the unit weight are not rounded down to zero.
+	pub fn fairness_adjusted_weight(weight: u128, authored_blocks: u32, strength: Percent) -> u128 {
+		let one = Percent::one().deconstruct() as u128;
+		let penalty = (strength.deconstruct() as u128).saturating_mul(authored_blocks as u128);
+		weight.saturating_mul(one).saturating_mul(one) / one.saturating_add(penalty)
 	}
 
 	// This code will be called by the author-inherent pallet to check whether the reported author
-	// of this block is eligible in this slot. We calculate that result on demand and do not
-	// record it in storage (although we do emit a debugging event for now).
+	// of this block is eligible in this slot. We calculate that result once per slot and cache it
+	// in storage for the next blocks of the same slot.
 	impl<T: Config> CanAuthor<T::AccountId> for Pallet<T> {
 		#[cfg(not(feature = "try-runtime"))]
 		fn can_author(author: &T::AccountId, slot: &u32) -> bool {
-			// Compute pseudo-random subset of potential authors
-			let (eligible, ineligible) =
-				compute_pseudo_random_subset::<T>(T::PotentialAuthors::get(), slot);
+			// Compute pseudo-random subset of potential authors, unless it is cached
+			let eligible = cached_eligible_authors::<T>(*slot);
 
 			// Print some logs for debugging purposes.
 			debug!(target: "author-filter", "Eligible Authors: {:?}", eligible);
-			debug!(target: "author-filter", "Ineligible Authors: {:?}", &ineligible);
 			debug!(target: "author-filter",
 				"Current author, {:?}, is eligible: {}",
 				author,
@@ -152,21 +339,86 @@ pub mod pallet {
 		}
 	}
 
+	// This code will be called by the runtime to predict which authors will be eligible in
+	// upcoming slots. Only the slot being authored is widened for the slots without a block.
+	impl<T: Config> EligibleAuthors<T::AccountId> for Pallet<T> {
+		fn eligible_authors(slot: &u32) -> Vec<T::AccountId> {
+			match EligibleAuthorsCache::<T>::get() {
+				Some((cached_slot, eligible)) if cached_slot == *slot => eligible.into_inner(),
+				_ if *slot == T::SlotBeacon::slot() => {
+					compute_pseudo_random_subset::<T>(T::PotentialAuthors::get(), slot).0
+				}
+				_ => predict_pseudo_random_subset::<T>(T::PotentialAuthors::get(), slot).0,
+			}
+		}
+	}
+
+	// This code will be called by the author-inherent pallet with the author of each block, which
+	// we record in the rolling window used to disfavor recent authors.
+	impl<T: Config> EventHandler<T::AccountId> for Pallet<T> {
+		fn note_author(author: T::AccountId) {
+			let window = T::FairnessWindow::get();
+			if window == 0 {
+				return;
+			}
+
+			// Overwrite the oldest author of the window
+			let index = RecentAuthorsIndex::<T>::get() % window;
+			if let Some(oldest_author) = RecentAuthors::<T>::get(index) {
+				AuthoredBlocks::<T>::mutate_exists(&oldest_author, |count| {
+					*count = count.and_then(|c| c.checked_sub(1)).filter(|c| *c > 0);
+				});
+			}
+			AuthoredBlocks::<T>::mutate(&author, |count| *count = count.saturating_add(1));
+			RecentAuthors::<T>::insert(index, author);
+			RecentAuthorsIndex::<T>::put((index + 1) % window);
+
+			frame_system::Pallet::<T>::register_extra_weight_unchecked(
+				T::WeightInfo::note_author(),
+				DispatchClass::Mandatory,
+			);
+		}
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The minimum of a clamped eligibility ratio is greater than its maximum
+		InvalidEligibilityClamp,
+	}
+
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		/// Update the eligible count. Intended to be called by governance.
+		/// Update how the number of eligible authors is determined. Intended to be called by
+		/// governance.
 		#[pallet::call_index(0)]
 		#[pallet::weight(T::WeightInfo::set_eligible())]
 		pub fn set_eligible(
 			origin: OriginFor<T>,
-			new: EligibilityValue,
+			new: EligibilityMode,
 		) -> DispatchResultWithPostInfo {
 			ensure_root(origin)?;
-			EligibleCount::<T>::put(&new);
+			ensure!(new.is_valid(), Error::<T>::InvalidEligibilityClamp);
+			EligibleMode::<T>::put(&new);
+			EligibleAuthorsCache::<T>::kill();
 			<Pallet<T>>::deposit_event(Event::EligibleUpdated(new));
 
 			Ok(Default::default())
 		}
+
+		/// Update how strongly recent authors are disfavored. Intended to be called by governance.
+		#[pallet::call_index(1)]
+		#[pallet::weight(T::WeightInfo::set_fairness_strength())]
+		pub fn set_fairness_strength(
+			origin: OriginFor<T>,
+			new: Percent,
+		) -> DispatchResultWithPostInfo {
+			ensure_root(origin)?;
+			FairnessStrength::<T>::put(new);
+			EligibleAuthorsCache::<T>::kill();
+			<Pallet<T>>::deposit_event(Event::FairnessStrengthUpdated(new));
+
+			Ok(Default::default())
+		}
 	}
 
 	/// The type of eligibility to use
@@ -179,6 +431,53 @@ pub mod pallet {
 		}
 	}
 
+	/// How the number of eligible authors is derived from the number of potential authors.
+	#[derive(
+		Clone, Debug, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, Serialize, Deserialize,
+	)]
+	pub enum EligibilityMode {
+		/// A fixed number of eligible authors.
+		Count(EligibilityValue),
+		/// A percentage of the potential authors, rounded up. At least one author is eligible.
+		Ratio(Percent),
+		/// A percentage of the potential authors, rounded up and clamped between `min` and `max`.
+		ClampedRatio {
+			ratio: Percent,
+			min: EligibilityValue,
+			max: EligibilityValue,
+		},
+	}
+
+	impl Default for EligibilityMode {
+		fn default() -> Self {
+			EligibilityMode::Count(EligibilityValue::default())
+		}
+	}
+
+	impl EligibilityMode {
+		/// The number of eligible authors among `potential_authors`. It may exceed the number of
+		/// potential authors with a fixed count or a clamp, in which case they are all eligible.
+		/// The maximum of an invalid clamp takes precedence over its minimum.
+		pub fn eligible_count(&self, potential_authors: u32) -> u32 {
+			match self {
+				EligibilityMode::Count(count) => count.get(),
+				EligibilityMode::Ratio(ratio) => ratio.mul_ceil(potential_authors).max(1),
+				EligibilityMode::ClampedRatio { ratio, min, max } => ratio
+					.mul_ceil(potential_authors)
+					.max(min.get())
+					.min(max.get()),
+			}
+		}
+
+		/// Whether the minimum of a clamp is not greater than its maximum.
+		pub fn is_valid(&self) -> bool {
+			match self {
+				EligibilityMode::ClampedRatio { min, max, .. } => min <= max,
+				_ => true,
+			}
+		}
+	}
+
 	#[pallet::storage]
 	#[pallet::getter(fn eligible_ratio)]
 	#[deprecated(note = "use `pallet::EligibleCount` instead")]
@@ -193,6 +492,7 @@ pub mod pallet {
 	/// The number of active authors that will be eligible at each height.
 	#[pallet::storage]
 	#[pallet::getter(fn eligible_count)]
+	#[deprecated(note = "use `pallet::EligibleMode` instead")]
 	pub type EligibleCount<T: Config> =
 		StorageValue<_, EligibilityValue, ValueQuery, DefaultEligibilityValue<T>>;
 
@@ -202,9 +502,50 @@ pub mod pallet {
 		EligibilityValue::default()
 	}
 
+	/// How the number of active authors that will be eligible at each height is determined.
+	#[pallet::storage]
+	#[pallet::getter(fn eligible_mode)]
+	pub type EligibleMode<T: Config> = StorageValue<_, EligibilityMode, ValueQuery>;
+
+	/// How strongly authors are disfavored for each block they authored in the rolling window.
+	/// Zero disables the effect.
+	#[pallet::storage]
+	#[pallet::getter(fn fairness_strength)]
+	pub type FairnessStrength<T: Config> = StorageValue<_, Percent, ValueQuery>;
+
+	/// The number of blocks authored by each author in the rolling window.
+	#[pallet::storage]
+	#[pallet::getter(fn authored_blocks)]
+	pub type AuthoredBlocks<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, u32, ValueQuery>;
+
+	/// The authors of the recent blocks, indexed by their position in the rolling window.
+	#[pallet::storage]
+	pub type RecentAuthors<T: Config> = StorageMap<_, Twox64Concat, u32, T::AccountId, OptionQuery>;
+
+	/// The position in the rolling window of the next block author.
+	#[pallet::storage]
+	pub type RecentAuthorsIndex<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	/// The authors eligible at the slot of the last block, so that they are not computed again by
+	/// the next blocks of the same slot.
+	#[pallet::storage]
+	pub type EligibleAuthorsCache<T: Config> =
+		StorageValue<_, (u32, BoundedVec<T::AccountId, T::MaxEligibleAuthors>), OptionQuery>;
+
+	/// The slot of the last block, used to count the slots without a block.
+	#[pallet::storage]
+	#[pallet::getter(fn last_authored_slot)]
+	pub type LastAuthoredSlot<T: Config> = StorageValue<_, u32, OptionQuery>;
+
+	/// The eligible count used by the last block, if it was widened because of slots without a
+	/// block.
+	#[pallet::storage]
+	#[pallet::getter(fn widened_eligible_count)]
+	pub type WidenedEligibleCount<T: Config> = StorageValue<_, u32, OptionQuery>;
+
 	#[pallet::genesis_config]
 	pub struct GenesisConfig<T: Config> {
-		pub eligible_count: EligibilityValue,
+		pub eligibility_mode: EligibilityMode,
 		#[serde(skip)]
 		pub _config: sp_std::marker::PhantomData<T>,
 	}
@@ -212,7 +553,7 @@ pub mod pallet {
 	impl<T: Config> Default for GenesisConfig<T> {
 		fn default() -> Self {
 			Self {
-				eligible_count: EligibilityValue::default(),
+				eligibility_mode: EligibilityMode::default(),
 				_config: Default::default(),
 			}
 		}
@@ -221,14 +562,24 @@ pub mod pallet {
 	#[pallet::genesis_build]
 	impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
 		fn build(&self) {
-			EligibleCount::<T>::put(self.eligible_count.clone());
+			assert!(
+				self.eligibility_mode.is_valid(),
+				"The minimum of the eligibility clamp must not exceed its maximum"
+			);
+			EligibleMode::<T>::put(self.eligibility_mode.clone());
 		}
 	}
 
 	#[pallet::event]
 	#[pallet::generate_deposit(fn deposit_event)]
 	pub enum Event {
-		/// The amount of eligible authors for the filter to select has been changed.
-		EligibleUpdated(EligibilityValue),
+		/// The way the amount of eligible authors for the filter to select is determined has been
+		/// changed.
+		EligibleUpdated(EligibilityMode),
+		/// The strength with which recent authors are disfavored has been changed.
+		FairnessStrengthUpdated(Percent),
+		/// The number of eligible authors has been widened because of slots without a block, or
+		/// restored to the eligible count once a block was produced.
+		EffectiveEligibleUpdated(u32),
 	}
 }
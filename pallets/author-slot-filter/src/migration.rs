@@ -16,14 +16,17 @@
 
 use core::marker::PhantomData;
 use frame_support::traits::Get;
-use frame_support::traits::OnRuntimeUpgrade;
+use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
 use frame_support::weights::Weight;
 use sp_runtime::Percent;
 
 use super::num::NonZeroU32;
 use super::pallet::Config;
+use super::pallet::EligibilityMode;
 use super::pallet::EligibilityValue;
+#[allow(deprecated)]
 use super::pallet::EligibleCount;
+use super::pallet::EligibleMode;
 use super::pallet::Pallet;
 
 #[cfg(feature = "try-runtime")]
@@ -34,6 +37,7 @@ use {
 
 pub struct EligibleRatioToEligiblityCount<T>(PhantomData<T>);
 
+#[allow(deprecated)]
 impl<T> OnRuntimeUpgrade for EligibleRatioToEligiblityCount<T>
 where
 	T: Config,
@@ -74,6 +78,59 @@ where
 	}
 }
 
+/// Keeps the eligible count of existing chains by moving it into an `EligibilityMode::Count`.
+/// An `EligibleMode` that is already set, for instance by governance, is kept as it is. Runs once,
+/// when upgrading the pallet from storage version 0 to 1.
+pub struct EligibleCountToEligibilityMode<T>(PhantomData<T>);
+
+#[allow(deprecated)]
+impl<T> OnRuntimeUpgrade for EligibleCountToEligibilityMode<T>
+where
+	T: Config,
+{
+	fn on_runtime_upgrade() -> Weight {
+		if Pallet::<T>::on_chain_storage_version() >= 1 {
+			log::info!(target: "EligibleCountToEligibilityMode", "skipping, already migrated");
+			return T::DbWeight::get().reads(1);
+		}
+		log::info!(target: "EligibleCountToEligibilityMode", "starting migration");
+
+		let old_value = <EligibleCount<T>>::take();
+		if !<EligibleMode<T>>::exists() {
+			<EligibleMode<T>>::put(EligibilityMode::Count(old_value));
+		}
+		StorageVersion::new(1).put::<Pallet<T>>();
+
+		T::DbWeight::get().reads_writes(3, 3)
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn pre_upgrade() -> Result<Vec<u8>, sp_runtime::DispatchError> {
+		let expected_value =
+			if Pallet::<T>::on_chain_storage_version() >= 1 || <EligibleMode<T>>::exists() {
+				<Pallet<T>>::eligible_mode()
+			} else {
+				EligibilityMode::Count(<Pallet<T>>::eligible_count())
+			};
+
+		Ok(expected_value.encode())
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn post_upgrade(state: Vec<u8>) -> Result<(), sp_runtime::DispatchError> {
+		let expected: EligibilityMode =
+			Decode::decode(&mut &state[..]).expect("pre_upgrade provides a valid state; qed");
+
+		let actual = <Pallet<T>>::eligible_mode();
+
+		assert_eq!(expected, actual);
+		assert!(!<EligibleCount<T>>::exists());
+		assert_eq!(Pallet::<T>::on_chain_storage_version(), 1);
+
+		Ok(())
+	}
+}
+
 fn percent_of_num(percent: Percent, num: u32) -> u32 {
 	percent.mul_ceil(num as u32)
 }
@@ -16,12 +16,11 @@
 
 use crate as pallet_testing;
 use frame_support::parameter_types;
-use frame_support::traits::ConstU32;
+use frame_support::traits::{ConstU32, Randomness};
 use frame_support::weights::RuntimeDbWeight;
-use frame_support_test::TestRandomness;
 use sp_core::H256;
 use sp_runtime::{
-	traits::{BlakeTwo256, IdentityLookup},
+	traits::{BlakeTwo256, Hash, IdentityLookup},
 	BuildStorage,
 };
 
@@ -38,6 +37,9 @@ frame_support::construct_runtime!(
 parameter_types! {
 	pub const BlockHashCount: u64 = 250;
 	pub Authors: Vec<u64> = vec![1, 2, 3, 4, 5];
+	/// Authors that are not listed have a weight of 1
+	pub storage AuthorStakes: Vec<(u64, u128)> = Vec::new();
+	pub storage CurrentSlot: u32 = 0;
 	pub const TestDbWeight: RuntimeDbWeight = RuntimeDbWeight {
 		read: 1,
 		write: 10,
@@ -70,10 +72,47 @@ impl frame_system::Config for Test {
 	type MaxConsumers = ConstU32<16>;
 }
 
+/// Randomness derived from the hash of the subject, so that every slot and every draw gets a
+/// different value
+pub struct HashedRandomness;
+impl Randomness<H256, u64> for HashedRandomness {
+	fn random(subject: &[u8]) -> (H256, u64) {
+		(BlakeTwo256::hash(subject), 0)
+	}
+}
+
+pub struct StakeWeight;
+impl pallet_testing::AuthorWeight<u64> for StakeWeight {
+	fn author_weight(author: &u64) -> u128 {
+		AuthorStakes::get()
+			.into_iter()
+			.find(|(account, _)| account == author)
+			.map(|(_, stake)| stake)
+			.unwrap_or(1)
+	}
+
+	fn is_uniform() -> bool {
+		AuthorStakes::get().is_empty()
+	}
+}
+
+pub struct DummyBeacon {}
+impl nimbus_primitives::SlotBeacon for DummyBeacon {
+	fn slot() -> u32 {
+		CurrentSlot::get()
+	}
+}
+
 impl pallet_testing::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
-	type RandomnessSource = TestRandomness<Self>;
+	type RandomnessSource = HashedRandomness;
 	type PotentialAuthors = Authors;
+	type AuthorWeight = StakeWeight;
+	type FairnessWindow = ConstU32<4>;
+	type SlotBeacon = DummyBeacon;
+	type UnfilledSlotsTolerance = ConstU32<1>;
+	type EligibilityWideningStep = ConstU32<1>;
+	type MaxEligibleAuthors = ConstU32<100>;
 	type WeightInfo = ();
 }
 
@@ -0,0 +1,204 @@
+// Copyright Moonsong Labs
+// This file is part of Moonkit.
+
+// Moonkit is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Moonkit is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Selection of a random subset of authors with a partial Fisher-Yates shuffle.
+//!
+//! All the random numbers are derived from a single seed, so that the randomness source is only
+//! queried once per selection. Picking `k` authors out of `n` takes `k` draws when every author
+//! has the same weight, and `O(n + k log n)` operations otherwise.
+
+use sp_core::H256;
+use sp_io::hashing::blake2_256;
+use sp_std::vec::Vec;
+
+/// The random number used for the `index`th draw of a selection.
+pub fn draw(seed: &H256, index: u32) -> u128 {
+	let mut subject = [0u8; 36];
+	subject[..32].copy_from_slice(seed.as_bytes());
+	subject[32..].copy_from_slice(&index.to_le_bytes());
+	let bytes: [u8; 16] = blake2_256(&subject)[..16]
+		.try_into()
+		.expect("A blake2_256 hash has 32 bytes; qed");
+	u128::from_le_bytes(bytes)
+}
+
+/// Moves `count` items picked uniformly at the front of `items`, in the order they were picked.
+pub fn partial_shuffle<A>(items: &mut [A], count: usize, seed: &H256) {
+	let len = items.len();
+	for i in 0..count.min(len) {
+		let offset = draw(seed, i as u32) % (len - i) as u128;
+		items.swap(i, i + offset as usize);
+	}
+}
+
+/// Moves `count` items at the front of `items`, in the order they were picked. Each item is picked
+/// with a probability proportional to its weight among the items left. Items without weight are
+/// picked uniformly once no item with a positive weight is left.
+pub fn weighted_partial_shuffle<A>(
+	items: &mut [A],
+	mut weights: Vec<u128>,
+	count: usize,
+	seed: &H256,
+) {
+	let len = items.len();
+	let mut tree = FenwickTree::new(&weights);
+	for i in 0..count.min(len) {
+		let randomness = draw(seed, i as u32);
+		let picked = if tree.total == 0 {
+			i + (randomness % (len - i) as u128) as usize
+		} else {
+			// Items before `i` were already picked and have no weight left in the tree
+			tree.find(randomness % tree.total)
+		};
+
+		// The picked item takes the place of the item at `i`, which moves to the picked position
+		if picked != i {
+			tree.sub(picked, weights[picked]);
+			tree.add(picked, weights[i]);
+		}
+		tree.sub(i, weights[i]);
+		weights.swap(i, picked);
+		weights[i] = 0;
+		items.swap(i, picked);
+	}
+}
+
+/// A Fenwick tree of weights, to pick an item by its cumulative weight in `O(log n)`.
+struct FenwickTree {
+	/// 1-indexed partial sums of the weights
+	sums: Vec<u128>,
+	/// The sum of all the weights
+	total: u128,
+}
+
+impl FenwickTree {
+	fn new(weights: &[u128]) -> Self {
+		let mut sums = Vec::with_capacity(weights.len() + 1);
+		sums.push(0);
+		sums.extend_from_slice(weights);
+		for i in 1..sums.len() {
+			let parent = i + (i & i.wrapping_neg());
+			if parent < sums.len() {
+				sums[parent] = sums[parent].saturating_add(sums[i]);
+			}
+		}
+		let total = weights
+			.iter()
+			.fold(0u128, |total, weight| total.saturating_add(*weight));
+		FenwickTree { sums, total }
+	}
+
+	fn add(&mut self, index: usize, weight: u128) {
+		let mut i = index + 1;
+		while i < self.sums.len() {
+			self.sums[i] = self.sums[i].saturating_add(weight);
+			i += i & i.wrapping_neg();
+		}
+		self.total = self.total.saturating_add(weight);
+	}
+
+	fn sub(&mut self, index: usize, weight: u128) {
+		let mut i = index + 1;
+		while i < self.sums.len() {
+			self.sums[i] = self.sums[i].saturating_sub(weight);
+			i += i & i.wrapping_neg();
+		}
+		self.total = self.total.saturating_sub(weight);
+	}
+
+	/// Returns the index of the item whose cumulative weight range contains `target`.
+	/// `target` must be lower than the total weight.
+	fn find(&self, mut target: u128) -> usize {
+		let len = self.sums.len() - 1;
+		let mut position = 0;
+		let mut step = if len == 0 {
+			0
+		} else {
+			1 << (usize::BITS - 1 - len.leading_zeros())
+		};
+		while step > 0 {
+			let next = position + step;
+			if next <= len && self.sums[next] <= target {
+				position = next;
+				target -= self.sums[next];
+			}
+			step >>= 1;
+		}
+		position.min(len.saturating_sub(1))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_partial_shuffle_picks_distinct_items() {
+		for seed in 0..100u8 {
+			let mut items: Vec<u32> = (0..10).collect();
+			partial_shuffle(&mut items, 4, &H256::repeat_byte(seed));
+			let mut sorted = items.clone();
+			sorted.sort();
+			assert_eq!(sorted, (0..10).collect::<Vec<u32>>());
+		}
+	}
+
+	#[test]
+	fn test_partial_shuffle_only_draws_count_items() {
+		let mut items: Vec<u32> = (0..1000).collect();
+		partial_shuffle(&mut items, 3, &H256::repeat_byte(1));
+		// Items after the drawn ones are only moved by the swaps
+		let moved = items
+			.iter()
+			.enumerate()
+			.skip(3)
+			.filter(|(index, item)| *index as u32 != **item)
+			.count();
+		assert!(moved <= 3);
+	}
+
+	#[test]
+	fn test_fenwick_tree_finds_cumulative_weight() {
+		let tree = FenwickTree::new(&[3, 0, 5, 1, 2]);
+		assert_eq!(tree.total, 11);
+		let found: Vec<usize> = (0..11).map(|target| tree.find(target)).collect();
+		assert_eq!(found, vec![0, 0, 0, 2, 2, 2, 2, 2, 3, 4, 4]);
+	}
+
+	#[test]
+	fn test_fenwick_tree_follows_updates() {
+		let mut tree = FenwickTree::new(&[3, 0, 5, 1, 2]);
+		tree.sub(2, 5);
+		tree.add(1, 4);
+		assert_eq!(tree.total, 10);
+		let found: Vec<usize> = (0..10).map(|target| tree.find(target)).collect();
+		assert_eq!(found, vec![0, 0, 0, 1, 1, 1, 1, 3, 4, 4]);
+	}
+
+	#[test]
+	fn test_weighted_partial_shuffle_follows_weights() {
+		let mut picked_first = [0u32; 3];
+		for seed in 0..=255u8 {
+			let mut items = vec![0usize, 1, 2];
+			weighted_partial_shuffle(&mut items, vec![1, 0, 3], 2, &H256::repeat_byte(seed));
+			// The item without weight is never picked while the others remain
+			assert_eq!(items[2], 1);
+			picked_first[items[0]] += 1;
+		}
+		// Item 2 has 3/4 of the weight
+		assert!(picked_first[2] > 160 && picked_first[2] < 220);
+	}
+}
@@ -18,21 +18,175 @@ use super::*;
 pub use crate::mock::*;
 use crate::num::NonZeroU32;
 
-use frame_support::assert_ok;
-use frame_support::traits::OnRuntimeUpgrade;
+use frame_support::traits::{GetStorageVersion, Hooks, OnRuntimeUpgrade, Randomness};
 use frame_support::weights::Weight;
-use sp_runtime::Percent;
+use frame_support::{assert_noop, assert_ok};
+use nimbus_primitives::{CanAuthor, EligibleAuthors, EventHandler};
+use sp_runtime::{BuildStorage, Percent};
 
 #[test]
 fn test_set_eligibility_works() {
 	new_test_ext().execute_with(|| {
-		let value = num::NonZeroU32::new_unchecked(34);
+		let value = EligibilityMode::Count(num::NonZeroU32::new_unchecked(34));
 
 		assert_ok!(AuthorSlotFilter::set_eligible(
 			RuntimeOrigin::root(),
 			value.clone()
 		));
-		assert_eq!(AuthorSlotFilter::eligible_count(), value)
+		assert_eq!(AuthorSlotFilter::eligible_mode(), value)
+	});
+}
+
+#[test]
+fn test_set_eligibility_rejects_inverted_clamp() {
+	new_test_ext().execute_with(|| {
+		let value = EligibilityMode::ClampedRatio {
+			ratio: Percent::from_percent(50),
+			min: NonZeroU32::new_unchecked(10),
+			max: NonZeroU32::new_unchecked(5),
+		};
+
+		assert_noop!(
+			AuthorSlotFilter::set_eligible(RuntimeOrigin::root(), value),
+			Error::<Test>::InvalidEligibilityClamp
+		);
+	});
+}
+
+#[test]
+#[should_panic(expected = "The minimum of the eligibility clamp must not exceed its maximum")]
+fn test_genesis_rejects_inverted_clamp() {
+	let mut t = frame_system::GenesisConfig::<Test>::default()
+		.build_storage()
+		.unwrap();
+	crate::GenesisConfig::<Test> {
+		eligibility_mode: EligibilityMode::ClampedRatio {
+			ratio: Percent::from_percent(50),
+			min: NonZeroU32::new_unchecked(10),
+			max: NonZeroU32::new_unchecked(5),
+		},
+		_config: Default::default(),
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+}
+
+#[test]
+fn test_inverted_clamp_counts_its_maximum() {
+	let inverted = EligibilityMode::ClampedRatio {
+		ratio: Percent::from_percent(50),
+		min: NonZeroU32::new_unchecked(10),
+		max: NonZeroU32::new_unchecked(5),
+	};
+	assert_eq!(inverted.eligible_count(2), 5);
+	assert_eq!(inverted.eligible_count(100), 5);
+}
+
+#[test]
+fn test_eligibility_modes_count_eligible_authors() {
+	let count = EligibilityMode::Count(NonZeroU32::new_unchecked(8));
+	assert_eq!(count.eligible_count(5), 8);
+	assert_eq!(count.eligible_count(100), 8);
+
+	let ratio = EligibilityMode::Ratio(Percent::from_percent(50));
+	assert_eq!(ratio.eligible_count(5), 3);
+	assert_eq!(ratio.eligible_count(100), 50);
+	assert_eq!(
+		EligibilityMode::Ratio(Percent::from_percent(0)).eligible_count(100),
+		1
+	);
+	assert_eq!(ratio.eligible_count(0), 1);
+
+	let clamped = EligibilityMode::ClampedRatio {
+		ratio: Percent::from_percent(50),
+		min: NonZeroU32::new_unchecked(4),
+		max: NonZeroU32::new_unchecked(20),
+	};
+	assert_eq!(clamped.eligible_count(5), 4);
+	assert_eq!(clamped.eligible_count(30), 15);
+	assert_eq!(clamped.eligible_count(100), 20);
+}
+
+#[test]
+fn test_ratio_mode_follows_the_potential_authors() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(AuthorSlotFilter::set_eligible(
+			RuntimeOrigin::root(),
+			EligibilityMode::Ratio(Percent::from_percent(40))
+		));
+
+		let (eligible, _) = compute_pseudo_random_subset::<Test>(Authors::get(), &0);
+		assert_eq!(eligible.len(), 2);
+		let (eligible, _) = compute_pseudo_random_subset::<Test>((1..=20).collect(), &0);
+		assert_eq!(eligible.len(), 8);
+	});
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_migration_keeps_the_existing_eligible_count() {
+	new_test_ext().execute_with(|| {
+		let eligible_count = NonZeroU32::new_unchecked(7);
+		let expected_weight = Weight::from_parts(
+			3 * TestDbWeight::get().write + 3 * TestDbWeight::get().read,
+			0,
+		);
+
+		<EligibleCount<Test>>::put(eligible_count.clone());
+
+		let actual_weight = migration::EligibleCountToEligibilityMode::<Test>::on_runtime_upgrade();
+		assert_eq!(expected_weight, actual_weight);
+
+		assert_eq!(
+			AuthorSlotFilter::eligible_mode(),
+			EligibilityMode::Count(eligible_count)
+		);
+		assert!(!<EligibleCount<Test>>::exists());
+		assert_eq!(AuthorSlotFilter::on_chain_storage_version(), 1);
+	});
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_migration_keeps_an_eligibility_mode_that_is_already_set() {
+	new_test_ext().execute_with(|| {
+		let mode = EligibilityMode::Ratio(Percent::from_percent(30));
+		<EligibleCount<Test>>::put(NonZeroU32::new_unchecked(7));
+		<EligibleMode<Test>>::put(mode.clone());
+
+		migration::EligibleCountToEligibilityMode::<Test>::on_runtime_upgrade();
+
+		assert_eq!(AuthorSlotFilter::eligible_mode(), mode);
+		assert!(!<EligibleCount<Test>>::exists());
+	});
+}
+
+#[test]
+fn test_migration_runs_only_once() {
+	new_test_ext().execute_with(|| {
+		migration::EligibleCountToEligibilityMode::<Test>::on_runtime_upgrade();
+
+		let mode = EligibilityMode::Ratio(Percent::from_percent(30));
+		assert_ok!(AuthorSlotFilter::set_eligible(
+			RuntimeOrigin::root(),
+			mode.clone()
+		));
+
+		let actual_weight = migration::EligibleCountToEligibilityMode::<Test>::on_runtime_upgrade();
+		assert_eq!(actual_weight, TestDbWeight::get().reads(1));
+		assert_eq!(AuthorSlotFilter::eligible_mode(), mode);
+	});
+}
+
+#[test]
+fn test_migration_keeps_the_default_eligible_count() {
+	new_test_ext().execute_with(|| {
+		migration::EligibleCountToEligibilityMode::<Test>::on_runtime_upgrade();
+
+		assert_eq!(
+			AuthorSlotFilter::eligible_mode(),
+			EligibilityMode::Count(EligibilityValue::default())
+		);
 	});
 }
 
@@ -97,3 +251,475 @@ fn test_migration_inserts_default_value_for_missing_eligible_ratio() {
 		assert_eq!(expected_default_eligible_count, actual_eligible_count);
 	});
 }
+
+/// A uniform partial Fisher-Yates shuffle of the potential authors, seeded once per slot
+fn uniform_subset(mut active: Vec<u64>, seed: u32) -> Vec<u64> {
+	let num_eligible = (AuthorSlotFilter::eligible_mode().eligible_count(active.len() as u32)
+		as usize)
+		.min(active.len());
+	let mut subject = [0u8; 10];
+	subject[..6].copy_from_slice(b"filter");
+	subject[6..].copy_from_slice(&seed.to_be_bytes());
+	let (randomness, _) = HashedRandomness::random(&subject);
+	for i in 0..num_eligible {
+		let index =
+			i + (selection::draw(&randomness, i as u32) % (active.len() - i) as u128) as usize;
+		active.swap(i, index);
+	}
+	active.truncate(num_eligible);
+	active
+}
+
+#[test]
+fn test_unit_weights_keep_uniform_selection() {
+	new_test_ext().execute_with(|| {
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(3)));
+
+		for slot in 0..100 {
+			let (eligible, ineligible) =
+				compute_pseudo_random_subset::<Test>(Authors::get(), &slot);
+			assert_eq!(eligible, uniform_subset(Authors::get(), slot));
+			assert_eq!(eligible.len() + ineligible.len(), Authors::get().len());
+		}
+	});
+}
+
+#[test]
+fn test_weighted_selection_is_deterministic() {
+	new_test_ext().execute_with(|| {
+		AuthorStakes::set(&vec![(1, 10), (2, 2_000), (3, 500), (4, 1), (5, 70)]);
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(3)));
+
+		for slot in 0..100 {
+			assert_eq!(
+				compute_pseudo_random_subset::<Test>(Authors::get(), &slot),
+				compute_pseudo_random_subset::<Test>(Authors::get(), &slot)
+			);
+		}
+	});
+}
+
+#[test]
+fn test_weighted_selection_is_without_replacement() {
+	new_test_ext().execute_with(|| {
+		AuthorStakes::set(&vec![(1, u64::MAX as u128), (2, 1), (3, 1), (4, 1), (5, 1)]);
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(5)));
+
+		for slot in 0..100 {
+			let (mut eligible, ineligible) =
+				compute_pseudo_random_subset::<Test>(Authors::get(), &slot);
+			assert!(ineligible.is_empty());
+			eligible.sort();
+			assert_eq!(eligible, Authors::get());
+		}
+	});
+}
+
+#[test]
+fn test_authors_without_weight_are_not_eligible_while_others_remain() {
+	new_test_ext().execute_with(|| {
+		AuthorStakes::set(&vec![(1, 0), (3, 0)]);
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(3)));
+
+		for slot in 0..100 {
+			let (mut eligible, mut ineligible) =
+				compute_pseudo_random_subset::<Test>(Authors::get(), &slot);
+			eligible.sort();
+			ineligible.sort();
+			assert_eq!(eligible, vec![2, 4, 5]);
+			assert_eq!(ineligible, vec![1, 3]);
+		}
+
+		// Authors without weight are picked once nobody else is left
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(4)));
+		for slot in 0..100 {
+			let (eligible, ineligible) =
+				compute_pseudo_random_subset::<Test>(Authors::get(), &slot);
+			assert_eq!(eligible.len(), 4);
+			assert_eq!(ineligible.len(), 1);
+			assert!(ineligible[0] == 1 || ineligible[0] == 3);
+		}
+	});
+}
+
+#[test]
+fn test_selection_is_proportional_to_weight() {
+	new_test_ext().execute_with(|| {
+		AuthorStakes::set(&vec![(1, 3)]);
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(1)));
+
+		// Author 1 has 3/7 of the total weight
+		let selected = (0..1000)
+			.filter(|slot| compute_pseudo_random_subset::<Test>(Authors::get(), slot).0 == vec![1])
+			.count();
+		assert!(
+			selected > 350 && selected < 510,
+			"selected {} times",
+			selected
+		);
+	});
+}
+
+#[test]
+fn test_large_weights_dominate_selection() {
+	new_test_ext().execute_with(|| {
+		// Stakes larger than u32::MAX are covered by the randomness
+		let large_stake = 1_000_000_000_000_000_000u128;
+		AuthorStakes::set(&vec![
+			(1, 1),
+			(2, large_stake),
+			(3, 1),
+			(4, large_stake),
+			(5, 1),
+		]);
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(2)));
+
+		for slot in 0..100 {
+			let (mut eligible, _) = compute_pseudo_random_subset::<Test>(Authors::get(), &slot);
+			eligible.sort();
+			assert_eq!(eligible, vec![2, 4]);
+		}
+	});
+}
+
+#[test]
+fn test_eligible_authors_agree_with_can_author() {
+	new_test_ext().execute_with(|| {
+		AuthorStakes::set(&vec![(1, 10), (2, 2_000), (3, 500), (4, 1), (5, 70)]);
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(2)));
+
+		for slot in 0..100 {
+			let eligible = AuthorSlotFilter::eligible_authors(&slot);
+			assert_eq!(eligible.len(), 2);
+			for author in Authors::get() {
+				assert_eq!(
+					AuthorSlotFilter::can_author(&author, &slot),
+					eligible.contains(&author)
+				);
+			}
+		}
+	});
+}
+
+#[test]
+fn test_set_fairness_strength_works() {
+	new_test_ext().execute_with(|| {
+		let strength = Percent::from_percent(30);
+
+		assert_noop!(
+			AuthorSlotFilter::set_fairness_strength(RuntimeOrigin::signed(1), strength),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(AuthorSlotFilter::set_fairness_strength(
+			RuntimeOrigin::root(),
+			strength
+		));
+		assert_eq!(AuthorSlotFilter::fairness_strength(), strength);
+	});
+}
+
+#[test]
+fn test_authored_blocks_are_counted_over_a_rolling_window() {
+	new_test_ext().execute_with(|| {
+		// The mock window is 4 blocks long
+		for author in [1, 1, 2, 1] {
+			AuthorSlotFilter::note_author(author);
+		}
+		assert_eq!(AuthorSlotFilter::authored_blocks(1), 3);
+		assert_eq!(AuthorSlotFilter::authored_blocks(2), 1);
+
+		// The oldest blocks leave the window
+		AuthorSlotFilter::note_author(3);
+		AuthorSlotFilter::note_author(3);
+		assert_eq!(AuthorSlotFilter::authored_blocks(1), 1);
+		assert_eq!(AuthorSlotFilter::authored_blocks(2), 1);
+		assert_eq!(AuthorSlotFilter::authored_blocks(3), 2);
+
+		AuthorSlotFilter::note_author(3);
+		AuthorSlotFilter::note_author(3);
+		assert_eq!(AuthorSlotFilter::authored_blocks(1), 0);
+		assert_eq!(AuthorSlotFilter::authored_blocks(2), 0);
+		assert_eq!(AuthorSlotFilter::authored_blocks(3), 4);
+		assert!(!AuthoredBlocks::<Test>::contains_key(1));
+	});
+}
+
+#[test]
+fn test_fairness_adjusted_weight() {
+	assert_eq!(
+		fairness_adjusted_weight(1, 0, Percent::from_percent(100)),
+		100
+	);
+	assert_eq!(
+		fairness_adjusted_weight(1, 1, Percent::from_percent(100)),
+		50
+	);
+	assert_eq!(
+		fairness_adjusted_weight(1, 3, Percent::from_percent(100)),
+		25
+	);
+	assert_eq!(
+		fairness_adjusted_weight(1, 4, Percent::from_percent(25)),
+		50
+	);
+	assert_eq!(fairness_adjusted_weight(0, 4, Percent::from_percent(25)), 0);
+	// Unit weights are graded by the strength instead of being rounded down to zero
+	assert_eq!(
+		fairness_adjusted_weight(1, 1, Percent::from_percent(25)),
+		80
+	);
+	assert_eq!(
+		fairness_adjusted_weight(1, 1, Percent::from_percent(50)),
+		66
+	);
+}
+
+#[test]
+fn test_recent_authors_are_not_disfavored_without_strength() {
+	new_test_ext().execute_with(|| {
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(3)));
+		for _ in 0..4 {
+			AuthorSlotFilter::note_author(1);
+		}
+
+		for slot in 0..100 {
+			let (eligible, _) = compute_pseudo_random_subset::<Test>(Authors::get(), &slot);
+			assert_eq!(eligible, uniform_subset(Authors::get(), slot));
+		}
+	});
+}
+
+#[test]
+fn test_recent_authors_are_disfavored() {
+	new_test_ext().execute_with(|| {
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(1)));
+		for _ in 0..4 {
+			AuthorSlotFilter::note_author(1);
+		}
+		assert_ok!(AuthorSlotFilter::set_fairness_strength(
+			RuntimeOrigin::root(),
+			Percent::from_percent(100)
+		));
+
+		// Author 1 has a weight of 1/5 against 1 for the others, so it is picked about 5% of
+		// the time instead of 20%
+		let selected = (0..1000)
+			.filter(|slot| compute_pseudo_random_subset::<Test>(Authors::get(), slot).0 == vec![1])
+			.count();
+		assert!(selected < 100, "selected {} times", selected);
+	});
+}
+
+#[test]
+fn test_eligible_count_is_not_widened_before_the_first_block() {
+	new_test_ext().execute_with(|| {
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(1)));
+
+		assert_eq!(effective_eligible_count::<Test>(100, 5), 1);
+	});
+}
+
+#[test]
+fn test_eligible_count_is_widened_for_each_unfilled_slot() {
+	new_test_ext().execute_with(|| {
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(1)));
+		LastAuthoredSlot::<Test>::put(10);
+
+		// The mock tolerates one unfilled slot, and widens by one author per further slot
+		assert_eq!(effective_eligible_count::<Test>(10, 5), 1);
+		assert_eq!(effective_eligible_count::<Test>(11, 5), 1);
+		assert_eq!(effective_eligible_count::<Test>(12, 5), 1);
+		assert_eq!(effective_eligible_count::<Test>(13, 5), 2);
+		assert_eq!(effective_eligible_count::<Test>(15, 5), 4);
+
+		let (eligible, _) = compute_pseudo_random_subset::<Test>(Authors::get(), &15);
+		assert_eq!(eligible.len(), 4);
+		// Never more than the potential authors
+		let (eligible, ineligible) = compute_pseudo_random_subset::<Test>(Authors::get(), &100);
+		assert_eq!(eligible.len(), 5);
+		assert!(ineligible.is_empty());
+	});
+}
+
+#[test]
+fn test_widened_authors_include_the_regular_authors() {
+	new_test_ext().execute_with(|| {
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(2)));
+
+		for slot in 0..100 {
+			LastAuthoredSlot::<Test>::kill();
+			let (regular, _) = compute_pseudo_random_subset::<Test>(Authors::get(), &slot);
+			LastAuthoredSlot::<Test>::put(slot.saturating_sub(3));
+			let (widened, _) = compute_pseudo_random_subset::<Test>(Authors::get(), &slot);
+
+			if slot >= 3 {
+				assert_eq!(widened.len(), 3);
+			}
+			assert_eq!(widened[..2], regular[..]);
+		}
+	});
+}
+
+#[test]
+fn test_upcoming_slots_are_predicted_without_widening() {
+	new_test_ext().execute_with(|| {
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(1)));
+		LastAuthoredSlot::<Test>::put(10);
+		CurrentSlot::set(&10);
+
+		// The slots after the tolerance may still get a block before slot 15
+		assert_eq!(AuthorSlotFilter::eligible_authors(&15).len(), 1);
+		assert_eq!(
+			AuthorSlotFilter::eligible_authors(&15),
+			uniform_subset(Authors::get(), 15)
+		);
+
+		// Once slot 15 is being authored, its eligible authors are widened
+		CurrentSlot::set(&15);
+		assert_eq!(AuthorSlotFilter::eligible_authors(&15).len(), 4);
+	});
+}
+
+#[test]
+fn test_widening_and_restoring_the_eligible_count_emit_events() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(1)));
+
+		// Regular blocks do not emit any event
+		CurrentSlot::set(&10);
+		AuthorSlotFilter::on_finalize(1);
+		CurrentSlot::set(&12);
+		AuthorSlotFilter::on_finalize(1);
+		assert_eq!(AuthorSlotFilter::last_authored_slot(), Some(12));
+		assert!(System::events().is_empty());
+
+		// The block after three unfilled slots was authored with a widened eligible count
+		CurrentSlot::set(&16);
+		AuthorSlotFilter::on_finalize(1);
+		assert_eq!(AuthorSlotFilter::widened_eligible_count(), Some(3));
+		System::assert_last_event(Event::EffectiveEligibleUpdated(3).into());
+
+		// The next block restores the eligible count
+		CurrentSlot::set(&17);
+		AuthorSlotFilter::on_finalize(1);
+		assert_eq!(AuthorSlotFilter::widened_eligible_count(), None);
+		System::assert_last_event(Event::EffectiveEligibleUpdated(1).into());
+		assert_eq!(System::events().len(), 2);
+	});
+}
+
+#[test]
+fn test_large_author_sets_are_selected_without_replacement() {
+	new_test_ext().execute_with(|| {
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(50)));
+		let authors: Vec<u64> = (1..=1000).collect();
+
+		let (mut eligible, ineligible) = compute_pseudo_random_subset::<Test>(authors.clone(), &7);
+		assert_eq!(eligible.len(), 50);
+		assert_eq!(ineligible.len(), 950);
+		eligible.extend(ineligible);
+		eligible.sort();
+		assert_eq!(eligible, authors);
+	});
+}
+
+#[test]
+fn test_eligible_authors_are_cached_per_slot() {
+	new_test_ext().execute_with(|| {
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(2)));
+		let eligible = uniform_subset(Authors::get(), 3);
+
+		assert!(AuthorSlotFilter::can_author(&eligible[0], &3));
+		assert_eq!(
+			EligibleAuthorsCache::<Test>::get().map(|(slot, cached)| (slot, cached.into_inner())),
+			Some((3, eligible.clone()))
+		);
+
+		// The next blocks of the same slot keep the cached authors
+		AuthorStakes::set(&vec![(eligible[0], 0), (eligible[1], 0)]);
+		assert!(AuthorSlotFilter::can_author(&eligible[0], &3));
+		assert_eq!(AuthorSlotFilter::eligible_authors(&3), eligible);
+
+		// Another slot computes its own authors
+		assert!(!AuthorSlotFilter::can_author(&eligible[0], &4));
+		assert_eq!(
+			EligibleAuthorsCache::<Test>::get().map(|(slot, _)| slot),
+			Some(4)
+		);
+	});
+}
+
+#[test]
+fn test_eligible_authors_cache_is_cleared_by_governance() {
+	new_test_ext().execute_with(|| {
+		assert!(AuthorSlotFilter::can_author(&1, &3));
+		assert!(EligibleAuthorsCache::<Test>::get().is_some());
+
+		assert_ok!(AuthorSlotFilter::set_eligible(
+			RuntimeOrigin::root(),
+			EligibilityMode::Count(NonZeroU32::new_unchecked(1))
+		));
+		assert!(EligibleAuthorsCache::<Test>::get().is_none());
+
+		AuthorSlotFilter::can_author(&1, &3);
+		assert!(EligibleAuthorsCache::<Test>::get().is_some());
+		assert_ok!(AuthorSlotFilter::set_fairness_strength(
+			RuntimeOrigin::root(),
+			Percent::from_percent(10)
+		));
+		assert!(EligibleAuthorsCache::<Test>::get().is_none());
+	});
+}
+
+#[test]
+fn test_can_author_registers_the_weight_of_the_selection() {
+	new_test_ext().execute_with(|| {
+		let mandatory_weight =
+			|| *System::block_weight().get(frame_support::dispatch::DispatchClass::Mandatory);
+
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(2)));
+
+		// Uniform weights only pay for the eligible authors
+		AuthorSlotFilter::can_author(&1, &3);
+		let selection_weight = mandatory_weight();
+		assert_eq!(
+			selection_weight,
+			<() as WeightInfo>::cached_eligible_authors(0)
+				+ <() as WeightInfo>::compute_uniform_subset(2)
+				+ TestDbWeight::get().writes(1)
+		);
+
+		// The next blocks of the slot only read the cache
+		AuthorSlotFilter::can_author(&1, &3);
+		let eligible = EligibleAuthorsCache::<Test>::get().unwrap().1;
+		assert_eq!(
+			mandatory_weight() - selection_weight,
+			<() as WeightInfo>::cached_eligible_authors(eligible.len() as u32)
+		);
+
+		// The weighted selection pays for every potential author
+		AuthorStakes::set(&vec![(1, 10)]);
+		let weight_before = mandatory_weight();
+		AuthorSlotFilter::can_author(&1, &4);
+		assert_eq!(
+			mandatory_weight() - weight_before,
+			<() as WeightInfo>::cached_eligible_authors(0)
+				+ <() as WeightInfo>::compute_pseudo_random_subset(5)
+				+ TestDbWeight::get().writes(1)
+		);
+	});
+}
+
+#[test]
+fn test_eligible_count_is_clamped_to_the_cache_bound() {
+	new_test_ext().execute_with(|| {
+		// The mock caches at most 100 eligible authors
+		EligibleMode::<Test>::put(EligibilityMode::Count(NonZeroU32::new_unchecked(150)));
+		let authors: Vec<u64> = (1..=200).collect();
+
+		let (eligible, ineligible) = compute_pseudo_random_subset::<Test>(authors, &7);
+		assert_eq!(eligible.len(), 100);
+		assert_eq!(ineligible.len(), 100);
+	});
+}
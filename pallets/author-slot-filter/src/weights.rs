@@ -15,33 +15,18 @@
 // along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
 
 
-//! Autogenerated weights for pallet_author_slot_filter
+//! Weights for pallet_author_slot_filter
 //!
-//! THIS FILE WAS AUTO-GENERATED USING THE SUBSTRATE BENCHMARK CLI VERSION 4.0.0-dev
-//! DATE: 2023-05-02, STEPS: `50`, REPEAT: `20`, LOW RANGE: `[]`, HIGH RANGE: `[]`
-//! WORST CASE MAP SIZE: `1000000`
-//! HOSTNAME: `benchmarker`, CPU: `Intel(R) Core(TM) i7-7700K CPU @ 4.20GHz`
-//! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: None, DB CACHE: 1024
-
-// Executed Command:
-// ./target/release/moonbeam
-// benchmark
-// pallet
-// --execution=wasm
-// --wasm-execution=compiled
-// --pallet
-// *
-// --extrinsic
-// *
-// --steps
-// 50
-// --repeat
-// 20
-// --template=./benchmarking/frame-weight-template.hbs
-// --json-file
-// raw.json
-// --output
-// weights/
+//! These weights have not been measured by the benchmark CLI since the pallet gained eligibility
+//! modes, fairness, the eligible authors cache and the uniform selection. `set_eligible` keeps the
+//! execution time measured when it only wrote the eligible count, and the other entries are
+//! estimates that follow the storage accesses of the benchmarks in `benchmarks.rs`, as noted on
+//! each entry. They must be replaced by the output of the benchmark CLI, run against a runtime
+//! that includes the pallet, such as the template runtime:
+//!
+//! ./target/release/<node> benchmark pallet --execution=wasm --wasm-execution=compiled
+//! --pallet pallet_author_slot_filter --extrinsic '*' --steps 50 --repeat 20
+//! --template=./benchmarking/frame-weight-template.hbs --output weights.rs
 
 #![cfg_attr(rustfmt, rustfmt_skip)]
 #![allow(unused_parens)]
@@ -53,33 +38,100 @@ use sp_std::marker::PhantomData;
 /// Weight functions needed for pallet_author_slot_filter.
 pub trait WeightInfo {
 	fn set_eligible() -> Weight;
+	fn compute_pseudo_random_subset(x: u32, ) -> Weight;
+	fn compute_uniform_subset(x: u32, ) -> Weight;
+	fn cached_eligible_authors(x: u32, ) -> Weight;
+	fn set_fairness_strength() -> Weight;
+	fn note_author() -> Weight;
 }
 
 /// Weights for pallet_author_slot_filter using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
-	/// Storage: AuthorFilter EligibleCount (r:0 w:1)
-	/// Proof Skipped: AuthorFilter EligibleCount (max_values: Some(1), max_size: None, mode: Measured)
+	/// Not measured: the execution time measured when it only wrote the eligible count, with the
+	/// write of the cleared cache.
 	fn set_eligible() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `0`
-		//  Estimated: `0`
-		// Minimum execution time: 12_558_000 picoseconds.
 		Weight::from_parts(12_823_000, 0)
-			.saturating_add(T::DbWeight::get().writes(1_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Not measured: reads `AuthoredBlocks` for each of the `x` potential authors.
+	/// The range of component `x` is `[1, 1000]`.
+	fn compute_pseudo_random_subset(x: u32, ) -> Weight {
+		Weight::from_parts(11_540_875, 4151)
+			.saturating_add(Weight::from_parts(4_387_912, 0).saturating_mul(x.into()))
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(x.into())))
+			.saturating_add(Weight::from_parts(0, 2537).saturating_mul(x.into()))
+	}
+	/// Not measured: draws `x` authors without reading anything for each of them.
+	/// The range of component `x` is `[1, 100]`.
+	fn compute_uniform_subset(x: u32, ) -> Weight {
+		Weight::from_parts(51_903_264, 4151)
+			.saturating_add(Weight::from_parts(1_062_480, 0).saturating_mul(x.into()))
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+	}
+	/// Not measured: reads the `x` cached authors.
+	/// The range of component `x` is `[1, 100]`.
+	fn cached_eligible_authors(x: u32, ) -> Weight {
+		Weight::from_parts(6_157_530, 1558)
+			.saturating_add(Weight::from_parts(24_815, 0).saturating_mul(x.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(Weight::from_parts(0, 32).saturating_mul(x.into()))
+	}
+	/// Not measured: the execution time of `set_eligible`, which writes the same storage.
+	fn set_fairness_strength() -> Weight {
+		Weight::from_parts(12_823_000, 0)
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Not measured: replaces the oldest author of the rolling window.
+	fn note_author() -> Weight {
+		Weight::from_parts(25_764_000, 6229)
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().writes(4_u64))
 	}
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
-	/// Storage: AuthorFilter EligibleCount (r:0 w:1)
-	/// Proof Skipped: AuthorFilter EligibleCount (max_values: Some(1), max_size: None, mode: Measured)
+	/// Not measured: the execution time measured when it only wrote the eligible count, with the
+	/// write of the cleared cache.
 	fn set_eligible() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `0`
-		//  Estimated: `0`
-		// Minimum execution time: 12_558_000 picoseconds.
 		Weight::from_parts(12_823_000, 0)
-			.saturating_add(RocksDbWeight::get().writes(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Not measured: reads `AuthoredBlocks` for each of the `x` potential authors.
+	/// The range of component `x` is `[1, 1000]`.
+	fn compute_pseudo_random_subset(x: u32, ) -> Weight {
+		Weight::from_parts(11_540_875, 4151)
+			.saturating_add(Weight::from_parts(4_387_912, 0).saturating_mul(x.into()))
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(x.into())))
+			.saturating_add(Weight::from_parts(0, 2537).saturating_mul(x.into()))
+	}
+	/// Not measured: draws `x` authors without reading anything for each of them.
+	/// The range of component `x` is `[1, 100]`.
+	fn compute_uniform_subset(x: u32, ) -> Weight {
+		Weight::from_parts(51_903_264, 4151)
+			.saturating_add(Weight::from_parts(1_062_480, 0).saturating_mul(x.into()))
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+	}
+	/// Not measured: reads the `x` cached authors.
+	/// The range of component `x` is `[1, 100]`.
+	fn cached_eligible_authors(x: u32, ) -> Weight {
+		Weight::from_parts(6_157_530, 1558)
+			.saturating_add(Weight::from_parts(24_815, 0).saturating_mul(x.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(Weight::from_parts(0, 32).saturating_mul(x.into()))
+	}
+	/// Not measured: the execution time of `set_eligible`, which writes the same storage.
+	fn set_fairness_strength() -> Weight {
+		Weight::from_parts(12_823_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Not measured: replaces the oldest author of the rolling window.
+	fn note_author() -> Weight {
+		Weight::from_parts(25_764_000, 6229)
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
 	}
 }
\ No newline at end of file
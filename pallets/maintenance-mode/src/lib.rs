@@ -17,25 +17,36 @@
 //! A pallet to put your runtime into a restricted maintenance or safe mode. This is useful when
 //! performing site maintenance, running data migrations, or protecting the chain during an attack.
 //!
-//! This introduces one storage read to fetch the base filter for each extrinsic. However, it should
-//! be that the state cache eliminates this cost almost entirely. I wonder if that can or should be
-//! reflected in the weight calculation.
+//! This introduces a few storage reads to fetch the base filter and the paused calls for each
+//! extrinsic. However, it should be that the state cache eliminates this cost almost entirely. I
+//! wonder if that can or should be reflected in the weight calculation.
 //!
-//! Possible future improvements
-//! 1. This could be more configurable by letting the runtime developer specify a type (probably an
-//! enum) that can be converted into a filter. Similar end result (but different implementation) as
-//! Acala has it
-//! github.com/AcalaNetwork/Acala/blob/pause-transaction/modules/transaction-pause/src/lib.rs#L71
+//! The chain also behaves as in maintenance mode while the configured `PendingMigrations` returns
+//! true, for instance while the multi-block migrations of pallet-migrations are in progress.
+//!
+//! To avoid getting stuck in maintenance mode, it can be entered for a limited number of blocks,
+//! after which the chain automatically switches back to normal mode. That period can be extended
+//! with `extend_maintenance_mode`. The timeout is enforced by `ExecutiveHooks`, which must
+//! therefore be given to `frame_executive::Executive` in place of `AllPalletsWithSystem`.
 //!
-//! 2. Automatically enable maintenance mode after a long timeout is detected between blocks.
-//! To implement this we would couple to the timestamp pallet and store the timestamp of the
-//! previous block.
+//! When used as `pallet_timestamp::Config::OnTimestampSet`, the pallet stores the timestamp of the
+//! previous block and automatically enables maintenance mode when the gap between two blocks is
+//! longer than `MaxBlockGap`, which protects the chain after a stall or an attack. The gap is
+//! detected while the timestamp inherent is applied, so maintenance mode is only entered at the
+//! start of the next block, to keep the same call filter and hooks for a whole block.
 //!
-//! 3. Different origins for entering and leaving maintenance mode.
+//! The pallet plugs into pallet-message-queue, either as its `QueuePausedQuery` or by wrapping its
+//! `MessageProcessor` with `MaintenanceModeMessageProcessor`. Either way the DMP, HRMP and XCMP
+//! queues stop being processed during maintenance without losing any message.
+//!
+//! Independently of the mode, individual pallets or calls can be paused by name at runtime, in a
+//! similar way as Acala does it
+//! github.com/AcalaNetwork/Acala/blob/pause-transaction/modules/transaction-pause/src/lib.rs#L71
 //!
-//! 4. Maintenance mode timeout. To avoid getting stuck in maintenance mode. It could automatically
-//! switch back to normal mode after a pre-decided number of blocks. Maybe there could be an
-//! extrinsic to extend the maintenance time.
+//! The former `MaintenanceOrigin` is split into `EnterMaintenanceOrigin` and `ResumeNormalOrigin`.
+//! The origins are only part of the configuration and no storage item depends on them, so the
+//! split ships without a storage migration: runtimes upgrading from `MaintenanceOrigin` only
+//! configure both new origins, for instance with the origin they used before.
 
 #![allow(non_camel_case_types)]
 #![cfg_attr(not(feature = "std"), no_std)]
@@ -54,17 +65,15 @@ pub use types::*;
 
 #[pallet]
 pub mod pallet {
-	#[cfg(feature = "xcm-support")]
-	use cumulus_primitives_core::{
-		relay_chain::BlockNumber as RelayBlockNumber, DmpMessageHandler,
-	};
+	use crate::MaintenanceReason;
 	use frame_support::pallet_prelude::*;
 	use frame_support::traits::{
-		BuildGenesisConfig, Contains, EnsureOrigin, OffchainWorker, OnFinalize, OnIdle,
-		OnInitialize, OnRuntimeUpgrade,
+		BuildGenesisConfig, CallMetadata, Contains, EnsureOrigin, GetCallMetadata, OffchainWorker,
+		OnFinalize, OnIdle, OnInitialize, OnRuntimeUpgrade, OnTimestampSet, PalletInfoAccess,
+		QueuePausedQuery,
 	};
-	use frame_system::pallet_prelude::*;
-	#[cfg(feature = "xcm-support")]
+	use frame_system::{pallet_prelude::*, WeightInfo as _};
+	use sp_runtime::traits::{AtLeast32BitUnsigned, Saturating};
 	use sp_std::vec::Vec;
 	#[cfg(feature = "xcm-support")]
 	use xcm_primitives::PauseXcmExecution;
@@ -78,7 +87,7 @@ pub mod pallet {
 	#[pallet::config]
 	pub trait Config: frame_system::Config {
 		/// Overarching event type
-		type RuntimeEvent: From<Event> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 		/// The base call filter to be used in normal operating mode
 		/// (When we aren't in the middle of a migration)
 		type NormalCallFilter: Contains<Self::RuntimeCall>;
@@ -86,22 +95,37 @@ pub mod pallet {
 		/// This should be very restrictive. Probably not allowing anything except possibly
 		/// something like sudo or other emergency processes
 		type MaintenanceCallFilter: Contains<Self::RuntimeCall>;
-		/// The origin from which the call to enter or exit maintenance mode must come
+		/// The origin from which the call to enter maintenance mode must come
+		/// This can be a fast, low-threshold origin, such as a technical committee member, so that
+		/// the chain can be protected quickly during an emergency.
+		type EnterMaintenanceOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+		/// The origin from which the calls to resume normal operation or to extend maintenance
+		/// mode must come
 		/// Take care when choosing your maintenance call filter to ensure that you'll still be
-		/// able to return to normal mode. For example, if your MaintenanceOrigin is a council, make
-		/// sure that your councilors can still cast votes.
-		type MaintenanceOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+		/// able to return to normal mode. For example, if your ResumeNormalOrigin is a council,
+		/// make sure that your councilors can still cast votes.
+		type ResumeNormalOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+		/// Whether there are migrations still pending, for example
+		/// `pallet_migrations::PendingMigrations`. While this is true the chain behaves as in
+		/// maintenance mode, so that users cannot interact with partially migrated storage.
+		type PendingMigrations: Get<bool>;
+		/// The type used to represent timestamps, as provided by pallet-timestamp
+		type Moment: Parameter + AtLeast32BitUnsigned + Default + Copy + MaxEncodedLen;
+		/// The maximum time allowed between two consecutive blocks. When a longer gap is
+		/// detected, the chain automatically enters maintenance mode.
+		/// This is only enforced if the pallet is used as pallet-timestamp's OnTimestampSet.
+		#[pallet::constant]
+		type MaxBlockGap: Get<Self::Moment>;
+		/// The maximum length of the pallet and call names that can be paused
+		#[pallet::constant]
+		type MaxNameLength: Get<u32>;
+		/// The names of the pallets whose calls cannot be paused, in addition to this pallet
+		/// This must include every pallet needed to dispatch from the ResumeNormalOrigin, such as
+		/// sudo or the governance collectives, otherwise pausing them could lock the chain.
+		type UnpausablePallets: Contains<Vec<u8>>;
 		/// Handler to suspend and resume XCM execution
 		#[cfg(feature = "xcm-support")]
 		type XcmExecutionManager: PauseXcmExecution;
-		/// The DMP handler to be used in normal operating mode
-		/// TODO: remove once https://github.com/paritytech/polkadot/pull/5035 is merged
-		#[cfg(feature = "xcm-support")]
-		type NormalDmpHandler: DmpMessageHandler;
-		/// The DMP handler to be used in maintenance mode
-		/// TODO: remove once https://github.com/paritytech/polkadot/pull/5035 is merged
-		#[cfg(feature = "xcm-support")]
-		type MaintenanceDmpHandler: DmpMessageHandler;
 		/// The executive hooks that will be used in normal operating mode
 		/// Important: Use AllPalletsWithSystem here if you dont want to modify the
 		/// hooks behaviour
@@ -112,7 +136,8 @@ pub mod pallet {
 			+ OffchainWorker<BlockNumberFor<Self>>;
 		/// The executive hooks that will be used in maintenance mode
 		/// Important: Use AllPalletsWithSystem here if you dont want to modify the
-		/// hooks behaviour
+		/// hooks behaviour. These hooks must include the pallet that drives the
+		/// PendingMigrations, otherwise the migrations will never complete.
 		type MaintenanceExecutiveHooks: OnRuntimeUpgrade
 			+ OnInitialize<BlockNumberFor<Self>>
 			+ OnIdle<BlockNumberFor<Self>>
@@ -122,11 +147,28 @@ pub mod pallet {
 
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(crate) fn deposit_event)]
-	pub enum Event {
+	pub enum Event<T: Config> {
 		/// The chain was put into Maintenance Mode
 		EnteredMaintenanceMode,
 		/// The chain returned to its normal operating state
 		NormalOperationResumed,
+		/// The end of the maintenance mode was postponed to the given block
+		MaintenanceModeExtended { end: BlockNumberFor<T> },
+		/// The maintenance mode timed out and the chain automatically returned to its normal
+		/// operating state
+		MaintenanceModeExpired,
+		/// The chain was automatically put into Maintenance Mode for the given reason
+		EnteredMaintenanceModeAutomatically { reason: MaintenanceReason },
+		/// All the calls of a pallet, or a single call if call_name is given, were paused
+		CallsPaused {
+			pallet_name: Vec<u8>,
+			call_name: Option<Vec<u8>>,
+		},
+		/// All the calls of a pallet, or a single call if call_name is given, were unpaused
+		CallsUnpaused {
+			pallet_name: Vec<u8>,
+			call_name: Option<Vec<u8>>,
+		},
 		/// The call to suspend on_idle XCM execution failed with inner error
 		FailedToSuspendIdleXcmExecution { error: DispatchError },
 		/// The call to resume on_idle XCM execution failed with inner error
@@ -140,6 +182,17 @@ pub mod pallet {
 		AlreadyInMaintenanceMode,
 		/// The chain cannot resume normal operation because it is not in maintenance mode
 		NotInMaintenanceMode,
+		/// The maintenance mode cannot be extended because it was entered without a duration
+		MaintenanceModeNotTimed,
+		/// The calls of this pallet or of one of the UnpausablePallets cannot be paused, otherwise
+		/// they could never be unpaused
+		CannotPauseThisPallet,
+		/// The pallet or call name is longer than MaxNameLength
+		NameTooLong,
+		/// The calls are already paused
+		AlreadyPaused,
+		/// The calls are not paused
+		NotPaused,
 	}
 
 	#[pallet::storage]
@@ -147,18 +200,60 @@ pub mod pallet {
 	/// Whether the site is in maintenance mode
 	type MaintenanceMode<T: Config> = StorageValue<_, bool, ValueQuery>;
 
+	#[pallet::storage]
+	#[pallet::getter(fn maintenance_mode_end)]
+	/// The block at which the chain automatically resumes normal operation, if maintenance mode
+	/// was entered for a limited duration
+	type MaintenanceModeEnd<T: Config> = StorageValue<_, BlockNumberFor<T>, OptionQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn previous_block_timestamp)]
+	/// The timestamp of the previous block, used to detect long gaps in block production
+	type PreviousBlockTimestamp<T: Config> = StorageValue<_, T::Moment, OptionQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn pending_maintenance_mode)]
+	/// The reason for entering maintenance mode at the start of the next block, if a long gap in
+	/// block production was detected in the current one
+	type PendingMaintenanceMode<T: Config> = StorageValue<_, MaintenanceReason, OptionQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn paused_pallets)]
+	/// The pallets whose calls are all paused
+	/// Maps pallet name -> ()
+	type PausedPallets<T: Config> =
+		StorageMap<_, Twox64Concat, BoundedVec<u8, T::MaxNameLength>, (), OptionQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn paused_calls)]
+	/// The individual calls that are paused
+	/// Maps (pallet name, call name) -> ()
+	type PausedCalls<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		BoundedVec<u8, T::MaxNameLength>,
+		Twox64Concat,
+		BoundedVec<u8, T::MaxNameLength>,
+		(),
+		OptionQuery,
+	>;
+
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		/// Place the chain in maintenance mode
+		/// Place the chain in maintenance mode, optionally for a limited number of blocks
 		///
 		/// Weight cost is:
 		/// * One DB read to ensure we're not already in maintenance mode
-		/// * Three DB writes - 1 for the mode, 1 for suspending xcm execution, 1 for the event
+		/// * Four DB writes - 1 for the mode, 1 for the end of the mode, 1 for suspending xcm
+		/// execution, 1 for the event
 		#[pallet::call_index(0)]
-		#[pallet::weight(T::DbWeight::get().read + 3 * T::DbWeight::get().write)]
-		pub fn enter_maintenance_mode(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+		#[pallet::weight(T::DbWeight::get().read + 4 * T::DbWeight::get().write)]
+		pub fn enter_maintenance_mode(
+			origin: OriginFor<T>,
+			duration: Option<BlockNumberFor<T>>,
+		) -> DispatchResultWithPostInfo {
 			// Ensure Origin
-			T::MaintenanceOrigin::ensure_origin(origin)?;
+			T::EnterMaintenanceOrigin::ensure_origin(origin)?;
 
 			// Ensure we're not aleady in maintenance mode.
 			// This test is not strictly necessary, but seeing the error may help a confused chain
@@ -168,13 +263,9 @@ pub mod pallet {
 				Error::<T>::AlreadyInMaintenanceMode
 			);
 
-			// Write to storage
-			MaintenanceMode::<T>::put(true);
-			// Suspend XCM execution
-			#[cfg(feature = "xcm-support")]
-			if let Err(error) = T::XcmExecutionManager::suspend_xcm_execution() {
-				<Pallet<T>>::deposit_event(Event::FailedToSuspendIdleXcmExecution { error });
-			}
+			let end = duration
+				.map(|duration| frame_system::Pallet::<T>::block_number().saturating_add(duration));
+			Self::do_enter_maintenance_mode(end);
 
 			// Event
 			<Pallet<T>>::deposit_event(Event::EnteredMaintenanceMode);
@@ -186,12 +277,13 @@ pub mod pallet {
 		///
 		/// Weight cost is:
 		/// * One DB read to ensure we're in maintenance mode
-		/// * Three DB writes - 1 for the mode, 1 for resuming xcm execution, 1 for the event
+		/// * Four DB writes - 1 for the mode, 1 for the end of the mode, 1 for resuming xcm
+		/// execution, 1 for the event
 		#[pallet::call_index(1)]
-		#[pallet::weight(T::DbWeight::get().read + 3 * T::DbWeight::get().write)]
+		#[pallet::weight(T::DbWeight::get().read + 4 * T::DbWeight::get().write)]
 		pub fn resume_normal_operation(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
 			// Ensure Origin
-			T::MaintenanceOrigin::ensure_origin(origin)?;
+			T::ResumeNormalOrigin::ensure_origin(origin)?;
 
 			// Ensure we're actually in maintenance mode.
 			// This test is not strictly necessary, but seeing the error may help a confused chain
@@ -201,16 +293,144 @@ pub mod pallet {
 				Error::<T>::NotInMaintenanceMode
 			);
 
+			Self::do_resume_normal_operation();
+
+			// Event
+			<Pallet<T>>::deposit_event(Event::NormalOperationResumed);
+
+			Ok(().into())
+		}
+
+		/// Postpone by the given number of blocks the automatic return to normal operating mode
+		///
+		/// Weight cost is:
+		/// * Two DB reads to ensure we're in a maintenance mode that has an end
+		/// * Two DB writes - 1 for the end of the mode, 1 for the event
+		#[pallet::call_index(2)]
+		#[pallet::weight(2 * T::DbWeight::get().read + 2 * T::DbWeight::get().write)]
+		pub fn extend_maintenance_mode(
+			origin: OriginFor<T>,
+			duration: BlockNumberFor<T>,
+		) -> DispatchResultWithPostInfo {
+			// Ensure Origin. Extending maintenance mode delays the return to normal operation, so
+			// it requires the same origin as resuming it.
+			T::ResumeNormalOrigin::ensure_origin(origin)?;
+
+			ensure!(
+				MaintenanceMode::<T>::get(),
+				Error::<T>::NotInMaintenanceMode
+			);
+			let end = MaintenanceModeEnd::<T>::get().ok_or(Error::<T>::MaintenanceModeNotTimed)?;
+
 			// Write to storage
-			MaintenanceMode::<T>::put(false);
-			// Resume XCM execution
-			#[cfg(feature = "xcm-support")]
-			if let Err(error) = T::XcmExecutionManager::resume_xcm_execution() {
-				<Pallet<T>>::deposit_event(Event::FailedToResumeIdleXcmExecution { error });
+			let end = end.saturating_add(duration);
+			MaintenanceModeEnd::<T>::put(end);
+
+			// Event
+			<Pallet<T>>::deposit_event(Event::MaintenanceModeExtended { end });
+
+			Ok(().into())
+		}
+
+		/// Pause all the calls of a pallet, or a single call of that pallet if call_name is given
+		///
+		/// Weight cost is:
+		/// * One DB read to ensure the calls are not already paused
+		/// * Two DB writes - 1 for the paused calls, 1 for the event
+		/// * Handling the names, which grows with their length
+		#[pallet::call_index(3)]
+		#[pallet::weight(
+			(T::DbWeight::get().read + 2 * T::DbWeight::get().write)
+				.saturating_add(Pallet::<T>::names_weight(pallet_name, call_name))
+		)]
+		pub fn pause_calls(
+			origin: OriginFor<T>,
+			pallet_name: Vec<u8>,
+			call_name: Option<Vec<u8>>,
+		) -> DispatchResultWithPostInfo {
+			// Ensure Origin
+			T::EnterMaintenanceOrigin::ensure_origin(origin)?;
+
+			ensure!(
+				pallet_name.as_slice() != <Self as PalletInfoAccess>::name().as_bytes()
+					&& !T::UnpausablePallets::contains(&pallet_name),
+				Error::<T>::CannotPauseThisPallet
+			);
+			let bounded_pallet_name = Self::bounded_name(&pallet_name)?;
+
+			// Write to storage
+			match &call_name {
+				Some(call_name) => {
+					let bounded_call_name = Self::bounded_name(call_name)?;
+					ensure!(
+						!PausedCalls::<T>::contains_key(&bounded_pallet_name, &bounded_call_name),
+						Error::<T>::AlreadyPaused
+					);
+					PausedCalls::<T>::insert(bounded_pallet_name, bounded_call_name, ());
+				}
+				None => {
+					ensure!(
+						!PausedPallets::<T>::contains_key(&bounded_pallet_name),
+						Error::<T>::AlreadyPaused
+					);
+					PausedPallets::<T>::insert(bounded_pallet_name, ());
+				}
 			}
 
 			// Event
-			<Pallet<T>>::deposit_event(Event::NormalOperationResumed);
+			<Pallet<T>>::deposit_event(Event::CallsPaused {
+				pallet_name,
+				call_name,
+			});
+
+			Ok(().into())
+		}
+
+		/// Unpause the calls previously paused with pause_calls and the same arguments
+		///
+		/// Weight cost is:
+		/// * One DB read to ensure the calls are paused
+		/// * Two DB writes - 1 for the paused calls, 1 for the event
+		/// * Handling the names, which grows with their length
+		#[pallet::call_index(4)]
+		#[pallet::weight(
+			(T::DbWeight::get().read + 2 * T::DbWeight::get().write)
+				.saturating_add(Pallet::<T>::names_weight(pallet_name, call_name))
+		)]
+		pub fn unpause_calls(
+			origin: OriginFor<T>,
+			pallet_name: Vec<u8>,
+			call_name: Option<Vec<u8>>,
+		) -> DispatchResultWithPostInfo {
+			// Ensure Origin
+			T::ResumeNormalOrigin::ensure_origin(origin)?;
+
+			let bounded_pallet_name = Self::bounded_name(&pallet_name)?;
+
+			// Write to storage
+			match &call_name {
+				Some(call_name) => {
+					let bounded_call_name = Self::bounded_name(call_name)?;
+					ensure!(
+						PausedCalls::<T>::contains_key(&bounded_pallet_name, &bounded_call_name),
+						Error::<T>::NotPaused
+					);
+					PausedCalls::<T>::remove(bounded_pallet_name, bounded_call_name);
+				}
+				None => {
+					ensure!(
+						PausedPallets::<T>::contains_key(&bounded_pallet_name),
+						Error::<T>::NotPaused
+					);
+					PausedPallets::<T>::remove(bounded_pallet_name);
+				}
+			}
+
+			// Event
+			<Pallet<T>>::deposit_event(Event::CallsUnpaused {
+				pallet_name,
+				call_name,
+			});
 
 			Ok(().into())
 		}
@@ -235,26 +455,163 @@ pub mod pallet {
 		}
 	}
 
-	impl<T: Config> Contains<T::RuntimeCall> for Pallet<T> {
+	impl<T: Config> Pallet<T> {
+		/// Leave maintenance mode if it was entered for a limited duration which is now over.
+		/// Returns the consumed weight.
+		/// This is only called from `ExecutiveHooks::on_initialize`, before the hooks of the block
+		/// are selected.
+		pub(crate) fn resume_normal_operation_if_expired(n: BlockNumberFor<T>) -> Weight {
+			match MaintenanceModeEnd::<T>::get() {
+				Some(end) if n >= end => {
+					Self::do_resume_normal_operation();
+					<Pallet<T>>::deposit_event(Event::MaintenanceModeExpired);
+
+					T::DbWeight::get().reads_writes(1, 4)
+				}
+				_ => T::DbWeight::get().reads(1),
+			}
+		}
+
+		/// Enter maintenance mode if it was requested automatically during the previous block.
+		/// Returns the consumed weight.
+		/// This is only called from `ExecutiveHooks::on_initialize`, before the hooks of the block
+		/// are selected.
+		pub(crate) fn enter_pending_maintenance_mode() -> Weight {
+			match PendingMaintenanceMode::<T>::take() {
+				// Maintenance mode was entered explicitly in the meantime
+				Some(_) if MaintenanceMode::<T>::get() => T::DbWeight::get().reads_writes(2, 1),
+				Some(reason) => {
+					Self::do_enter_maintenance_mode(None);
+					<Pallet<T>>::deposit_event(Event::EnteredMaintenanceModeAutomatically {
+						reason,
+					});
+
+					T::DbWeight::get().reads_writes(2, 4)
+				}
+				None => T::DbWeight::get().reads(1),
+			}
+		}
+
+		/// Whether the given call is paused, either individually or along with all the calls of
+		/// its pallet
+		pub fn is_paused(call: &T::RuntimeCall) -> bool
+		where
+			T::RuntimeCall: GetCallMetadata,
+		{
+			let CallMetadata {
+				pallet_name,
+				function_name,
+			} = call.get_call_metadata();
+
+			// Names that are too long cannot have been paused
+			let pallet_name = match Self::bounded_name(pallet_name.as_bytes()) {
+				Ok(name) => name,
+				Err(_) => return false,
+			};
+			if PausedPallets::<T>::contains_key(&pallet_name) {
+				return true;
+			}
+			match Self::bounded_name(function_name.as_bytes()) {
+				Ok(function_name) => PausedCalls::<T>::contains_key(pallet_name, function_name),
+				Err(_) => false,
+			}
+		}
+
+		/// The weight of handling the given pallet and call names, which are only bounded once
+		/// they have been decoded. It is charged as a remark of the same length, which is also
+		/// dominated by copying the bytes around.
+		fn names_weight(pallet_name: &[u8], call_name: &Option<Vec<u8>>) -> Weight {
+			let len = pallet_name
+				.len()
+				.saturating_add(call_name.as_ref().map_or(0, |name| name.len()));
+			<T as frame_system::Config>::SystemWeightInfo::remark(
+				len.try_into().unwrap_or(u32::MAX),
+			)
+		}
+
+		fn bounded_name(name: &[u8]) -> Result<BoundedVec<u8, T::MaxNameLength>, Error<T>> {
+			name.to_vec()
+				.try_into()
+				.map_err(|_| Error::<T>::NameTooLong)
+		}
+
+		fn do_enter_maintenance_mode(end: Option<BlockNumberFor<T>>) {
+			// Write to storage
+			MaintenanceMode::<T>::put(true);
+			if let Some(end) = end {
+				MaintenanceModeEnd::<T>::put(end);
+			}
+			// Suspend XCM execution
+			#[cfg(feature = "xcm-support")]
+			if let Err(error) = T::XcmExecutionManager::suspend_xcm_execution() {
+				<Pallet<T>>::deposit_event(Event::FailedToSuspendIdleXcmExecution { error });
+			}
+		}
+
+		fn do_resume_normal_operation() {
+			// Write to storage
+			MaintenanceMode::<T>::put(false);
+			MaintenanceModeEnd::<T>::kill();
+			// Resume XCM execution
+			#[cfg(feature = "xcm-support")]
+			if let Err(error) = T::XcmExecutionManager::resume_xcm_execution() {
+				<Pallet<T>>::deposit_event(Event::FailedToResumeIdleXcmExecution { error });
+			}
+		}
+
+		/// Whether the maintenance variants of the call filter, DMP handler and executive hooks
+		/// must be used. This is the case when the chain was put into maintenance mode, or while
+		/// migrations are still pending.
+		pub fn is_maintenance_mode_active() -> bool {
+			MaintenanceMode::<T>::get() || T::PendingMigrations::get()
+		}
+	}
+
+	impl<T: Config> OnTimestampSet<T::Moment> for Pallet<T> {
+		fn on_timestamp_set(now: T::Moment) {
+			let mut weight = T::DbWeight::get().reads_writes(1, 1);
+
+			if let Some(previous) = PreviousBlockTimestamp::<T>::get() {
+				weight = weight.saturating_add(T::DbWeight::get().reads(1));
+
+				if now.saturating_sub(previous) > T::MaxBlockGap::get()
+					&& !MaintenanceMode::<T>::get()
+				{
+					// Switching now would change the call filter in the middle of the block
+					PendingMaintenanceMode::<T>::put(MaintenanceReason::BlockProductionGap);
+					weight = weight.saturating_add(T::DbWeight::get().writes(1));
+				}
+			}
+
+			PreviousBlockTimestamp::<T>::put(now);
+
+			frame_system::Pallet::<T>::register_extra_weight_unchecked(
+				weight,
+				DispatchClass::Mandatory,
+			);
+		}
+	}
+
+	impl<T: Config> Contains<T::RuntimeCall> for Pallet<T>
+	where
+		T::RuntimeCall: GetCallMetadata,
+	{
 		fn contains(call: &T::RuntimeCall) -> bool {
-			if MaintenanceMode::<T>::get() {
+			if Self::is_paused(call) {
+				false
+			} else if Self::is_maintenance_mode_active() {
 				T::MaintenanceCallFilter::contains(call)
 			} else {
 				T::NormalCallFilter::contains(call)
 			}
 		}
 	}
-	#[cfg(feature = "xcm-support")]
-	impl<T: Config> DmpMessageHandler for Pallet<T> {
-		fn handle_dmp_messages(
-			iter: impl Iterator<Item = (RelayBlockNumber, Vec<u8>)>,
-			limit: Weight,
-		) -> Weight {
-			if MaintenanceMode::<T>::get() {
-				T::MaintenanceDmpHandler::handle_dmp_messages(iter, limit)
-			} else {
-				T::NormalDmpHandler::handle_dmp_messages(iter, limit)
-			}
+	/// Meant to be used as `pallet_message_queue::Config::QueuePausedQuery`, so that no queue is
+	/// serviced in maintenance mode. The messages stay in their queues until normal operation is
+	/// resumed.
+	impl<T: Config, Origin> QueuePausedQuery<Origin> for Pallet<T> {
+		fn is_paused(_origin: &Origin) -> bool {
+			Self::is_maintenance_mode_active()
 		}
 	}
 }
@@ -17,15 +17,17 @@
 //! A minimal runtime including the maintenance-mode pallet
 use super::*;
 use crate as pallet_maintenance_mode;
-use cumulus_primitives_core::{relay_chain::BlockNumber as RelayBlockNumber, DmpMessageHandler};
 use frame_support::{
-	construct_runtime, parameter_types,
+	construct_runtime, ord_parameter_types, parameter_types,
 	traits::{
-		Contains, Everything, OffchainWorker, OnFinalize, OnIdle, OnInitialize, OnRuntimeUpgrade,
+		ConstU32, Contains, EitherOfDiverse, Everything, OffchainWorker, OnFinalize, OnIdle,
+		OnInitialize, OnRuntimeUpgrade, ProcessMessage, ProcessMessageError,
 	},
-	weights::Weight,
+	weights::{Weight, WeightMeter},
 };
-use frame_system::EnsureRoot;
+use frame_system::{EnsureRoot, EnsureSignedBy};
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
 use sp_core::H256;
 use sp_runtime::{
 	traits::{BlakeTwo256, IdentityLookup},
@@ -43,8 +45,9 @@ construct_runtime!(
 	pub enum Test
 	{
 		System: frame_system::{Pallet, Call, Config<T>, Storage, Event<T>},
-		MaintenanceMode: pallet_maintenance_mode::{Pallet, Call, Storage, Event, Config<T>},
+		MaintenanceMode: pallet_maintenance_mode::{Pallet, Call, Storage, Event<T>, Config<T>},
 		MockPalletMaintenanceHooks: mock_pallet_maintenance_hooks::{Pallet, Call, Event},
+		MessageQueue: pallet_message_queue::{Pallet, Call, Storage, Event<T>},
 	}
 );
 
@@ -89,32 +92,48 @@ impl Contains<RuntimeCall> for MaintenanceCallFilter {
 	}
 }
 
-pub struct MaintenanceDmpHandler;
-#[cfg(feature = "xcm-support")]
-impl DmpMessageHandler for MaintenanceDmpHandler {
-	// This implementation makes messages be queued
-	// Since the limit is 0, messages are queued for next iteration
-	fn handle_dmp_messages(
-		_iter: impl Iterator<Item = (RelayBlockNumber, Vec<u8>)>,
-		_limit: Weight,
-	) -> Weight {
-		return Weight::from_parts(1, 0);
-	}
+/// The origin of the messages handled by the message queue: the relay chain for DMP, or a
+/// sibling parachain for HRMP and XCMP
+#[derive(Encode, Decode, MaxEncodedLen, Clone, Copy, Eq, PartialEq, TypeInfo, Debug)]
+pub enum MessageOrigin {
+	Parent,
+	Sibling(u32),
 }
 
-pub struct NormalDmpHandler;
-#[cfg(feature = "xcm-support")]
-impl DmpMessageHandler for NormalDmpHandler {
-	// This implementation makes messages be queued
-	// Since the limit is 0, messages are queued for next iteration
-	fn handle_dmp_messages(
-		_iter: impl Iterator<Item = (RelayBlockNumber, Vec<u8>)>,
-		_limit: Weight,
-	) -> Weight {
-		Weight::zero()
+parameter_types! {
+	pub storage ProcessedMessages: Vec<(Vec<u8>, MessageOrigin)> = Vec::new();
+}
+
+/// Records every processed message in ProcessedMessages
+pub struct RecordingMessageProcessor;
+impl ProcessMessage for RecordingMessageProcessor {
+	type Origin = MessageOrigin;
+
+	fn process_message(
+		message: &[u8],
+		origin: Self::Origin,
+		_meter: &mut WeightMeter,
+		_id: &mut [u8; 32],
+	) -> Result<bool, ProcessMessageError> {
+		let mut processed = ProcessedMessages::get();
+		processed.push((message.to_vec(), origin));
+		ProcessedMessages::set(&processed);
+		Ok(true)
 	}
 }
 
+impl pallet_message_queue::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type MessageProcessor = MaintenanceModeMessageProcessor<Test, RecordingMessageProcessor>;
+	type Size = u32;
+	type QueueChangeHandler = ();
+	type QueuePausedQuery = MaintenanceMode;
+	type HeapSize = ConstU32<64>;
+	type MaxStale = ConstU32<8>;
+	type ServiceWeight = ();
+}
+
 impl mock_pallet_maintenance_hooks::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 }
@@ -241,17 +260,37 @@ impl OffchainWorker<BlockNumber> for NormalHooks {
 	}
 }
 
+parameter_types! {
+	pub storage PendingMigrations: bool = false;
+	pub const MaxBlockGap: u64 = 60_000;
+}
+
+/// Stands for the governance pallets of a real runtime, which must never be paused
+pub struct UnpausablePallets;
+impl Contains<Vec<u8>> for UnpausablePallets {
+	fn contains(pallet_name: &Vec<u8>) -> bool {
+		pallet_name.as_slice() == b"MockPalletMaintenanceHooks"
+	}
+}
+
+ord_parameter_types! {
+	pub const TechCommitteeMember: AccountId = 1;
+}
+
 impl Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type NormalCallFilter = Everything;
 	type MaintenanceCallFilter = MaintenanceCallFilter;
-	type MaintenanceOrigin = EnsureRoot<AccountId>;
+	type EnterMaintenanceOrigin =
+		EitherOfDiverse<EnsureRoot<AccountId>, EnsureSignedBy<TechCommitteeMember, AccountId>>;
+	type ResumeNormalOrigin = EnsureRoot<AccountId>;
+	type PendingMigrations = PendingMigrations;
+	type Moment = u64;
+	type MaxBlockGap = MaxBlockGap;
+	type MaxNameLength = frame_support::traits::ConstU32<32>;
+	type UnpausablePallets = UnpausablePallets;
 	#[cfg(feature = "xcm-support")]
 	type XcmExecutionManager = ();
-	#[cfg(feature = "xcm-support")]
-	type NormalDmpHandler = NormalDmpHandler;
-	#[cfg(feature = "xcm-support")]
-	type MaintenanceDmpHandler = MaintenanceDmpHandler;
 	type NormalExecutiveHooks = NormalHooks;
 	type MaintenanceExecutiveHooks = MaintenanceHooks;
 }
@@ -295,7 +334,7 @@ impl ExtBuilder {
 	}
 }
 
-pub(crate) fn events() -> Vec<pallet_maintenance_mode::Event> {
+pub(crate) fn events() -> Vec<pallet_maintenance_mode::Event<Test>> {
 	System::events()
 		.into_iter()
 		.map(|r| r.event)
@@ -322,3 +361,12 @@ pub(crate) fn mock_events() -> Vec<mock_pallet_maintenance_hooks::Event> {
 		})
 		.collect::<Vec<_>>()
 }
+
+pub(crate) fn roll_to(block_number: BlockNumber) {
+	while System::block_number() < block_number {
+		System::set_block_number(System::block_number() + 1);
+		System::on_initialize(System::block_number());
+		ExecutiveHooks::<Test>::on_initialize(System::block_number());
+		System::on_finalize(System::block_number());
+	}
+}
@@ -16,16 +16,24 @@
 
 //! Unit testing
 use crate::mock::{
-	events, mock_events, ExtBuilder, MaintenanceMode, RuntimeCall as OuterCall, RuntimeOrigin, Test,
+	events, mock_events, roll_to, ExtBuilder, MaintenanceMode, MessageOrigin, MessageQueue,
+	PendingMigrations, ProcessedMessages, RecordingMessageProcessor, RuntimeCall as OuterCall,
+	RuntimeOrigin, Test,
+};
+use crate::{
+	Call, Error, Event, ExecutiveHooks, MaintenanceModeMessageProcessor, MaintenanceReason,
 };
-use crate::{Call, Error, Event, ExecutiveHooks};
-use cumulus_primitives_core::DmpMessageHandler;
 use frame_support::{
 	assert_noop, assert_ok,
-	traits::{OffchainWorker, OnFinalize, OnIdle, OnInitialize, OnRuntimeUpgrade},
-	weights::Weight,
+	dispatch::GetDispatchInfo,
+	traits::{
+		EnqueueMessage, OffchainWorker, OnFinalize, OnIdle, OnInitialize, OnRuntimeUpgrade,
+		OnTimestampSet, ProcessMessage, ProcessMessageError, QueuePausedQuery, ServiceQueues,
+	},
+	weights::{Weight, WeightMeter},
+	BoundedSlice,
 };
-use sp_runtime::traits::Dispatchable;
+use sp_runtime::{traits::Dispatchable, DispatchError::BadOrigin};
 #[test]
 fn can_remark_during_normal_operation() {
 	ExtBuilder::default().build().execute_with(|| {
@@ -51,7 +59,7 @@ fn cannot_remark_during_maintenance_mode() {
 #[test]
 fn can_enter_maintenance_mode() {
 	ExtBuilder::default().build().execute_with(|| {
-		let call: OuterCall = Call::enter_maintenance_mode {}.into();
+		let call: OuterCall = Call::enter_maintenance_mode { duration: None }.into();
 		assert_ok!(call.dispatch(RuntimeOrigin::root()));
 
 		assert_eq!(events(), vec![Event::EnteredMaintenanceMode,]);
@@ -64,7 +72,7 @@ fn cannot_enter_maintenance_mode_from_wrong_origin() {
 		.with_maintenance_mode(true)
 		.build()
 		.execute_with(|| {
-			let call: OuterCall = Call::enter_maintenance_mode {}.into();
+			let call: OuterCall = Call::enter_maintenance_mode { duration: None }.into();
 			assert_noop!(
 				call.dispatch(RuntimeOrigin::signed(1)),
 				frame_system::Error::<Test>::CallFiltered
@@ -72,13 +80,61 @@ fn cannot_enter_maintenance_mode_from_wrong_origin() {
 		})
 }
 
+#[test]
+fn can_enter_maintenance_mode_from_enter_origin() {
+	ExtBuilder::default().build().execute_with(|| {
+		let call: OuterCall = Call::enter_maintenance_mode { duration: None }.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::signed(1)));
+
+		assert_eq!(events(), vec![Event::EnteredMaintenanceMode,]);
+	})
+}
+
+#[test]
+fn cannot_enter_maintenance_mode_from_other_signed_origin() {
+	ExtBuilder::default().build().execute_with(|| {
+		let call: OuterCall = Call::enter_maintenance_mode { duration: None }.into();
+		assert_noop!(call.dispatch(RuntimeOrigin::signed(2)), BadOrigin);
+	})
+}
+
+#[test]
+fn cannot_resume_normal_operation_from_enter_origin() {
+	ExtBuilder::default()
+		.with_maintenance_mode(true)
+		.build()
+		.execute_with(|| {
+			// Call the pallet directly, so that the call filter does not hide the origin check
+			assert_noop!(
+				MaintenanceMode::resume_normal_operation(RuntimeOrigin::signed(1)),
+				BadOrigin
+			);
+		})
+}
+
+#[test]
+fn cannot_extend_maintenance_mode_from_enter_origin() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(MaintenanceMode::enter_maintenance_mode(
+			RuntimeOrigin::signed(1),
+			Some(2)
+		));
+
+		// Call the pallet directly, so that the call filter does not hide the origin check
+		assert_noop!(
+			MaintenanceMode::extend_maintenance_mode(RuntimeOrigin::signed(1), 5),
+			BadOrigin
+		);
+	})
+}
+
 #[test]
 fn cannot_enter_maintenance_mode_when_already_in_it() {
 	ExtBuilder::default()
 		.with_maintenance_mode(true)
 		.build()
 		.execute_with(|| {
-			let call: OuterCall = Call::enter_maintenance_mode {}.into();
+			let call: OuterCall = Call::enter_maintenance_mode { duration: None }.into();
 			assert_noop!(
 				call.dispatch(RuntimeOrigin::root()),
 				Error::<Test>::AlreadyInMaintenanceMode
@@ -124,31 +180,83 @@ fn cannot_resume_normal_operation_while_already_operating_normally() {
 	})
 }
 
-#[cfg(feature = "xcm-support")]
 #[test]
-fn normal_dmp_in_non_maintenance() {
+fn messages_are_processed_in_non_maintenance() {
 	ExtBuilder::default()
 		.with_maintenance_mode(false)
 		.build()
 		.execute_with(|| {
+			MessageQueue::enqueue_message(
+				BoundedSlice::truncate_from(&b"dmp"[..]),
+				MessageOrigin::Parent,
+			);
+			MessageQueue::enqueue_message(
+				BoundedSlice::truncate_from(&b"xcmp"[..]),
+				MessageOrigin::Sibling(2000),
+			);
+			MessageQueue::service_queues(Weight::MAX);
+
+			let processed = ProcessedMessages::get();
+			assert_eq!(processed.len(), 2);
+			assert!(processed.contains(&(b"dmp".to_vec(), MessageOrigin::Parent)));
+			assert!(processed.contains(&(b"xcmp".to_vec(), MessageOrigin::Sibling(2000))));
+		})
+}
+
+#[test]
+fn messages_are_kept_in_maintenance() {
+	ExtBuilder::default()
+		.with_maintenance_mode(true)
+		.build()
+		.execute_with(|| {
+			MessageQueue::enqueue_message(
+				BoundedSlice::truncate_from(&b"dmp"[..]),
+				MessageOrigin::Parent,
+			);
+			MessageQueue::enqueue_message(
+				BoundedSlice::truncate_from(&b"xcmp"[..]),
+				MessageOrigin::Sibling(2000),
+			);
+			MessageQueue::service_queues(Weight::MAX);
+
+			assert!(ProcessedMessages::get().is_empty());
+			assert_eq!(MessageQueue::footprint(MessageOrigin::Parent).count, 1);
 			assert_eq!(
-				MaintenanceMode::handle_dmp_messages(vec![].into_iter(), Weight::from_parts(1, 0)),
-				Weight::zero()
+				MessageQueue::footprint(MessageOrigin::Sibling(2000)).count,
+				1
+			);
+
+			// Messages are processed once normal operation is resumed
+			assert_ok!(MaintenanceMode::resume_normal_operation(
+				RuntimeOrigin::root()
+			));
+			MessageQueue::service_queues(Weight::MAX);
+
+			assert_eq!(ProcessedMessages::get().len(), 2);
+			assert_eq!(MessageQueue::footprint(MessageOrigin::Parent).count, 0);
+			assert_eq!(
+				MessageQueue::footprint(MessageOrigin::Sibling(2000)).count,
+				0
 			);
 		})
 }
 
-#[cfg(feature = "xcm-support")]
 #[test]
-fn maintenance_dmp_in_maintenance() {
+fn message_processor_yields_in_maintenance() {
 	ExtBuilder::default()
 		.with_maintenance_mode(true)
 		.build()
 		.execute_with(|| {
 			assert_eq!(
-				MaintenanceMode::handle_dmp_messages(vec![].into_iter(), Weight::from_parts(1, 0)),
-				Weight::from_parts(1, 0)
+				MaintenanceModeMessageProcessor::<Test, RecordingMessageProcessor>::process_message(
+					b"dmp",
+					MessageOrigin::Parent,
+					&mut WeightMeter::from_limit(Weight::MAX),
+					&mut [0u8; 32],
+				),
+				Err(ProcessMessageError::Yield)
 			);
+			assert!(ProcessedMessages::get().is_empty());
 		})
 }
 
@@ -213,3 +321,459 @@ fn maintenance_hooks_in_maintenance() {
 			);
 		})
 }
+
+#[test]
+fn cannot_remark_while_migrations_are_pending() {
+	ExtBuilder::default().build().execute_with(|| {
+		PendingMigrations::set(&true);
+
+		let call: OuterCall = frame_system::Call::remark { remark: vec![] }.into();
+		assert_noop!(
+			call.dispatch(RuntimeOrigin::signed(1)),
+			frame_system::Error::<Test>::CallFiltered
+		);
+	})
+}
+
+#[test]
+fn can_remark_once_migrations_are_completed() {
+	ExtBuilder::default().build().execute_with(|| {
+		PendingMigrations::set(&true);
+		assert!(MaintenanceMode::is_maintenance_mode_active());
+
+		PendingMigrations::set(&false);
+		assert!(!MaintenanceMode::is_maintenance_mode_active());
+
+		let call: OuterCall = frame_system::Call::remark { remark: vec![] }.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::signed(1)));
+	})
+}
+
+#[test]
+fn stays_in_maintenance_mode_after_migrations_if_entered_explicitly() {
+	ExtBuilder::default()
+		.with_maintenance_mode(true)
+		.build()
+		.execute_with(|| {
+			PendingMigrations::set(&true);
+			PendingMigrations::set(&false);
+
+			assert!(MaintenanceMode::is_maintenance_mode_active());
+		})
+}
+
+#[test]
+fn message_queues_paused_while_migrations_are_pending() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert!(
+			!<MaintenanceMode as QueuePausedQuery<MessageOrigin>>::is_paused(
+				&MessageOrigin::Parent
+			)
+		);
+
+		PendingMigrations::set(&true);
+
+		assert!(
+			<MaintenanceMode as QueuePausedQuery<MessageOrigin>>::is_paused(&MessageOrigin::Parent)
+		);
+	})
+}
+
+#[test]
+fn maintenance_hooks_while_migrations_are_pending() {
+	ExtBuilder::default().build().execute_with(|| {
+		PendingMigrations::set(&true);
+
+		assert_eq!(
+			ExecutiveHooks::<Test>::on_initialize(0),
+			Weight::from_parts(1, 0)
+		);
+		ExecutiveHooks::<Test>::on_finalize(0);
+
+		assert_eq!(
+			mock_events(),
+			[
+				crate::mock::mock_pallet_maintenance_hooks::Event::MaintenanceOnInitialize,
+				crate::mock::mock_pallet_maintenance_hooks::Event::MaintenanceOnFinalize,
+			]
+		);
+	})
+}
+
+#[test]
+fn can_enter_maintenance_mode_for_a_duration() {
+	ExtBuilder::default().build().execute_with(|| {
+		let call: OuterCall = Call::enter_maintenance_mode { duration: Some(3) }.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::root()));
+		assert_eq!(MaintenanceMode::maintenance_mode_end(), Some(4));
+
+		roll_to(3);
+		assert!(MaintenanceMode::maintenance_mode());
+
+		roll_to(4);
+		assert!(!MaintenanceMode::maintenance_mode());
+		assert_eq!(MaintenanceMode::maintenance_mode_end(), None);
+		assert_eq!(
+			events(),
+			vec![Event::EnteredMaintenanceMode, Event::MaintenanceModeExpired]
+		);
+	})
+}
+
+#[test]
+fn can_extend_maintenance_mode() {
+	ExtBuilder::default().build().execute_with(|| {
+		let call: OuterCall = Call::enter_maintenance_mode { duration: Some(2) }.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::root()));
+
+		let call: OuterCall = Call::extend_maintenance_mode { duration: 5 }.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::root()));
+		assert_eq!(MaintenanceMode::maintenance_mode_end(), Some(8));
+
+		roll_to(7);
+		assert!(MaintenanceMode::maintenance_mode());
+
+		roll_to(8);
+		assert!(!MaintenanceMode::maintenance_mode());
+		assert_eq!(
+			events(),
+			vec![
+				Event::EnteredMaintenanceMode,
+				Event::MaintenanceModeExtended { end: 8 },
+				Event::MaintenanceModeExpired
+			]
+		);
+	})
+}
+
+#[test]
+fn cannot_extend_maintenance_mode_from_wrong_origin() {
+	ExtBuilder::default().build().execute_with(|| {
+		let call: OuterCall = Call::enter_maintenance_mode { duration: Some(2) }.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::root()));
+
+		let call: OuterCall = Call::extend_maintenance_mode { duration: 5 }.into();
+		assert_noop!(
+			call.dispatch(RuntimeOrigin::signed(1)),
+			frame_system::Error::<Test>::CallFiltered
+		);
+	})
+}
+
+#[test]
+fn cannot_extend_maintenance_mode_without_duration() {
+	ExtBuilder::default()
+		.with_maintenance_mode(true)
+		.build()
+		.execute_with(|| {
+			let call: OuterCall = Call::extend_maintenance_mode { duration: 5 }.into();
+			assert_noop!(
+				call.dispatch(RuntimeOrigin::root()),
+				Error::<Test>::MaintenanceModeNotTimed
+			);
+		})
+}
+
+#[test]
+fn cannot_extend_maintenance_mode_while_operating_normally() {
+	ExtBuilder::default().build().execute_with(|| {
+		let call: OuterCall = Call::extend_maintenance_mode { duration: 5 }.into();
+		assert_noop!(
+			call.dispatch(RuntimeOrigin::root()),
+			Error::<Test>::NotInMaintenanceMode
+		);
+	})
+}
+
+#[test]
+fn resuming_normal_operation_clears_maintenance_mode_end() {
+	ExtBuilder::default().build().execute_with(|| {
+		let call: OuterCall = Call::enter_maintenance_mode { duration: Some(2) }.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::root()));
+		let call: OuterCall = Call::resume_normal_operation {}.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::root()));
+		assert_eq!(MaintenanceMode::maintenance_mode_end(), None);
+
+		// Entering again without duration must not be affected by the previous end
+		let call: OuterCall = Call::enter_maintenance_mode { duration: None }.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::root()));
+
+		roll_to(5);
+		assert!(MaintenanceMode::maintenance_mode());
+	})
+}
+
+#[test]
+fn normal_hooks_in_block_where_maintenance_mode_expires() {
+	ExtBuilder::default().build().execute_with(|| {
+		let call: OuterCall = Call::enter_maintenance_mode { duration: Some(1) }.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::root()));
+
+		assert_eq!(ExecutiveHooks::<Test>::on_initialize(2), Weight::zero());
+		assert_eq!(
+			mock_events(),
+			[crate::mock::mock_pallet_maintenance_hooks::Event::NormalOnInitialize]
+		);
+	})
+}
+
+#[test]
+fn stores_previous_block_timestamp() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(MaintenanceMode::previous_block_timestamp(), None);
+
+		MaintenanceMode::on_timestamp_set(6_000);
+		assert_eq!(MaintenanceMode::previous_block_timestamp(), Some(6_000));
+
+		MaintenanceMode::on_timestamp_set(12_000);
+		assert_eq!(MaintenanceMode::previous_block_timestamp(), Some(12_000));
+		assert!(!MaintenanceMode::maintenance_mode());
+	})
+}
+
+#[test]
+fn enters_maintenance_mode_after_long_block_gap() {
+	ExtBuilder::default().build().execute_with(|| {
+		MaintenanceMode::on_timestamp_set(6_000);
+		// Exactly MaxBlockGap is still tolerated
+		MaintenanceMode::on_timestamp_set(66_000);
+		assert!(!MaintenanceMode::maintenance_mode());
+
+		MaintenanceMode::on_timestamp_set(126_001);
+		assert_eq!(
+			MaintenanceMode::pending_maintenance_mode(),
+			Some(MaintenanceReason::BlockProductionGap)
+		);
+
+		roll_to(2);
+		assert!(MaintenanceMode::maintenance_mode());
+		assert_eq!(MaintenanceMode::maintenance_mode_end(), None);
+		assert_eq!(MaintenanceMode::pending_maintenance_mode(), None);
+		assert_eq!(
+			events(),
+			vec![Event::EnteredMaintenanceModeAutomatically {
+				reason: MaintenanceReason::BlockProductionGap
+			}]
+		);
+	})
+}
+
+#[test]
+fn long_block_gap_does_not_change_call_filter_within_the_block() {
+	ExtBuilder::default().build().execute_with(|| {
+		MaintenanceMode::on_timestamp_set(6_000);
+		MaintenanceMode::on_timestamp_set(600_000);
+
+		assert!(!MaintenanceMode::maintenance_mode());
+		let call: OuterCall = frame_system::Call::remark { remark: vec![] }.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::signed(1)));
+
+		assert_eq!(
+			ExecutiveHooks::<Test>::on_initialize(2),
+			Weight::from_parts(1, 0)
+		);
+		assert!(MaintenanceMode::maintenance_mode());
+		assert_eq!(
+			mock_events(),
+			[crate::mock::mock_pallet_maintenance_hooks::Event::MaintenanceOnInitialize]
+		);
+	})
+}
+
+#[test]
+fn long_block_gap_in_maintenance_mode_emits_no_event() {
+	ExtBuilder::default()
+		.with_maintenance_mode(true)
+		.build()
+		.execute_with(|| {
+			MaintenanceMode::on_timestamp_set(6_000);
+			MaintenanceMode::on_timestamp_set(600_000);
+
+			assert!(MaintenanceMode::maintenance_mode());
+			assert_eq!(events(), vec![]);
+		})
+}
+
+#[test]
+fn can_pause_and_unpause_a_call() {
+	ExtBuilder::default().build().execute_with(|| {
+		let call: OuterCall = Call::pause_calls {
+			pallet_name: b"System".to_vec(),
+			call_name: Some(b"remark".to_vec()),
+		}
+		.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::signed(1)));
+
+		let remark: OuterCall = frame_system::Call::remark { remark: vec![] }.into();
+		assert_noop!(
+			remark.clone().dispatch(RuntimeOrigin::signed(1)),
+			frame_system::Error::<Test>::CallFiltered
+		);
+		// Other calls of the pallet are not paused
+		let remark_with_event: OuterCall =
+			frame_system::Call::remark_with_event { remark: vec![] }.into();
+		assert_ok!(remark_with_event.dispatch(RuntimeOrigin::signed(1)));
+
+		let call: OuterCall = Call::unpause_calls {
+			pallet_name: b"System".to_vec(),
+			call_name: Some(b"remark".to_vec()),
+		}
+		.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::root()));
+		assert_ok!(remark.dispatch(RuntimeOrigin::signed(1)));
+
+		assert_eq!(
+			events(),
+			vec![
+				Event::CallsPaused {
+					pallet_name: b"System".to_vec(),
+					call_name: Some(b"remark".to_vec()),
+				},
+				Event::CallsUnpaused {
+					pallet_name: b"System".to_vec(),
+					call_name: Some(b"remark".to_vec()),
+				}
+			]
+		);
+	})
+}
+
+#[test]
+fn can_pause_and_unpause_a_pallet() {
+	ExtBuilder::default().build().execute_with(|| {
+		let call: OuterCall = Call::pause_calls {
+			pallet_name: b"System".to_vec(),
+			call_name: None,
+		}
+		.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::signed(1)));
+
+		let remark_with_event: OuterCall =
+			frame_system::Call::remark_with_event { remark: vec![] }.into();
+		assert_noop!(
+			remark_with_event.clone().dispatch(RuntimeOrigin::signed(1)),
+			frame_system::Error::<Test>::CallFiltered
+		);
+
+		let call: OuterCall = Call::unpause_calls {
+			pallet_name: b"System".to_vec(),
+			call_name: None,
+		}
+		.into();
+		assert_ok!(call.dispatch(RuntimeOrigin::root()));
+		assert_ok!(remark_with_event.dispatch(RuntimeOrigin::signed(1)));
+	})
+}
+
+#[test]
+fn cannot_pause_maintenance_mode_pallet() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			MaintenanceMode::pause_calls(RuntimeOrigin::root(), b"MaintenanceMode".to_vec(), None),
+			Error::<Test>::CannotPauseThisPallet
+		);
+	})
+}
+
+#[test]
+fn cannot_pause_unpausable_pallets() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			MaintenanceMode::pause_calls(
+				RuntimeOrigin::root(),
+				b"MockPalletMaintenanceHooks".to_vec(),
+				None
+			),
+			Error::<Test>::CannotPauseThisPallet
+		);
+		assert_noop!(
+			MaintenanceMode::pause_calls(
+				RuntimeOrigin::root(),
+				b"MockPalletMaintenanceHooks".to_vec(),
+				Some(b"call".to_vec())
+			),
+			Error::<Test>::CannotPauseThisPallet
+		);
+	})
+}
+
+#[test]
+fn pause_calls_weight_grows_with_name_length() {
+	let short: OuterCall = Call::pause_calls {
+		pallet_name: b"System".to_vec(),
+		call_name: None,
+	}
+	.into();
+	let long: OuterCall = Call::pause_calls {
+		pallet_name: b"System".to_vec(),
+		call_name: Some(vec![b'a'; 1_000]),
+	}
+	.into();
+	assert!(
+		long.get_dispatch_info().weight.ref_time() > short.get_dispatch_info().weight.ref_time()
+	);
+}
+
+#[test]
+fn cannot_pause_calls_with_too_long_name() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			MaintenanceMode::pause_calls(RuntimeOrigin::root(), vec![b'a'; 33], None),
+			Error::<Test>::NameTooLong
+		);
+	})
+}
+
+#[test]
+fn cannot_pause_calls_twice() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(MaintenanceMode::pause_calls(
+			RuntimeOrigin::root(),
+			b"System".to_vec(),
+			Some(b"remark".to_vec())
+		));
+		assert_noop!(
+			MaintenanceMode::pause_calls(
+				RuntimeOrigin::root(),
+				b"System".to_vec(),
+				Some(b"remark".to_vec())
+			),
+			Error::<Test>::AlreadyPaused
+		);
+	})
+}
+
+#[test]
+fn cannot_unpause_calls_that_are_not_paused() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(MaintenanceMode::pause_calls(
+			RuntimeOrigin::root(),
+			b"System".to_vec(),
+			None
+		));
+		// Pausing a whole pallet doesn't pause the call individually
+		assert_noop!(
+			MaintenanceMode::unpause_calls(
+				RuntimeOrigin::root(),
+				b"System".to_vec(),
+				Some(b"remark".to_vec())
+			),
+			Error::<Test>::NotPaused
+		);
+	})
+}
+
+#[test]
+fn cannot_unpause_calls_from_enter_origin() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(MaintenanceMode::pause_calls(
+			RuntimeOrigin::signed(1),
+			b"System".to_vec(),
+			None
+		));
+		assert_noop!(
+			MaintenanceMode::unpause_calls(RuntimeOrigin::signed(1), b"System".to_vec(), None),
+			BadOrigin
+		);
+	})
+}
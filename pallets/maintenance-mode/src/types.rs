@@ -19,11 +19,24 @@
 // hooks in each of the normal and maintenance modes.
 use super::*;
 use frame_support::{
-	traits::{OffchainWorker, OnFinalize, OnIdle, OnInitialize, OnRuntimeUpgrade},
-	weights::Weight,
+	traits::{
+		OffchainWorker, OnFinalize, OnIdle, OnInitialize, OnRuntimeUpgrade, ProcessMessage,
+		ProcessMessageError,
+	},
+	weights::{Weight, WeightMeter},
 };
 use frame_system::pallet_prelude::BlockNumberFor as BlockNumberOf;
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
 use sp_std::marker::PhantomData;
+
+/// The reason why the chain was automatically put into maintenance mode
+#[derive(Clone, Copy, Encode, Decode, MaxEncodedLen, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum MaintenanceReason {
+	/// The time elapsed since the previous block exceeded the configured MaxBlockGap
+	BlockProductionGap,
+}
 #[cfg(feature = "try-runtime")]
 use sp_std::vec::Vec;
 
@@ -34,7 +47,7 @@ where
 	T: Config,
 {
 	fn on_idle(n: BlockNumberOf<T>, remaining_weight: Weight) -> Weight {
-		if Pallet::<T>::maintenance_mode() {
+		if Pallet::<T>::is_maintenance_mode_active() {
 			T::MaintenanceExecutiveHooks::on_idle(n, remaining_weight)
 		} else {
 			T::NormalExecutiveHooks::on_idle(n, remaining_weight)
@@ -47,11 +60,16 @@ where
 	T: Config,
 {
 	fn on_initialize(n: BlockNumberOf<T>) -> Weight {
-		if Pallet::<T>::maintenance_mode() {
+		// Apply the pending mode changes first, so that the whole block runs with the same
+		// set of hooks
+		let weight = Pallet::<T>::resume_normal_operation_if_expired(n)
+			.saturating_add(Pallet::<T>::enter_pending_maintenance_mode());
+
+		weight.saturating_add(if Pallet::<T>::is_maintenance_mode_active() {
 			T::MaintenanceExecutiveHooks::on_initialize(n)
 		} else {
 			T::NormalExecutiveHooks::on_initialize(n)
-		}
+		})
 	}
 }
 
@@ -60,7 +78,7 @@ where
 	T: Config,
 {
 	fn on_finalize(n: BlockNumberOf<T>) {
-		if Pallet::<T>::maintenance_mode() {
+		if Pallet::<T>::is_maintenance_mode_active() {
 			T::MaintenanceExecutiveHooks::on_finalize(n)
 		} else {
 			T::NormalExecutiveHooks::on_finalize(n)
@@ -73,7 +91,7 @@ where
 	T: Config,
 {
 	fn offchain_worker(n: BlockNumberOf<T>) {
-		if Pallet::<T>::maintenance_mode() {
+		if Pallet::<T>::is_maintenance_mode_active() {
 			T::MaintenanceExecutiveHooks::offchain_worker(n)
 		} else {
 			T::NormalExecutiveHooks::offchain_worker(n)
@@ -86,7 +104,7 @@ where
 	T: Config,
 {
 	fn on_runtime_upgrade() -> Weight {
-		if Pallet::<T>::maintenance_mode() {
+		if Pallet::<T>::is_maintenance_mode_active() {
 			T::MaintenanceExecutiveHooks::on_runtime_upgrade()
 		} else {
 			T::NormalExecutiveHooks::on_runtime_upgrade()
@@ -95,7 +113,7 @@ where
 
 	#[cfg(feature = "try-runtime")]
 	fn try_on_runtime_upgrade(checks: bool) -> Result<Weight, sp_runtime::TryRuntimeError> {
-		if Pallet::<T>::maintenance_mode() {
+		if Pallet::<T>::is_maintenance_mode_active() {
 			T::MaintenanceExecutiveHooks::try_on_runtime_upgrade(checks)
 		} else {
 			T::NormalExecutiveHooks::try_on_runtime_upgrade(checks)
@@ -114,3 +132,29 @@ impl<T: frame_system::Config> frame_support::traits::TryState<BlockNumberOf<T>>
 		Ok(())
 	}
 }
+
+/// Wraps the message processor of pallet-message-queue, so that messages are only processed in
+/// normal operating mode. In maintenance mode, processing yields and the messages are kept in
+/// their queue until normal operation is resumed.
+pub struct MaintenanceModeMessageProcessor<T, Inner>(PhantomData<(T, Inner)>);
+
+impl<T, Inner> ProcessMessage for MaintenanceModeMessageProcessor<T, Inner>
+where
+	T: Config,
+	Inner: ProcessMessage,
+{
+	type Origin = Inner::Origin;
+
+	fn process_message(
+		message: &[u8],
+		origin: Self::Origin,
+		meter: &mut WeightMeter,
+		id: &mut [u8; 32],
+	) -> Result<bool, ProcessMessageError> {
+		if Pallet::<T>::is_maintenance_mode_active() {
+			Err(ProcessMessageError::Yield)
+		} else {
+			Inner::process_message(message, origin, meter, id)
+		}
+	}
+}
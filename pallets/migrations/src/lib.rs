@@ -25,6 +25,9 @@ mod mock;
 mod tests;
 
 use frame_support::{pallet, weights::Weight};
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
 
 pub use pallet::*;
 
@@ -49,12 +52,29 @@ pub trait Migration {
 
 	/// Perform the required migration and return the weight consumed.
 	///
-	/// Currently there is no way to migrate across blocks, so this method must (1) perform its full
-	/// migration and (2) not produce a block that has gone over-weight. Not meeting these strict
-	/// constraints will lead to a bricked chain upon a runtime upgrade because the parachain will
-	/// not be able to produce a block that the relay chain will accept.
+	/// Unless `step` is overridden, this method must (1) perform its full migration and (2) not
+	/// produce a block that has gone over-weight. Not meeting these strict constraints will lead to
+	/// a bricked chain upon a runtime upgrade because the parachain will not be able to produce a
+	/// block that the relay chain will accept. Migrations that cannot fit in a single block should
+	/// implement `step` instead.
 	fn migrate(&self, available_weight: Weight) -> Weight;
 
+	/// Perform one step of a multi-block migration and return the weight consumed.
+	///
+	/// `cursor` is `None` the first time the migration is stepped, and afterwards is whatever the
+	/// previous step returned. A step must return `Some(cursor)` if there is work left to do in a
+	/// following block, or `None` once the migration has completed. The cursor is persisted in
+	/// `MigrationState` between blocks.
+	///
+	/// The default implementation performs the whole migration through `migrate` in a single step.
+	fn step(
+		&self,
+		_cursor: Option<Vec<u8>>,
+		available_weight: Weight,
+	) -> (Option<Vec<u8>>, Weight) {
+		(None, self.migrate(available_weight))
+	}
+
 	/// Run a standard pre-runtime test. This works the same way as in a normal runtime upgrade.
 	#[cfg(feature = "try-runtime")]
 	fn pre_upgrade(&self) -> Result<Vec<u8>, sp_runtime::DispatchError> {
@@ -68,6 +88,24 @@ pub trait Migration {
 	}
 }
 
+/// The progress of a migration, as stored in `MigrationState`.
+///
+/// The encoding of `Pending` and `Completed` matches the `bool` previously stored for each
+/// migration, so existing entries decode without a storage migration.
+#[derive(Clone, Default, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum MigrationStatus {
+	/// The migration has not been started yet
+	#[default]
+	#[codec(index = 0)]
+	Pending,
+	/// The migration has been fully applied
+	#[codec(index = 1)]
+	Completed,
+	/// The migration has been started and must resume from the given cursor
+	#[codec(index = 2)]
+	InProgress { cursor: Vec<u8> },
+}
+
 // The migration trait
 pub trait GetMigrations {
 	// Migration list Getter
@@ -85,6 +123,19 @@ impl GetMigrations for Tuple {
 	}
 }
 
+/// Whether the migrations of the last runtime upgrade are still in progress, meaning that the
+/// chain state is only partially migrated.
+///
+/// Intended to be used as `pallet_maintenance_mode::Config::PendingMigrations`, so that the chain
+/// stays in maintenance mode until every multi-block migration has completed.
+pub struct PendingMigrations<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: Config> frame_support::traits::Get<bool> for PendingMigrations<T> {
+	fn get() -> bool {
+		!Pallet::<T>::is_fully_upgraded()
+	}
+}
+
 #[pallet]
 pub mod pallet {
 	use super::*;
@@ -107,6 +158,12 @@ pub mod pallet {
 
 		/// Handler to suspend and resume XCM execution
 		type XcmExecutionManager: PauseXcmExecution;
+
+		/// The maximum weight that pending multi-block migrations can consume in the
+		/// `on_initialize` of each block following the runtime upgrade. They are also stepped in
+		/// `on_idle` with the weight left over by the extrinsics of the block.
+		#[pallet::constant]
+		type MaxMigrationWeightPerBlock: Get<Weight>;
 	}
 
 	#[pallet::event]
@@ -123,6 +180,11 @@ pub mod pallet {
 			migration_name: Vec<u8>,
 			consumed_weight: Weight,
 		},
+		/// Migration performed a step and will resume in a following block
+		MigrationStepped {
+			migration_name: Vec<u8>,
+			consumed_weight: Weight,
+		},
 		/// XCM execution suspension failed with inner error
 		FailedToSuspendIdleXcmExecution { error: DispatchError },
 		/// XCM execution resume failed with inner error
@@ -154,9 +216,9 @@ pub mod pallet {
 			));
 
 			if !<FullyUpgraded<T>>::get() {
-				log::error!(
-					"migrations weren't completed in on_runtime_upgrade(), but we're not
-				configured for multi-block migrations; state is potentially inconsistent!"
+				log::info!(
+					"migrations weren't completed in on_runtime_upgrade(), they will be resumed in
+				the following blocks"
 				);
 			}
 
@@ -172,18 +234,45 @@ pub mod pallet {
 				if let Err(error) = T::XcmExecutionManager::suspend_xcm_execution() {
 					<Pallet<T>>::deposit_event(Event::FailedToSuspendIdleXcmExecution { error });
 				}
-				// Account on_finalize write
-				T::DbWeight::get().reads_writes(1, 1)
+				// Account on_finalize read and write
+				T::DbWeight::get().reads_writes(2, 1)
+			} else if !<FullyUpgraded<T>>::get() {
+				// Resume the pending multi-block migrations. This is not done in the upgrade block
+				// itself because on_runtime_upgrade() already consumed all of it.
+				let weight = T::DbWeight::get().reads(2);
+				let available_weight = T::MaxMigrationWeightPerBlock::get().saturating_sub(weight);
+				weight.saturating_add(resume_runtime_upgrades::<T>(available_weight))
 			} else {
-				T::DbWeight::get().reads(1)
+				T::DbWeight::get().reads(2)
+			}
+		}
+
+		fn on_idle(_: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			let weight = T::DbWeight::get().reads(2);
+			// The upgrade block has no weight left, and there is no need to check for the pending
+			// migrations if not even a step of them would fit.
+			if ShouldPauseXcm::<T>::get()
+				|| <FullyUpgraded<T>>::get()
+				|| !remaining_weight.all_gte(weight)
+			{
+				return weight;
 			}
+
+			// Keep stepping the pending multi-block migrations with the weight left over by the
+			// extrinsics of the block.
+			weight.saturating_add(resume_runtime_upgrades::<T>(
+				remaining_weight.saturating_sub(weight),
+			))
 		}
 
 		fn on_finalize(_: BlockNumberFor<T>) {
 			if ShouldPauseXcm::<T>::get() {
-				// Resume XCM execution
-				if let Err(error) = T::XcmExecutionManager::resume_xcm_execution() {
-					<Pallet<T>>::deposit_event(Event::FailedToResumeIdleXcmExecution { error });
+				// Resume XCM execution, unless multi-block migrations are still pending. In that
+				// case it will be resumed once they have completed.
+				if <FullyUpgraded<T>>::get() {
+					if let Err(error) = T::XcmExecutionManager::resume_xcm_execution() {
+						<Pallet<T>>::deposit_event(Event::FailedToResumeIdleXcmExecution { error });
+					}
 				}
 				ShouldPauseXcm::<T>::put(false);
 			}
@@ -199,8 +288,9 @@ pub mod pallet {
 				let migration_name = migration.friendly_name();
 				let migration_name_as_bytes = migration_name.as_bytes();
 
-				let migration_done = <MigrationState<T>>::get(migration_name_as_bytes);
-				if migration_done {
+				// Migrations already in progress have partially altered the state, so their
+				// pre_upgrade() checks would be meaningless.
+				if <MigrationState<T>>::get(migration_name_as_bytes) != MigrationStatus::Pending {
 					continue;
 				}
 				log::debug!(
@@ -234,15 +324,25 @@ pub mod pallet {
 			for migration in &T::MigrationsList::get_migrations() {
 				let migration_name = migration.friendly_name();
 
-				// we can't query MigrationState because on_runtime_upgrade() would have
-				// unconditionally set it to true, so we read a hint from temp storage which was
-				// left for us by pre_upgrade()
+				// we can't query MigrationState alone because on_runtime_upgrade() would have
+				// set it to completed, so we read a hint from temp storage which was left for us
+				// by pre_upgrade()
 
 				match state_map.get(&migration_name.to_string()) {
 					Some(value) => assert!(value.clone(), "our dummy value might as well be true"),
 					None => continue,
 				}
 
+				// multi-block migrations can only be checked once they have completed
+				if <MigrationState<T>>::get(migration_name.as_bytes()) != MigrationStatus::Completed
+				{
+					log::info!(
+						"migration {} is still in progress, skipping post_upgrade()",
+						migration_name
+					);
+					continue;
+				}
+
 				log::debug!(
 					target: "pallet-migrations",
 					"invoking post_upgrade() on migration {}", migration_name
@@ -284,9 +384,10 @@ pub mod pallet {
 	#[pallet::storage]
 	#[pallet::getter(fn migration_state)]
 	/// MigrationState tracks the progress of a migration.
-	/// Maps name (Vec<u8>) -> whether or not migration has been completed (bool)
+	/// Maps name (Vec<u8>) -> status of the migration, including the cursor to resume from if it
+	/// spans several blocks (MigrationStatus)
 	pub(crate) type MigrationState<T: Config> =
-		StorageMap<_, Twox64Concat, Vec<u8>, bool, ValueQuery>;
+		StorageMap<_, Twox64Concat, Vec<u8>, MigrationStatus, ValueQuery>;
 
 	#[pallet::storage]
 	#[pallet::getter(fn should_pause_xcm)]
@@ -322,11 +423,31 @@ pub mod pallet {
 				.into_iter()
 				.map(|migration| migration.friendly_name().as_bytes().to_vec())
 			{
-				<MigrationState<T>>::insert(migration_name, true);
+				<MigrationState<T>>::insert(migration_name, MigrationStatus::Completed);
+			}
+			<FullyUpgraded<T>>::put(true);
+		}
+	}
+
+	/// Resume the pending migrations within the given weight, and resume XCM execution, which has
+	/// been kept suspended since the upgrade block, once they have completed.
+	fn resume_runtime_upgrades<T: Config>(available_weight: Weight) -> Weight {
+		let weight = perform_runtime_upgrades::<T>(available_weight);
+
+		if <FullyUpgraded<T>>::get() {
+			if let Err(error) = T::XcmExecutionManager::resume_xcm_execution() {
+				<Pallet<T>>::deposit_event(Event::FailedToResumeIdleXcmExecution { error });
 			}
 		}
+
+		weight.saturating_add(T::DbWeight::get().reads(1))
 	}
 
+	/// Run the pending migrations, in order, within the given weight.
+	///
+	/// Stops at the first migration that does not complete in a single step; it will be resumed
+	/// from its stored cursor the next time this function is called. `FullyUpgraded` is only set
+	/// once every migration has completed.
 	fn perform_runtime_upgrades<T: Config>(available_weight: Weight) -> Weight {
 		let mut weight = Weight::zero();
 
@@ -335,49 +456,74 @@ pub mod pallet {
 			let migration_name_as_bytes = migration_name.as_bytes();
 			log::debug!( target: "pallet-migrations", "evaluating migration {}", migration_name);
 
-			let migration_done = <MigrationState<T>>::get(migration_name_as_bytes);
-
-			if !migration_done {
-				<Pallet<T>>::deposit_event(Event::MigrationStarted {
-					migration_name: migration_name_as_bytes.into(),
-				});
-
-				// when we go overweight, leave a warning... there's nothing we can really do about
-				// this scenario other than hope that the block is actually accepted.
-				let available_for_step = if available_weight.ref_time() > weight.ref_time() {
-					available_weight - weight
-				} else {
-					log::error!(
-						"previous migration went overweight;
-						ignoring and providing migration {} 0 weight.",
-						migration_name,
-					);
+			let cursor = match <MigrationState<T>>::get(migration_name_as_bytes) {
+				MigrationStatus::Completed => continue,
+				MigrationStatus::Pending => {
+					<Pallet<T>>::deposit_event(Event::MigrationStarted {
+						migration_name: migration_name_as_bytes.into(),
+					});
+					None
+				}
+				MigrationStatus::InProgress { cursor } => Some(cursor),
+			};
 
-					Weight::zero()
-				};
+			// when we go overweight, leave a warning... there's nothing we can really do about
+			// this scenario other than hope that the block is actually accepted.
+			let available_for_step = if available_weight.ref_time() > weight.ref_time() {
+				available_weight - weight
+			} else {
+				log::error!(
+					"previous migration went overweight;
+					ignoring and providing migration {} 0 weight.",
+					migration_name,
+				);
+
+				Weight::zero()
+			};
 
-				log::info!( target: "pallet-migrations",
-					"performing migration {}, available weight: {}",
+			log::info!( target: "pallet-migrations",
+				"performing migration {}, available weight: {}",
+				migration_name,
+				available_for_step
+			);
+
+			let (next_cursor, consumed_weight) = migration.step(cursor, available_for_step);
+
+			weight = weight.saturating_add(consumed_weight);
+			if weight.ref_time() > available_weight.ref_time() {
+				log::error!(
+					"Migration {} consumed more weight than it was given! ({} > {})",
 					migration_name,
+					consumed_weight,
 					available_for_step
 				);
+			}
 
-				let consumed_weight = migration.migrate(available_for_step);
-				<Pallet<T>>::deposit_event(Event::MigrationCompleted {
-					migration_name: migration_name_as_bytes.into(),
-					consumed_weight: consumed_weight,
-				});
-				<MigrationState<T>>::insert(migration_name_as_bytes, true);
-
-				weight = weight.saturating_add(consumed_weight);
-				if weight.ref_time() > available_weight.ref_time() {
-					log::error!(
-						"Migration {} consumed more weight than it was given! ({} > {})",
-						migration_name,
+			match next_cursor {
+				None => {
+					<Pallet<T>>::deposit_event(Event::MigrationCompleted {
+						migration_name: migration_name_as_bytes.into(),
 						consumed_weight,
-						available_for_step
+					});
+					<MigrationState<T>>::insert(
+						migration_name_as_bytes,
+						MigrationStatus::Completed,
 					);
 				}
+				Some(cursor) => {
+					<Pallet<T>>::deposit_event(Event::MigrationStepped {
+						migration_name: migration_name_as_bytes.into(),
+						consumed_weight,
+					});
+					<MigrationState<T>>::insert(
+						migration_name_as_bytes,
+						MigrationStatus::InProgress { cursor },
+					);
+
+					// Following migrations may depend on this one, so they must wait for it to
+					// complete.
+					return weight.saturating_add(T::DbWeight::get().writes(1));
+				}
 			}
 		}
 
@@ -146,6 +146,7 @@ parameter_types! {
 pub struct MockMigrationManager<'test> {
 	name_fn_callbacks: Vec<Box<dyn 'test + FnMut() -> &'static str>>,
 	migrate_fn_callbacks: Vec<Box<dyn 'test + FnMut(Weight) -> Weight>>,
+	step_fn_callbacks: Vec<Option<Box<dyn 'test + FnMut(Option<Vec<u8>>, Weight) -> StepResult>>>,
 	pre_upgrade_fn_callbacks:
 		Vec<Box<dyn 'test + FnMut() -> Result<Vec<u8>, sp_runtime::DispatchError>>>,
 	post_upgrade_fn_callbacks:
@@ -157,6 +158,7 @@ impl Default for MockMigrationManager<'_> {
 		Self {
 			name_fn_callbacks: Default::default(),
 			migrate_fn_callbacks: Default::default(),
+			step_fn_callbacks: Default::default(),
 			pre_upgrade_fn_callbacks: Default::default(),
 			post_upgrade_fn_callbacks: Default::default(),
 		}
@@ -171,6 +173,23 @@ impl<'test> MockMigrationManager<'test> {
 	{
 		self.name_fn_callbacks.push(Box::new(name_fn));
 		self.migrate_fn_callbacks.push(Box::new(migrate_fn));
+		self.step_fn_callbacks.push(None);
+		self.pre_upgrade_fn_callbacks
+			.push(Box::new(|| Ok(Vec::new())));
+		self.post_upgrade_fn_callbacks.push(Box::new(|| Ok(())));
+	}
+	/// Register a multi-block migration, whose step_fn maps to Migration::step()
+	pub fn register_stepped_callback<FN, FS>(&mut self, name_fn: FN, step_fn: FS)
+	where
+		FN: 'test + FnMut() -> &'static str,
+		FS: 'test + FnMut(Option<Vec<u8>>, Weight) -> StepResult,
+	{
+		self.name_fn_callbacks.push(Box::new(name_fn));
+		self.migrate_fn_callbacks
+			.push(Box::new(|_: Weight| -> Weight {
+				panic!("stepped migrations are never migrated at once")
+			}));
+		self.step_fn_callbacks.push(Some(Box::new(step_fn)));
 		self.pre_upgrade_fn_callbacks
 			.push(Box::new(|| Ok(Vec::new())));
 		self.post_upgrade_fn_callbacks.push(Box::new(|| Ok(())));
@@ -191,6 +210,7 @@ impl<'test> MockMigrationManager<'test> {
 	{
 		self.name_fn_callbacks.push(Box::new(name_fn));
 		self.migrate_fn_callbacks.push(Box::new(migrate_fn));
+		self.step_fn_callbacks.push(None);
 		self.pre_upgrade_fn_callbacks.push(Box::new(pre_upgrade_fn));
 		self.post_upgrade_fn_callbacks
 			.push(Box::new(post_upgrade_fn));
@@ -204,6 +224,17 @@ impl<'test> MockMigrationManager<'test> {
 		self.migrate_fn_callbacks[index](available_weight)
 	}
 
+	pub(crate) fn invoke_step_fn(
+		&mut self,
+		index: usize,
+		cursor: Option<Vec<u8>>,
+		available_weight: Weight,
+	) -> Option<StepResult> {
+		self.step_fn_callbacks[index]
+			.as_mut()
+			.map(|step_fn| step_fn(cursor, available_weight))
+	}
+
 	#[cfg(feature = "try-runtime")]
 	pub(crate) fn invoke_pre_upgrade(
 		&mut self,
@@ -229,6 +260,9 @@ impl<'test> MockMigrationManager<'test> {
 	}
 }
 
+/// The result of Migration::step(): the cursor to resume from, if any, and the consumed weight
+pub type StepResult = (Option<Vec<u8>>, Weight);
+
 // Our global Migrations list. Necessary because the Get impl must be fulfilled with nothing but
 // a static context.
 environmental!(MOCK_MIGRATIONS_LIST: MockMigrationManager<'static>);
@@ -273,6 +307,14 @@ impl Migration for MockMigration {
 		});
 		result
 	}
+	fn step(&self, cursor: Option<Vec<u8>>, available_weight: Weight) -> StepResult {
+		let mut result = None;
+		MOCK_MIGRATIONS_LIST::with(|mgr: &mut MockMigrationManager| {
+			result = mgr.invoke_step_fn(self.index, cursor.clone(), available_weight);
+		});
+		// Migrations registered without a step_fn behave as single-block migrations
+		result.unwrap_or_else(|| (None, self.migrate(available_weight)))
+	}
 	#[cfg(feature = "try-runtime")]
 	fn pre_upgrade(&self) -> Result<Vec<u8>, sp_runtime::DispatchError> {
 		let mut result = Ok(vec![]);
@@ -304,10 +346,29 @@ impl GetMigrations for MockMigrations {
 	}
 }
 
+parameter_types! {
+	pub const MaxMigrationWeightPerBlock: Weight = Weight::from_parts(1_000_000_000, 0);
+	pub storage XcmExecutionSuspended: bool = false;
+}
+
+/// Keeps track of whether XCM execution is currently suspended
+pub struct MockXcmExecutionManager;
+impl xcm_primitives::PauseXcmExecution for MockXcmExecutionManager {
+	fn suspend_xcm_execution() -> sp_runtime::DispatchResult {
+		XcmExecutionSuspended::set(&true);
+		Ok(())
+	}
+	fn resume_xcm_execution() -> sp_runtime::DispatchResult {
+		XcmExecutionSuspended::set(&false);
+		Ok(())
+	}
+}
+
 impl Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type MigrationsList = MockMigrations;
-	type XcmExecutionManager = ();
+	type XcmExecutionManager = MockXcmExecutionManager;
+	type MaxMigrationWeightPerBlock = MaxMigrationWeightPerBlock;
 }
 
 /// Externality builder for pallet migration's mock runtime
@@ -347,7 +408,7 @@ impl ExtBuilder {
 					<crate::pallet::MigrationState<Runtime>>::hashed_key_for(
 						migration_name.as_bytes(),
 					),
-					false.encode(),
+					MigrationStatus::Pending.encode(),
 				);
 			}
 		}
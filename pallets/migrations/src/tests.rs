@@ -17,10 +17,16 @@
 //! Unit testing
 use {
 	crate::{
-		mock::{events, ExtBuilder, Migrations, MockMigrationManager, Runtime, System},
-		Event,
+		mock::{
+			events, ExtBuilder, Migrations, MockMigrationManager, Runtime, System,
+			XcmExecutionSuspended,
+		},
+		Event, MigrationStatus,
+	},
+	frame_support::{
+		traits::{OnFinalize, OnIdle, OnInitialize, OnRuntimeUpgrade},
+		weights::Weight,
 	},
-	frame_support::{traits::OnRuntimeUpgrade, weights::Weight},
 	sp_runtime::traits::Get,
 	std::sync::{Arc, Mutex},
 };
@@ -289,6 +295,265 @@ fn overweight_migrations_tolerated() {
 	);
 }
 
+#[test]
+fn multi_block_migration_is_resumed_across_blocks() {
+	let received_cursors = Arc::new(Mutex::new(Vec::<Option<Vec<u8>>>::new()));
+
+	crate::mock::execute_with_mock_migrations(
+		&mut |mgr: &mut MockMigrationManager| {
+			let received_cursors = Arc::clone(&received_cursors);
+
+			mgr.register_stepped_callback(
+				move || "stepped_migration",
+				move |cursor, _| {
+					received_cursors.lock().unwrap().push(cursor.clone());
+					// complete after three steps
+					let next_cursor = match cursor {
+						None => Some(vec![1u8]),
+						Some(c) if c == vec![1u8] => Some(vec![2u8]),
+						_ => None,
+					};
+					(next_cursor, Weight::from_parts(10, 0))
+				},
+			);
+		},
+		&mut || {
+			ExtBuilder::with_uncompleted_migrations(vec!["stepped_migration"])
+				.build()
+				.execute_with(|| {
+					Migrations::on_runtime_upgrade();
+
+					assert!(!Migrations::is_fully_upgraded());
+					assert_eq!(
+						Migrations::migration_state(b"stepped_migration".to_vec()),
+						MigrationStatus::InProgress { cursor: vec![1u8] }
+					);
+
+					crate::mock::roll_until_upgraded(false);
+
+					assert_eq!(
+						Migrations::migration_state(b"stepped_migration".to_vec()),
+						MigrationStatus::Completed
+					);
+					assert_eq!(
+						*received_cursors.lock().unwrap(),
+						vec![None, Some(vec![1u8]), Some(vec![2u8])]
+					);
+					let expected = vec![
+						Event::RuntimeUpgradeStarted(),
+						Event::MigrationStarted {
+							migration_name: "stepped_migration".into(),
+						},
+						Event::MigrationStepped {
+							migration_name: "stepped_migration".into(),
+							consumed_weight: Weight::from_parts(10, 0),
+						},
+						Event::MigrationStepped {
+							migration_name: "stepped_migration".into(),
+							consumed_weight: Weight::from_parts(10, 0),
+						},
+						Event::MigrationCompleted {
+							migration_name: "stepped_migration".into(),
+							consumed_weight: Weight::from_parts(10, 0),
+						},
+						Event::RuntimeUpgradeCompleted {
+							weight: Weight::from_parts(100000010u64, 0),
+						}, // includes reads/writes
+					];
+					assert_eq!(events(), expected);
+				});
+		},
+	);
+}
+
+#[test]
+fn migrations_wait_for_pending_multi_block_migration() {
+	let num_stepped_calls = Arc::new(Mutex::new(0u32));
+	let num_migration_calls = Arc::new(Mutex::new(0u32));
+
+	crate::mock::execute_with_mock_migrations(
+		&mut |mgr: &mut MockMigrationManager| {
+			let num_stepped_calls = Arc::clone(&num_stepped_calls);
+			let num_migration_calls = Arc::clone(&num_migration_calls);
+
+			mgr.register_stepped_callback(
+				move || "stepped_migration",
+				move |cursor, _| {
+					*num_stepped_calls.lock().unwrap() += 1;
+					// complete after two steps
+					let next_cursor = match cursor {
+						None => Some(vec![]),
+						Some(_) => None,
+					};
+					(next_cursor, Weight::zero())
+				},
+			);
+
+			mgr.register_callback(
+				move || "migration",
+				move |_| -> Weight {
+					*num_migration_calls.lock().unwrap() += 1;
+					Weight::zero()
+				},
+			);
+		},
+		&mut || {
+			ExtBuilder::with_uncompleted_migrations(vec!["stepped_migration", "migration"])
+				.build()
+				.execute_with(|| {
+					Migrations::on_runtime_upgrade();
+
+					assert_eq!(*num_stepped_calls.lock().unwrap(), 1);
+					assert_eq!(*num_migration_calls.lock().unwrap(), 0);
+					assert_eq!(
+						Migrations::migration_state(b"migration".to_vec()),
+						MigrationStatus::Pending
+					);
+
+					crate::mock::roll_until_upgraded(false);
+
+					assert_eq!(*num_stepped_calls.lock().unwrap(), 2);
+					assert_eq!(*num_migration_calls.lock().unwrap(), 1);
+					assert_eq!(
+						Migrations::migration_state(b"migration".to_vec()),
+						MigrationStatus::Completed
+					);
+				});
+		},
+	);
+}
+
+#[test]
+fn upgrade_block_does_not_resume_multi_block_migrations() {
+	let num_stepped_calls = Arc::new(Mutex::new(0u32));
+
+	crate::mock::execute_with_mock_migrations(
+		&mut |mgr: &mut MockMigrationManager| {
+			let num_stepped_calls = Arc::clone(&num_stepped_calls);
+
+			mgr.register_stepped_callback(
+				move || "stepped_migration",
+				move |_, _| {
+					*num_stepped_calls.lock().unwrap() += 1;
+					(Some(vec![]), Weight::zero())
+				},
+			);
+		},
+		&mut || {
+			ExtBuilder::with_uncompleted_migrations(vec!["stepped_migration"])
+				.build()
+				.execute_with(|| {
+					Migrations::on_runtime_upgrade();
+					assert_eq!(*num_stepped_calls.lock().unwrap(), 1);
+
+					// on_initialize of the block in which xcm is paused is still part of the
+					// upgrade, which already consumed the whole block
+					Migrations::on_initialize(System::block_number());
+					assert_eq!(*num_stepped_calls.lock().unwrap(), 1);
+					Migrations::on_finalize(System::block_number());
+
+					crate::mock::roll_to(3, false);
+					assert_eq!(*num_stepped_calls.lock().unwrap(), 3);
+					assert!(!Migrations::is_fully_upgraded());
+				});
+		},
+	);
+}
+
+#[test]
+fn xcm_execution_stays_suspended_until_migrations_complete() {
+	crate::mock::execute_with_mock_migrations(
+		&mut |mgr: &mut MockMigrationManager| {
+			mgr.register_stepped_callback(
+				move || "stepped_migration",
+				move |cursor, _| {
+					// complete after two steps
+					let next_cursor = match cursor {
+						None => Some(vec![]),
+						Some(_) => None,
+					};
+					(next_cursor, Weight::zero())
+				},
+			);
+		},
+		&mut || {
+			ExtBuilder::with_uncompleted_migrations(vec!["stepped_migration"])
+				.build()
+				.execute_with(|| {
+					Migrations::on_runtime_upgrade();
+					Migrations::on_initialize(System::block_number());
+					Migrations::on_finalize(System::block_number());
+
+					assert!(XcmExecutionSuspended::get());
+					assert!(crate::PendingMigrations::<Runtime>::get());
+
+					crate::mock::roll_to(2, false);
+
+					assert!(!XcmExecutionSuspended::get());
+					assert!(!crate::PendingMigrations::<Runtime>::get());
+				});
+		},
+	);
+}
+
+#[test]
+fn multi_block_migrations_are_stepped_with_idle_weight() {
+	let num_stepped_calls = Arc::new(Mutex::new(0u32));
+
+	crate::mock::execute_with_mock_migrations(
+		&mut |mgr: &mut MockMigrationManager| {
+			let num_stepped_calls = Arc::clone(&num_stepped_calls);
+
+			mgr.register_stepped_callback(
+				move || "stepped_migration",
+				move |cursor, _| {
+					*num_stepped_calls.lock().unwrap() += 1;
+					// complete after three steps
+					let next_cursor = match cursor {
+						None => Some(vec![1u8]),
+						Some(c) if c == vec![1u8] => Some(vec![2u8]),
+						_ => None,
+					};
+					(next_cursor, Weight::from_parts(10, 0))
+				},
+			);
+		},
+		&mut || {
+			ExtBuilder::with_uncompleted_migrations(vec!["stepped_migration"])
+				.build()
+				.execute_with(|| {
+					Migrations::on_runtime_upgrade();
+					Migrations::on_initialize(System::block_number());
+
+					// The upgrade block has no weight left
+					Migrations::on_idle(System::block_number(), Weight::MAX);
+					assert_eq!(*num_stepped_calls.lock().unwrap(), 1);
+					Migrations::on_finalize(System::block_number());
+
+					crate::mock::roll_to(System::block_number() + 1, false);
+					assert_eq!(*num_stepped_calls.lock().unwrap(), 2);
+
+					// Not even the pending migrations can be checked with this weight
+					let weight = Migrations::on_idle(System::block_number(), Weight::zero());
+					assert_eq!(
+						weight,
+						<Runtime as frame_system::Config>::DbWeight::get().reads(2)
+					);
+					assert_eq!(*num_stepped_calls.lock().unwrap(), 2);
+
+					let weight = Migrations::on_idle(
+						System::block_number(),
+						Weight::from_parts(u64::MAX, 0),
+					);
+					assert_eq!(*num_stepped_calls.lock().unwrap(), 3);
+					assert!(weight.ref_time() > 10);
+					assert!(Migrations::is_fully_upgraded());
+					assert!(!XcmExecutionSuspended::get());
+				});
+		},
+	);
+}
+
 #[cfg(all(test, feature = "try-runtime"))]
 fn try_runtime_functions_work() {
 	let pre_fn_called = Arc::new(Mutex::new(false));
@@ -38,7 +38,7 @@ construct_runtime!(
 	{
 		System: frame_system::{Pallet, Call, Config<T>, Storage, Event<T>},
 		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
-		AuthorMapping: pallet_author_mapping::{Pallet, Call, Storage, Config<T>, Event<T>},
+		AuthorMapping: pallet_author_mapping::{Pallet, Call, Storage, Config<T>, Event<T>, HoldReason},
 		Randomness: pallet_randomness::{Pallet, Call, Storage, Event<T>, Inherent},
 	}
 );
@@ -89,21 +89,31 @@ impl pallet_balances::Config for Test {
 	type ExistentialDeposit = ExistentialDeposit;
 	type AccountStore = System;
 	type WeightInfo = ();
-	type RuntimeHoldReason = ();
+	type RuntimeHoldReason = RuntimeHoldReason;
 	type FreezeIdentifier = ();
-	type MaxHolds = ();
+	type MaxHolds = frame_support::traits::ConstU32<1>;
 	type MaxFreezes = ();
 	type RuntimeFreezeReason = ();
 }
 
 parameter_types! {
 	pub const DepositAmount: Balance = 100;
+	pub const InactivityReward: sp_runtime::Percent = sp_runtime::Percent::from_percent(10);
 }
 impl pallet_author_mapping::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
+	type RuntimeHoldReason = RuntimeHoldReason;
 	type DepositCurrency = Balances;
 	type DepositAmount = DepositAmount;
 	type Keys = VrfId;
+	type KeysOwnership = pallet_author_mapping::AppKeyOwnership;
+	type MaxKeysPerAccount = frame_support::traits::ConstU32<4>;
+	type CurrentSession = ();
+	type MaxScheduledRotations = frame_support::traits::ConstU32<4>;
+	type ForceOrigin = frame_system::EnsureRoot<AccountId>;
+	type Slashed = ();
+	type InactivityPeriod = frame_support::traits::ConstU64<10>;
+	type InactivityReward = InactivityReward;
 	type WeightInfo = ();
 }
 
@@ -213,6 +223,7 @@ impl ExtBuilder {
 
 		pallet_author_mapping::GenesisConfig::<Test> {
 			mappings: self.mappings,
+			..Default::default()
 		}
 		.assimilate_storage(&mut t)
 		.expect("Pallet author mapping's storage can be assimilated");
@@ -0,0 +1,117 @@
+// Copyright Moonsong Labs
+// This file is part of Moonkit.
+
+// Moonkit is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Moonkit is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
+
+#![cfg(feature = "runtime-benchmarks")]
+
+use crate::{Call, Config, CurrentVrfOutput, ExpectedEligible, Pallet, Seed};
+use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite};
+use frame_support::traits::OnFinalize;
+use frame_system::RawOrigin;
+use nimbus_primitives::{digests::CompatibleDigestItem as NimbusDigest, AccountLookup, NimbusId};
+use parity_scale_codec::Decode;
+use session_keys_primitives::{
+	digest::CompatibleDigestItem as VrfDigest, KeysLookup, PreDigest, VrfId,
+};
+use sp_runtime::app_crypto::ByteArray;
+use sp_std::vec;
+
+/// The sr25519 key derived from the seed `[1; 32]`, used both as NimbusId and VrfId
+const AUTHOR_KEY: &str = "189dac29296d31814dc8c56cf3d36a0543372bba7538fa322a4aebfebc39e056";
+
+/// The slot VRF output and proof of `AUTHOR_KEY` for slot 0 and the default seed
+const AUTHOR_PRE_DIGEST: &str = "18bc0b0f921c7b550f6851f6da0a4de5b9ba7dfa0dad0df4f7cc0938950ce450\
+	d5c618ab1819d6569b7a4fc3bec323b816a91a82a220e04c69183b3c9ed989033dadf783e97315107f220823d311ca0f\
+	007453ed858bc24854a4007624dfaa0b";
+
+benchmarks! {
+	where_clause {
+		where <T::VrfKeyLookup as KeysLookup<NimbusId, VrfId>>::Account: From<T::AccountId>
+	}
+
+	set_expected_eligible {
+		let expected = 34u32;
+	}: _(RawOrigin::Root, expected)
+	verify {
+		assert_eq!(Pallet::<T>::expected_eligible(), expected);
+	}
+
+	// Worst case: the proof is valid and the author is eligible, so that its output is kept
+	check_slot_vrf {
+		let key = hex::decode(AUTHOR_KEY).expect("The key is valid hex");
+		let nimbus_id = NimbusId::from_slice(&key).expect("The key has 32 bytes");
+		let vrf_id = VrfId::from_slice(&key).expect("The key has 32 bytes");
+		let pre_digest = hex::decode(AUTHOR_PRE_DIGEST).expect("The pre-digest is valid hex");
+		let pre_digest = PreDigest::decode(&mut &pre_digest[..])
+			.expect("The pre-digest is a VRF output and proof");
+
+		let account: T::AccountId = account("author", 0, 0);
+		T::VrfKeyLookup::set_keys(nimbus_id.clone(), account.into(), vrf_id);
+		let author = T::AccountLookup::lookup_account(&nimbus_id)
+			.expect("The keys of the author were just set");
+
+		Seed::<T>::put(T::Hash::default());
+		ExpectedEligible::<T>::put(u32::MAX);
+		let digest = sp_runtime::generic::Digest {
+			logs: vec![
+				NimbusDigest::nimbus_pre_digest(nimbus_id),
+				VrfDigest::vrf_pre_digest(pre_digest),
+			],
+		};
+		frame_system::Pallet::<T>::initialize(
+			&frame_system::Pallet::<T>::block_number(),
+			&T::Hash::default(),
+			&digest,
+		);
+	}: {
+		assert!(Pallet::<T>::check_slot_vrf(&author, 0, 1));
+	}
+	verify {
+		assert!(CurrentVrfOutput::<T>::get().is_some());
+	}
+
+	// Worst case: the output of the block becomes the seed
+	on_finalize {
+		let output = T::Hash::default();
+		CurrentVrfOutput::<T>::put(output);
+		let block_number = frame_system::Pallet::<T>::block_number();
+	}: {
+		Pallet::<T>::on_finalize(block_number);
+	}
+	verify {
+		assert_eq!(Pallet::<T>::seed(), output);
+		assert!(CurrentVrfOutput::<T>::get().is_none());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::tests::Test;
+	use sp_io::TestExternalities;
+	use sp_runtime::BuildStorage;
+
+	pub fn new_test_ext() -> TestExternalities {
+		let t = frame_system::GenesisConfig::<Test>::default()
+			.build_storage()
+			.unwrap();
+		TestExternalities::new(t)
+	}
+}
+
+impl_benchmark_test_suite!(
+	Pallet,
+	crate::benchmarks::tests::new_test_ext(),
+	crate::tests::Test
+);
@@ -0,0 +1,260 @@
+// Copyright Moonsong Labs
+// This file is part of Moonkit.
+
+// Moonkit is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Moonkit is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A Nimbus filter in which the eligible authors of each slot are elected privately, similarly to
+//! BABE primary slots.
+//!
+//! Unlike `author-slot-filter` and `aura-style-filter`, nobody can tell in advance who will be
+//! eligible. Each author evaluates a VRF over the slot and a seed with its own VRF key, and is
+//! eligible if the resulting ticket is below a threshold. The threshold is chosen so that
+//! `ExpectedEligible` authors are eligible at each slot on average.
+//!
+//! The author proves its eligibility by including the VRF output and proof in the `rand`
+//! pre-runtime digest, which this pallet verifies when the author-inherent pallet asks whether
+//! the author can author. The output of each block becomes the seed of the next one.
+//!
+//! Since nobody but the author can predict its eligibility, `NimbusApi::can_author` is always
+//! false for this filter. Runtimes using it must implement the `SlotVrfApi` runtime api with the
+//! `seed`, `threshold` and `vrf_key` helpers of this pallet, and collators must be started with
+//! the `VrfSlotClaimer` of nimbus-consensus, which claims the slots and builds the digest.
+//!
+//! The VRF transcript includes the slot, so this filter interprets the `rand` pre-runtime digest
+//! differently from `pallet-randomness`, and the two cannot be used in the same runtime.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use frame_support::pallet;
+
+pub use crate::weights::WeightInfo;
+pub use pallet::*;
+
+#[cfg(any(test, feature = "runtime-benchmarks"))]
+mod benchmarks;
+
+pub mod weights;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
+#[pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+	use log::debug;
+	use nimbus_primitives::{AccountLookup, CanAuthor, NimbusId, NIMBUS_ENGINE_ID};
+	use session_keys_primitives::{
+		slot_vrf_ticket, verify_slot_vrf, KeysLookup, PreDigest, VrfId, VRF_ENGINE_ID,
+	};
+	use sp_std::vec::Vec;
+
+	/// The VRF Slot Filter pallet
+	#[pallet::pallet]
+	pub struct Pallet<T>(PhantomData<T>);
+
+	/// Configuration trait of this pallet.
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type
+		type RuntimeEvent: From<Event> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+		/// A source for the complete set of potential authors.
+		/// The starting point of the filtering.
+		type PotentialAuthors: Get<Vec<Self::AccountId>>;
+		/// Takes NimbusId to return the account of the author
+		type AccountLookup: AccountLookup<Self::AccountId>;
+		/// Takes NimbusId to return VrfId
+		type VrfKeyLookup: KeysLookup<NimbusId, VrfId>;
+		type WeightInfo: WeightInfo;
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The expected number of eligible authors cannot be zero
+		ZeroExpectedEligible,
+	}
+
+	/// The VRF output of the last block, used as the seed of the slot VRF of the next block.
+	#[pallet::storage]
+	#[pallet::getter(fn seed)]
+	pub type Seed<T: Config> = StorageValue<_, T::Hash, ValueQuery>;
+
+	/// The VRF output verified in the current block, which becomes the seed at the end of the
+	/// block.
+	#[pallet::storage]
+	pub(crate) type CurrentVrfOutput<T: Config> = StorageValue<_, T::Hash, OptionQuery>;
+
+	/// The number of authors expected to be eligible at each slot.
+	#[pallet::storage]
+	#[pallet::getter(fn expected_eligible)]
+	pub type ExpectedEligible<T: Config> =
+		StorageValue<_, u32, ValueQuery, DefaultExpectedEligible<T>>;
+
+	// Default value for the `ExpectedEligible`.
+	#[pallet::type_value]
+	pub fn DefaultExpectedEligible<T: Config>() -> u32 {
+		2
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_: BlockNumberFor<T>) -> Weight {
+			T::WeightInfo::on_finalize()
+		}
+		fn on_finalize(_: BlockNumberFor<T>) {
+			if let Some(vrf_output) = CurrentVrfOutput::<T>::take() {
+				Seed::<T>::put(vrf_output);
+			}
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Update the number of authors expected to be eligible at each slot. Intended to be called
+		/// by governance.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::set_expected_eligible())]
+		pub fn set_expected_eligible(origin: OriginFor<T>, new: u32) -> DispatchResultWithPostInfo {
+			ensure_root(origin)?;
+			ensure!(new > 0, Error::<T>::ZeroExpectedEligible);
+			ExpectedEligible::<T>::put(new);
+			<Pallet<T>>::deposit_event(Event::ExpectedEligibleUpdated(new));
+
+			Ok(Default::default())
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(fn deposit_event)]
+	pub enum Event {
+		/// The number of authors expected to be eligible at each slot has been changed.
+		ExpectedEligibleUpdated(u32),
+	}
+
+	/// The threshold under which a ticket is eligible, so that each of the `potential_authors` is
+	/// eligible with a probability of `expected_eligible / potential_authors`.
+	pub fn slot_vrf_threshold(expected_eligible: u32, potential_authors: u32) -> u128 {
+		if potential_authors == 0 {
+			0
+		} else if expected_eligible >= potential_authors {
+			u128::MAX
+		} else {
+			(u128::MAX / potential_authors as u128).saturating_mul(expected_eligible as u128)
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The threshold under which a ticket is eligible with the current potential authors.
+		pub fn threshold() -> u128 {
+			slot_vrf_threshold(
+				ExpectedEligible::<T>::get(),
+				T::PotentialAuthors::get().len() as u32,
+			)
+		}
+
+		/// The VRF key with which the given author signs its slot VRF.
+		pub fn vrf_key(nimbus_id: &NimbusId) -> Option<VrfId> {
+			T::VrfKeyLookup::lookup_keys(nimbus_id)
+		}
+
+		/// Finds the author's NimbusId and its VRF pre-digest in the digests of the current block
+		fn find_vrf_pre_digest() -> Option<(NimbusId, PreDigest)> {
+			let mut nimbus_id = None;
+			let mut pre_digest = None;
+			for (id, mut data) in <frame_system::Pallet<T>>::digest()
+				.logs
+				.iter()
+				.filter_map(|d| d.as_pre_runtime())
+			{
+				if id == NIMBUS_ENGINE_ID {
+					nimbus_id = NimbusId::decode(&mut data).ok();
+				} else if id == VRF_ENGINE_ID {
+					pre_digest = PreDigest::decode(&mut data).ok();
+				}
+			}
+
+			Some((nimbus_id?, pre_digest?))
+		}
+
+		/// Whether the VRF pre-digest of the current block proves that the author is eligible in
+		/// this slot among `potential_authors` authors. The VRF output of an eligible author is
+		/// kept to become the next seed.
+		pub fn check_slot_vrf(author: &T::AccountId, slot: u32, potential_authors: u32) -> bool {
+			let (nimbus_id, pre_digest) = match Self::find_vrf_pre_digest() {
+				Some(found) => found,
+				None => {
+					debug!(target: "vrf-filter", "No VRF pre-digest for author {:?}", author);
+					return false;
+				}
+			};
+
+			// The VRF must be signed by the key of the author
+			if T::AccountLookup::lookup_account(&nimbus_id).as_ref() != Some(author) {
+				return false;
+			}
+			let vrf_id = match Self::vrf_key(&nimbus_id) {
+				Some(vrf_id) => vrf_id,
+				None => return false,
+			};
+
+			let vrf_output = match verify_slot_vrf(&vrf_id, Seed::<T>::get(), slot, &pre_digest) {
+				Some(vrf_output) => vrf_output,
+				None => {
+					debug!(target: "vrf-filter", "Invalid VRF proof for author {:?}", author);
+					return false;
+				}
+			};
+
+			let threshold = slot_vrf_threshold(ExpectedEligible::<T>::get(), potential_authors);
+			let eligible = slot_vrf_ticket(&vrf_output) < threshold;
+			debug!(target: "vrf-filter",
+				"Current author, {:?}, is eligible: {}",
+				author,
+				eligible
+			);
+
+			// If the output cannot be decoded as a hash, the next block keeps the current seed
+			if eligible {
+				if let Ok(vrf_output) = T::Hash::decode(&mut &vrf_output[..]) {
+					CurrentVrfOutput::<T>::put(vrf_output);
+				}
+			}
+
+			eligible
+		}
+	}
+
+	// This code will be called by the author-inherent pallet to check whether the reported author
+	// of this block is eligible in this slot. The author proves its eligibility with the VRF
+	// pre-digest, so the result cannot be predicted by anyone else.
+	impl<T: Config> CanAuthor<T::AccountId> for Pallet<T> {
+		#[cfg(not(feature = "try-runtime"))]
+		fn can_author(author: &T::AccountId, slot: &u32) -> bool {
+			let potential_authors = T::PotentialAuthors::get();
+			if !potential_authors.contains(author) {
+				return false;
+			}
+
+			// This runs from the author inherent, so its weight is registered as mandatory
+			frame_system::Pallet::<T>::register_extra_weight_unchecked(
+				T::WeightInfo::check_slot_vrf(),
+				DispatchClass::Mandatory,
+			);
+			Self::check_slot_vrf(author, *slot, potential_authors.len() as u32)
+		}
+	}
+}
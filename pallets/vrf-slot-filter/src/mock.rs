@@ -0,0 +1,119 @@
+// Copyright Moonsong Labs
+// This file is part of Moonkit.
+
+// Moonkit is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Moonkit is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate as pallet_testing;
+use frame_support::parameter_types;
+use frame_support::traits::ConstU32;
+use frame_support::weights::RuntimeDbWeight;
+use nimbus_primitives::{AccountLookup, NimbusId};
+use session_keys_primitives::{KeysLookup, VrfId};
+use sp_core::{sr25519, Pair, H256};
+use sp_runtime::{
+	traits::{BlakeTwo256, IdentityLookup},
+	BuildStorage,
+};
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime!(
+	pub enum Test
+	{
+		System: frame_system,
+		VrfSlotFilter: pallet_testing,
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub Authors: Vec<u64> = vec![1, 2, 3, 4, 5];
+	pub const TestDbWeight: RuntimeDbWeight = RuntimeDbWeight {
+		read: 1,
+		write: 10,
+	};
+}
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = TestDbWeight;
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type Block = Block;
+	type Hash = H256;
+	type Nonce = u64;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+/// The key pair of each author, derived from its account
+pub fn author_pair(author: u64) -> sr25519::Pair {
+	sr25519::Pair::from_seed(&[author as u8; 32])
+}
+
+pub fn author_nimbus_id(author: u64) -> NimbusId {
+	NimbusId::from(author_pair(author).public())
+}
+
+/// Maps the NimbusId of each of the potential authors to its account
+pub struct TestAccountLookup;
+impl AccountLookup<u64> for TestAccountLookup {
+	fn lookup_account(nimbus_id: &NimbusId) -> Option<u64> {
+		Authors::get()
+			.into_iter()
+			.find(|author| &author_nimbus_id(*author) == nimbus_id)
+	}
+}
+
+/// Authors use the same key as NimbusId and VrfId
+pub struct TestVrfKeyLookup;
+impl KeysLookup<NimbusId, VrfId> for TestVrfKeyLookup {
+	#[cfg(feature = "runtime-benchmarks")]
+	type Account = u64;
+	fn lookup_keys(nimbus_id: &NimbusId) -> Option<VrfId> {
+		Some(VrfId::from(nimbus_id.clone()))
+	}
+	#[cfg(feature = "runtime-benchmarks")]
+	fn set_keys(_id: NimbusId, _account: u64, _keys: VrfId) {}
+}
+
+impl pallet_testing::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type PotentialAuthors = Authors;
+	type AccountLookup = TestAccountLookup;
+	type VrfKeyLookup = TestVrfKeyLookup;
+	type WeightInfo = ();
+}
+
+/// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	frame_system::GenesisConfig::<Test>::default()
+		.build_storage()
+		.unwrap()
+		.into()
+}
@@ -0,0 +1,243 @@
+// Copyright Moonsong Labs
+// This file is part of Moonkit.
+
+// Moonkit is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Moonkit is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::*;
+pub use crate::mock::*;
+
+use frame_support::traits::Hooks;
+use frame_support::weights::Weight;
+use frame_support::{assert_noop, assert_ok};
+use nimbus_primitives::{CanAuthor, NIMBUS_ENGINE_ID};
+use parity_scale_codec::Encode;
+use session_keys_primitives::{
+	make_slot_vrf_sign_data, slot_vrf_ticket, verify_slot_vrf, PreDigest, VrfId, VRF_ENGINE_ID,
+};
+use sp_core::crypto::VrfSecret;
+use sp_core::H256;
+use sp_runtime::{Digest, DigestItem, DispatchError};
+
+/// The VRF pre-digest of the author for the given seed and slot
+fn slot_pre_digest(author: u64, seed: H256, slot: u32) -> PreDigest {
+	let signature = author_pair(author).vrf_sign(&make_slot_vrf_sign_data(seed, slot));
+	PreDigest {
+		vrf_output: signature.output,
+		vrf_proof: signature.proof,
+	}
+}
+
+/// Initializes a block whose digests claim the given author with the given VRF pre-digest
+fn initialize_block(author: u64, pre_digest: Option<PreDigest>) {
+	let mut logs = vec![DigestItem::PreRuntime(
+		NIMBUS_ENGINE_ID,
+		author_nimbus_id(author).encode(),
+	)];
+	if let Some(pre_digest) = pre_digest {
+		logs.push(DigestItem::PreRuntime(VRF_ENGINE_ID, pre_digest.encode()));
+	}
+	System::initialize(&1, &Default::default(), &Digest { logs });
+}
+
+/// Whether the author is eligible at the slot, computed independently of the pallet
+fn is_eligible(author: u64, seed: H256, slot: u32, threshold: u128) -> bool {
+	let vrf_id = VrfId::from(author_nimbus_id(author));
+	let vrf_output = verify_slot_vrf(&vrf_id, seed, slot, &slot_pre_digest(author, seed, slot))
+		.expect("The proof is valid");
+	slot_vrf_ticket(&vrf_output) < threshold
+}
+
+#[test]
+fn test_set_expected_eligible_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_eq!(VrfSlotFilter::expected_eligible(), 2);
+
+		assert_ok!(VrfSlotFilter::set_expected_eligible(
+			RuntimeOrigin::root(),
+			34
+		));
+		assert_eq!(VrfSlotFilter::expected_eligible(), 34);
+		System::assert_last_event(Event::ExpectedEligibleUpdated(34).into());
+	});
+}
+
+#[test]
+fn set_expected_eligible_rejects_zero() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			VrfSlotFilter::set_expected_eligible(RuntimeOrigin::root(), 0),
+			Error::<Test>::ZeroExpectedEligible
+		);
+	});
+}
+
+#[test]
+fn set_expected_eligible_requires_root() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			VrfSlotFilter::set_expected_eligible(RuntimeOrigin::signed(1), 3),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn threshold_is_proportional_to_expected_eligible() {
+	assert_eq!(slot_vrf_threshold(2, 0), 0);
+	assert_eq!(slot_vrf_threshold(5, 5), u128::MAX);
+	assert_eq!(slot_vrf_threshold(8, 5), u128::MAX);
+	assert_eq!(slot_vrf_threshold(1, 4), u128::MAX / 4);
+	assert_eq!(slot_vrf_threshold(2, 4), (u128::MAX / 4) * 2);
+}
+
+#[test]
+fn valid_vrf_proof_makes_author_eligible() {
+	new_test_ext().execute_with(|| {
+		// Every potential author is eligible
+		ExpectedEligible::<Test>::put(5);
+		let seed = VrfSlotFilter::seed();
+
+		initialize_block(3, Some(slot_pre_digest(3, seed, 7)));
+		assert!(VrfSlotFilter::can_author(&3, &7));
+	});
+}
+
+#[test]
+fn vrf_proof_of_another_slot_is_rejected() {
+	new_test_ext().execute_with(|| {
+		ExpectedEligible::<Test>::put(5);
+		let seed = VrfSlotFilter::seed();
+
+		initialize_block(3, Some(slot_pre_digest(3, seed, 7)));
+		assert!(!VrfSlotFilter::can_author(&3, &8));
+	});
+}
+
+#[test]
+fn vrf_proof_of_another_author_is_rejected() {
+	new_test_ext().execute_with(|| {
+		ExpectedEligible::<Test>::put(5);
+		let seed = VrfSlotFilter::seed();
+
+		// Author 3 claims the block with the proof of author 2
+		initialize_block(3, Some(slot_pre_digest(2, seed, 7)));
+		assert!(!VrfSlotFilter::can_author(&3, &7));
+	});
+}
+
+#[test]
+fn missing_vrf_pre_digest_is_rejected() {
+	new_test_ext().execute_with(|| {
+		ExpectedEligible::<Test>::put(5);
+
+		initialize_block(3, None);
+		assert!(!VrfSlotFilter::can_author(&3, &7));
+	});
+}
+
+#[test]
+fn author_claimed_by_digest_must_be_the_checked_author() {
+	new_test_ext().execute_with(|| {
+		ExpectedEligible::<Test>::put(5);
+		let seed = VrfSlotFilter::seed();
+
+		initialize_block(3, Some(slot_pre_digest(3, seed, 7)));
+		assert!(!VrfSlotFilter::can_author(&2, &7));
+	});
+}
+
+#[test]
+fn non_potential_author_is_rejected() {
+	new_test_ext().execute_with(|| {
+		ExpectedEligible::<Test>::put(5);
+		let seed = VrfSlotFilter::seed();
+
+		initialize_block(6, Some(slot_pre_digest(6, seed, 7)));
+		assert!(!VrfSlotFilter::can_author(&6, &7));
+	});
+}
+
+#[test]
+fn eligibility_follows_the_vrf_ticket() {
+	new_test_ext().execute_with(|| {
+		let seed = VrfSlotFilter::seed();
+		let threshold = VrfSlotFilter::threshold();
+
+		let mut eligible_slots = 0;
+		for slot in 0..200 {
+			initialize_block(1, Some(slot_pre_digest(1, seed, slot)));
+			let eligible = VrfSlotFilter::can_author(&1, &slot);
+			assert_eq!(eligible, is_eligible(1, seed, slot, threshold));
+			if eligible {
+				eligible_slots += 1;
+			}
+		}
+
+		// 2 out of 5 authors are expected to be eligible, so about 80 slots out of 200
+		assert!(eligible_slots > 40 && eligible_slots < 120);
+	});
+}
+
+#[test]
+fn vrf_output_becomes_the_next_seed() {
+	new_test_ext().execute_with(|| {
+		ExpectedEligible::<Test>::put(5);
+		let seed = VrfSlotFilter::seed();
+		let pre_digest = slot_pre_digest(3, seed, 7);
+		let vrf_output = verify_slot_vrf(&VrfId::from(author_nimbus_id(3)), seed, 7, &pre_digest)
+			.expect("The proof is valid");
+
+		initialize_block(3, Some(pre_digest));
+		assert!(VrfSlotFilter::can_author(&3, &7));
+		// The seed is updated at the end of the block only
+		assert_eq!(VrfSlotFilter::seed(), seed);
+
+		VrfSlotFilter::on_finalize(1);
+		assert_eq!(VrfSlotFilter::seed(), H256::from(vrf_output));
+	});
+}
+
+#[test]
+fn seed_is_kept_when_no_author_was_eligible() {
+	new_test_ext().execute_with(|| {
+		let seed = H256::repeat_byte(1);
+		Seed::<Test>::put(seed);
+
+		initialize_block(3, None);
+		assert!(!VrfSlotFilter::can_author(&3, &7));
+
+		VrfSlotFilter::on_finalize(1);
+		assert_eq!(VrfSlotFilter::seed(), seed);
+	});
+}
+
+#[test]
+fn can_author_registers_the_weight_of_the_vrf_check() {
+	new_test_ext().execute_with(|| {
+		let mandatory_weight =
+			|| *System::block_weight().get(frame_support::dispatch::DispatchClass::Mandatory);
+		ExpectedEligible::<Test>::put(5);
+		let seed = VrfSlotFilter::seed();
+
+		// Authors that are not potential authors are rejected before the VRF is checked
+		initialize_block(6, Some(slot_pre_digest(6, seed, 7)));
+		assert!(!VrfSlotFilter::can_author(&6, &7));
+		assert_eq!(mandatory_weight(), Weight::zero());
+
+		initialize_block(3, Some(slot_pre_digest(3, seed, 7)));
+		assert!(VrfSlotFilter::can_author(&3, &7));
+		assert_eq!(mandatory_weight(), <() as WeightInfo>::check_slot_vrf());
+	});
+}
@@ -0,0 +1,88 @@
+// Copyright Moonsong Labs
+// This file is part of Moonkit.
+
+// Moonkit is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Moonkit is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Weights for pallet_vrf_slot_filter
+//!
+//! These weights have not been measured by the benchmark CLI yet. They follow the storage accesses
+//! of the benchmarks in `benchmarks.rs`, and their execution times are borrowed from measured
+//! weights of similar shape, as noted on each entry. They must be replaced by the output of the
+//! benchmark CLI, run against a runtime that includes the pallet:
+//!
+//! ./target/release/<node> benchmark pallet --execution=wasm --wasm-execution=compiled
+//! --pallet pallet_vrf_slot_filter --extrinsic '*' --steps 50 --repeat 20
+//! --template=./benchmarking/frame-weight-template.hbs --output weights.rs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_vrf_slot_filter.
+pub trait WeightInfo {
+	fn set_expected_eligible() -> Weight;
+	fn check_slot_vrf() -> Weight;
+	fn on_finalize() -> Weight;
+}
+
+/// Weights for pallet_vrf_slot_filter using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Not measured: the execution time of `set_eligible` in pallet_author_slot_filter, which
+	/// writes a single value as well.
+	fn set_expected_eligible() -> Weight {
+		Weight::from_parts(12_823_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Not measured: the execution time and proof size of `on_initialize` in pallet_randomness,
+	/// which verifies a VRF proof against the keys of pallet_author_mapping as well.
+	fn check_slot_vrf() -> Weight {
+		Weight::from_parts(1_191_969_000, 14980)
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Not measured: the execution time of `set_eligible` in pallet_author_slot_filter.
+	fn on_finalize() -> Weight {
+		Weight::from_parts(12_823_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Not measured: the execution time of `set_eligible` in pallet_author_slot_filter, which
+	/// writes a single value as well.
+	fn set_expected_eligible() -> Weight {
+		Weight::from_parts(12_823_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Not measured: the execution time and proof size of `on_initialize` in pallet_randomness,
+	/// which verifies a VRF proof against the keys of pallet_author_mapping as well.
+	fn check_slot_vrf() -> Weight {
+		Weight::from_parts(1_191_969_000, 14980)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Not measured: the execution time of `set_eligible` in pallet_author_slot_filter.
+	fn on_finalize() -> Weight {
+		Weight::from_parts(12_823_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+}
@@ -21,11 +21,11 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use parity_scale_codec::Codec;
 use sp_application_crypto::KeyTypeId;
 use sp_runtime::generic::DigestItem;
 use sp_runtime::traits::BlockNumberProvider;
 use sp_runtime::ConsensusEngineId;
-#[cfg(feature = "runtime-benchmarks")]
 use sp_std::vec::Vec;
 
 pub mod digests;
@@ -65,8 +65,12 @@ pub trait EventHandler<Author> {
 	fn note_author(author: Author);
 }
 
-impl<T> EventHandler<T> for () {
-	fn note_author(_author: T) {}
+#[impl_trait_for_tuples::impl_for_tuples(5)]
+impl<Author: Clone> EventHandler<Author> for Tuple {
+	#[allow(unused_variables)]
+	fn note_author(author: Author) {
+		for_tuples!( #( Tuple::note_author(author.clone()); )* );
+	}
 }
 
 /// A mechanism for determining the current slot.
@@ -98,7 +102,7 @@ impl<T: BlockNumberProvider<BlockNumber = u32>> SlotBeacon for T {
 /// That is to say the caller specifies an author an author and the implementation
 /// replies whether that author is eligible. This is useful in many cases and is
 /// particularly useful when the active set is unbounded.
-/// There may be another variant where the caller only supplies a slot and the
+/// See `EligibleAuthors` for the variant where the caller only supplies a slot and the
 /// implementation replies with a complete set of eligible authors.
 pub trait CanAuthor<AuthorId> {
 	#[cfg(feature = "try-runtime")]
@@ -124,6 +128,29 @@ impl<T> CanAuthor<T> for () {
 	}
 }
 
+/// Trait to list all the authors that are eligible in a slot.
+///
+/// This is the set-logic variant of `CanAuthor`, and is mostly useful to predict
+/// which authors will be eligible in upcoming slots.
+pub trait EligibleAuthors<AuthorId> {
+	fn eligible_authors(slot: &u32) -> Vec<AuthorId>;
+}
+
+/// Trait for filters in which backup authors can author a slot once some time has passed
+/// without a block from the eligible author.
+pub trait BackupAuthors<AuthorId> {
+	/// The time, in milliseconds since the unix epoch, from which this author is eligible as a
+	/// backup author in this slot, or `None` if it is not a backup author of this slot.
+	fn backup_eligible_from(author: &AuthorId, slot: &u32) -> Option<u64>;
+}
+
+/// Default implementation without backup authors.
+impl<T> BackupAuthors<T> for () {
+	fn backup_eligible_from(_: &T, _: &u32) -> Option<u64> {
+		None
+	}
+}
+
 /// A Trait to lookup runtime AccountIds from AuthorIds (probably NimbusIds)
 /// The trait is generic over the AccountId, becuase different runtimes use
 /// different notions of AccoutId. It is also generic over the AuthorId to
@@ -169,5 +196,27 @@ sp_api::decl_runtime_apis! {
 	/// The runtime api used to predict whether a Nimbus author will be eligible in the given slot
 	pub trait NimbusApi {
 		fn can_author(author: NimbusId, relay_parent: u32, parent_header: &Block::Header) -> bool;
+		/// The time, in milliseconds since the unix epoch, from which the author will be eligible
+		/// as a backup author in the given slot, or `None` if it is not a backup author of this slot.
+		#[api_version(2)]
+		fn backup_eligible_from(
+			author: NimbusId,
+			relay_parent: u32,
+			parent_header: &Block::Header,
+		) -> Option<u64>;
+	}
+
+	/// The runtime api used to predict which authors will be eligible in upcoming slots.
+	/// The prediction is made from the state following the given parent header, so filters
+	/// whose entropy changes at each block can only give an estimate beyond the next block.
+	pub trait EligibleAuthorsApi<AccountId> where AccountId: Codec {
+		/// The authors that are eligible in the given slot
+		fn eligible_authors(slot: u32, parent_header: &Block::Header) -> Vec<AccountId>;
+		/// The authors that are eligible in each of the `count` slots starting at `first_slot`
+		fn eligible_authors_in_range(
+			first_slot: u32,
+			count: u32,
+			parent_header: &Block::Header,
+		) -> Vec<(u32, Vec<AccountId>)>;
 	}
 }
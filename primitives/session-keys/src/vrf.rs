@@ -15,8 +15,10 @@
 // along with Moonkit.  If not, see <http://www.gnu.org/licenses/>.
 
 //! VRF Key type
+use crate::digest::PreDigest;
 use nimbus_primitives::NimbusId;
 use sp_application_crypto::{sr25519, KeyTypeId, UncheckedFrom};
+use sp_core::crypto::ByteArray;
 use sp_core::sr25519::vrf::{VrfInput, VrfSignData};
 //#[cfg(feature = "std")] <- TODO: Check if this is still needed
 use sp_runtime::{BoundToRuntimeAppPublic, ConsensusEngineId};
@@ -33,6 +35,79 @@ pub fn make_vrf_sign_data<Hash: AsRef<[u8]>>(last_vrf_output: Hash) -> VrfSignDa
 	make_vrf_transcript(last_vrf_output).into()
 }
 
+/// Make the VRF transcript used to elect the authors of a slot
+pub fn make_slot_vrf_transcript<Hash: AsRef<[u8]>>(seed: Hash, slot: u32) -> VrfInput {
+	VrfInput::new(
+		&VRF_ENGINE_ID,
+		&[
+			(b"slot vrf seed", seed.as_ref()),
+			(b"slot", &slot.to_le_bytes()),
+		],
+	)
+}
+
+pub fn make_slot_vrf_sign_data<Hash: AsRef<[u8]>>(seed: Hash, slot: u32) -> VrfSignData {
+	make_slot_vrf_transcript(seed, slot).into()
+}
+
+/// Verifies the slot VRF output and proof of the given pre-digest against the VrfId of the author.
+/// Returns the VRF output bytes if the proof is valid.
+pub fn verify_slot_vrf<Hash: AsRef<[u8]>>(
+	vrf_id: &VrfId,
+	seed: Hash,
+	slot: u32,
+	pre_digest: &PreDigest,
+) -> Option<[u8; 32]> {
+	let public_key = schnorrkel::PublicKey::from_bytes(vrf_id.as_slice()).ok()?;
+	let transcript = make_slot_vrf_transcript(seed, slot);
+	let (inout, _) = public_key
+		.vrf_verify(
+			transcript.0.clone(),
+			&pre_digest.vrf_output.0,
+			&pre_digest.vrf_proof.0,
+		)
+		.ok()?;
+	Some(inout.make_bytes(&VRF_INOUT_CONTEXT))
+}
+
+/// The ticket drawn by a slot VRF output. The author is eligible if it is below the threshold.
+pub fn slot_vrf_ticket(vrf_output: &[u8; 32]) -> u128 {
+	let mut bytes = [0u8; 16];
+	bytes.copy_from_slice(&vrf_output[..16]);
+	u128::from_le_bytes(bytes)
+}
+
+/// Signs the slot VRF with the given key from the keystore, and returns the pre-digest to include
+/// in the block if its ticket is below the threshold.
+#[cfg(feature = "std")]
+pub fn claim_slot_vrf<Hash: AsRef<[u8]>>(
+	keystore: &dyn sp_keystore::Keystore,
+	vrf_id: &VrfId,
+	seed: Hash,
+	slot: u32,
+	threshold: u128,
+) -> Option<PreDigest> {
+	let public: sr25519::Public = vrf_id.clone().into();
+	let signature = keystore
+		.sr25519_vrf_sign(
+			VRF_KEY_ID,
+			&public,
+			&make_slot_vrf_sign_data(seed.as_ref(), slot),
+		)
+		.ok()??;
+	let pre_digest = PreDigest {
+		vrf_output: signature.output,
+		vrf_proof: signature.proof,
+	};
+
+	let vrf_output = verify_slot_vrf(vrf_id, seed.as_ref(), slot, &pre_digest)?;
+	if slot_vrf_ticket(&vrf_output) < threshold {
+		Some(pre_digest)
+	} else {
+		None
+	}
+}
+
 /// Struct to implement `BoundToRuntimeAppPublic` by assigning Public = VrfId
 pub struct VrfSessionKey;
 
@@ -79,6 +154,16 @@ sp_api::decl_runtime_apis! {
 		fn get_last_vrf_output() -> Option<Block::Hash>;
 		fn vrf_key_lookup(nimbus_id: nimbus_primitives::NimbusId) -> Option<crate::VrfId>;
 	}
+
+	/// The runtime api used by authors to learn whether their slot VRF makes them eligible
+	pub trait SlotVrfApi {
+		/// The seed of the slot VRF for the block built on top of this one
+		fn slot_vrf_seed() -> Block::Hash;
+		/// The threshold under which the ticket of an author makes it eligible
+		fn slot_vrf_threshold() -> u128;
+		/// The VRF key with which the given author signs its slot VRF
+		fn slot_vrf_key(nimbus_id: NimbusId) -> Option<crate::VrfId>;
+	}
 }
 
 #[test]
@@ -185,9 +185,18 @@ fn testnet_genesis(
 			..Default::default()
 		},
 		author_filter: moonkit_template_runtime::AuthorFilterConfig {
-			eligible_count: moonkit_template_runtime::EligibilityValue::default(),
+			eligibility_mode: moonkit_template_runtime::EligibilityMode::default(),
 			..Default::default()
 		},
+		author_mapping: moonkit_template_runtime::AuthorMappingConfig {
+			mappings: vec![],
+			// The initial collators are registered without a deposit
+			mappings_with_keys: authorities
+				.iter()
+				.cloned()
+				.map(|(account, nimbus_id)| (nimbus_id.clone(), account, nimbus_id, false))
+				.collect(),
+		},
 		potential_author_set: moonkit_template_runtime::PotentialAuthorSetConfig {
 			mapping: authorities,
 		},
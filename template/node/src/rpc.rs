@@ -5,6 +5,8 @@
 
 #![warn(missing_docs)]
 
+pub mod eligible_authors;
+
 use std::sync::Arc;
 
 use moonkit_template_runtime::{opaque::Block, AccountId, Balance, Nonce};
@@ -43,9 +45,11 @@ where
 		+ 'static,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Nonce>,
+	C::Api: nimbus_primitives::EligibleAuthorsApi<Block, AccountId>,
 	C::Api: BlockBuilder<Block>,
 	P: TransactionPool + Sync + Send + 'static,
 {
+	use eligible_authors::{EligibleAuthors, EligibleAuthorsApiServer};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 
@@ -57,7 +61,8 @@ where
 	} = deps;
 
 	module.merge(System::new(client.clone(), pool, deny_unsafe).into_rpc())?;
-	module.merge(TransactionPayment::new(client).into_rpc())?;
+	module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
+	module.merge(EligibleAuthors::new(client).into_rpc())?;
 
 	Ok(module)
 }
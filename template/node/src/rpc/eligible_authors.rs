@@ -0,0 +1,138 @@
+//! RPC methods to predict which authors will be eligible in upcoming slots.
+//! This is useful for collator operators who want to know when their key will be eligible.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use nimbus_primitives::EligibleAuthorsApi as EligibleAuthorsRuntimeApi;
+use parity_scale_codec::Codec;
+use serde::{de::DeserializeOwned, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+
+/// The maximum number of slots that can be predicted in a single request
+pub const MAX_SLOTS_PER_REQUEST: u32 = 256;
+
+/// Error code returned when the runtime api call fails
+const RUNTIME_ERROR: i32 = 1;
+/// Error code returned when the request is invalid
+const INVALID_REQUEST: i32 = 2;
+
+/// Eligible authors RPC methods.
+#[rpc(server)]
+pub trait EligibleAuthorsApi<BlockHash, AccountId> {
+	/// Returns the authors that are eligible in the given slot, as predicted from the state at
+	/// the given block (the best block by default).
+	#[method(name = "nimbus_eligibleAuthors")]
+	fn eligible_authors(&self, slot: u32, at: Option<BlockHash>) -> RpcResult<Vec<AccountId>>;
+
+	/// Returns the authors that are eligible in each of the `count` slots starting at
+	/// `first_slot`, as predicted from the state at the given block (the best block by default).
+	#[method(name = "nimbus_eligibleAuthorsInRange")]
+	fn eligible_authors_in_range(
+		&self,
+		first_slot: u32,
+		count: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<(u32, Vec<AccountId>)>>;
+}
+
+/// Provides the eligible authors RPC methods by querying the runtime.
+pub struct EligibleAuthors<C, Block> {
+	client: Arc<C>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> EligibleAuthors<C, Block> {
+	/// Creates a new instance of the eligible authors RPC.
+	pub fn new(client: Arc<C>) -> Self {
+		Self {
+			client,
+			_marker: Default::default(),
+		}
+	}
+}
+
+impl<C, Block> EligibleAuthors<C, Block>
+where
+	Block: BlockT,
+	C: HeaderBackend<Block>,
+{
+	/// Returns the hash and the header of the block from which the prediction is made
+	fn parent(&self, at: Option<Block::Hash>) -> RpcResult<(Block::Hash, Block::Header)> {
+		let hash = at.unwrap_or_else(|| self.client.info().best_hash);
+		let header = self
+			.client
+			.header(hash)
+			.map_err(|e| runtime_error("Unable to fetch the block header", e))?
+			.ok_or_else(|| {
+				CallError::Custom(ErrorObject::owned(
+					INVALID_REQUEST,
+					"Unknown block",
+					Some(format!("{:?}", hash)),
+				))
+			})?;
+		Ok((hash, header))
+	}
+}
+
+impl<C, Block, AccountId> EligibleAuthorsApiServer<<Block as BlockT>::Hash, AccountId>
+	for EligibleAuthors<C, Block>
+where
+	Block: BlockT,
+	AccountId: Codec + Serialize + DeserializeOwned + Send + Sync + 'static,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: EligibleAuthorsRuntimeApi<Block, AccountId>,
+{
+	fn eligible_authors(
+		&self,
+		slot: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<AccountId>> {
+		let (hash, header) = self.parent(at)?;
+
+		self.client
+			.runtime_api()
+			.eligible_authors(hash, slot, &header)
+			.map_err(|e| runtime_error("Unable to predict the eligible authors", e).into())
+	}
+
+	fn eligible_authors_in_range(
+		&self,
+		first_slot: u32,
+		count: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<(u32, Vec<AccountId>)>> {
+		if count > MAX_SLOTS_PER_REQUEST {
+			return Err(CallError::Custom(ErrorObject::owned(
+				INVALID_REQUEST,
+				"Too many slots requested",
+				Some(format!(
+					"at most {} slots per request",
+					MAX_SLOTS_PER_REQUEST
+				)),
+			))
+			.into());
+		}
+
+		let (hash, header) = self.parent(at)?;
+
+		self.client
+			.runtime_api()
+			.eligible_authors_in_range(hash, first_slot, count, &header)
+			.map_err(|e| runtime_error("Unable to predict the eligible authors", e).into())
+	}
+}
+
+fn runtime_error(message: &str, error: impl std::fmt::Debug) -> CallError {
+	CallError::Custom(ErrorObject::owned(
+		RUNTIME_ERROR,
+		message,
+		Some(format!("{:?}", error)),
+	))
+}
@@ -17,7 +17,7 @@ use sp_runtime::{
 };
 
 pub use nimbus_primitives::NimbusId;
-pub use pallet_author_slot_filter::EligibilityValue;
+pub use pallet_author_slot_filter::{EligibilityMode, EligibilityValue};
 
 use sp_std::prelude::*;
 #[cfg(feature = "std")]
@@ -42,7 +42,7 @@ use frame_system::{
 	EnsureRoot,
 };
 
-pub use sp_runtime::{MultiAddress, Perbill, Permill};
+pub use sp_runtime::{MultiAddress, Perbill, Percent, Permill};
 
 #[cfg(any(feature = "std", test))]
 pub use sp_runtime::BuildStorage;
@@ -136,6 +136,9 @@ pub type Executive = frame_executive::Executive<
 pub struct OnRuntimeUpgrade;
 impl frame_support::traits::OnRuntimeUpgrade for OnRuntimeUpgrade {
 	fn on_runtime_upgrade() -> Weight {
+		// The author mapping is included from genesis, so `pallet_author_mapping::migrations` are
+		// not needed. A chain that included it before storage version 3 runs them here, in order,
+		// up to `InitializeLastAuthored`.
 		frame_support::migrations::migrate_from_pallet_version_to_storage_version::<
 			AllPalletsWithSystem,
 		>(&RocksDbWeight::get())
@@ -371,10 +374,10 @@ impl pallet_balances::Config for Runtime {
 	type WeightInfo = pallet_balances::weights::SubstrateWeight<Runtime>;
 	type MaxReserves = MaxReserves;
 	type ReserveIdentifier = [u8; 8];
-	type RuntimeHoldReason = ();
+	type RuntimeHoldReason = RuntimeHoldReason;
 	type RuntimeFreezeReason = ();
 	type FreezeIdentifier = ();
-	type MaxHolds = ConstU32<0>;
+	type MaxHolds = ConstU32<1>;
 	type MaxFreezes = ConstU32<0>;
 }
 
@@ -609,6 +612,7 @@ impl pallet_author_inherent::Config for Runtime {
 	type SlotBeacon = cumulus_pallet_parachain_system::RelaychainDataProvider<Self>;
 	type AccountLookup = PotentialAuthorSet;
 	type CanAuthor = AuthorFilter;
+	type EventHandler = (AuthorFilter, AuthorMapping);
 	type WeightInfo = ();
 }
 
@@ -616,6 +620,14 @@ impl pallet_author_slot_filter::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type RandomnessSource = RandomnessCollectiveFlip;
 	type PotentialAuthors = PotentialAuthorSet;
+	type AuthorWeight = ();
+	type FairnessWindow = ConstU32<100>;
+	type SlotBeacon = cumulus_pallet_parachain_system::RelaychainDataProvider<Self>;
+	// Relay block numbers are used as slots, and a block is normally produced every other relay
+	// block, so that one unfilled slot is expected between two blocks.
+	type UnfilledSlotsTolerance = ConstU32<2>;
+	type EligibilityWideningStep = ConstU32<5>;
+	type MaxEligibleAuthors = ConstU32<100>;
 	type WeightInfo = ();
 }
 
@@ -631,6 +643,29 @@ parameter_types! {
 
 impl pallet_account_set::Config for Runtime {}
 
+parameter_types! {
+	pub const AuthorMappingDeposit: Balance = 100 * UNIT;
+	pub const AuthorMappingInactivityReward: Percent = Percent::from_percent(10);
+}
+
+impl pallet_author_mapping::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeHoldReason = RuntimeHoldReason;
+	type DepositCurrency = Balances;
+	type DepositAmount = AuthorMappingDeposit;
+	// The template has no other session keys, the keys are the NimbusId itself.
+	type Keys = NimbusId;
+	type KeysOwnership = ();
+	type MaxKeysPerAccount = ConstU32<1>;
+	type CurrentSession = ();
+	type MaxScheduledRotations = ConstU32<8>;
+	type ForceOrigin = EnsureRoot<AccountId>;
+	type Slashed = ();
+	type InactivityPeriod = ConstU32<{ 30 * DAYS }>;
+	type InactivityReward = AuthorMappingInactivityReward;
+	type WeightInfo = pallet_author_mapping::weights::SubstrateWeight<Runtime>;
+}
+
 /// Configure the pallet template in pallets/template.
 impl pallet_template::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
@@ -658,6 +693,9 @@ construct_runtime!(
 		AuthorFilter: pallet_author_slot_filter::{Pallet, Storage, Event, Config<T>} = 21,
 		PotentialAuthorSet: pallet_account_set::{Pallet, Storage, Config<T>} = 22,
 		NimbusAsyncBacking: pallet_async_backing::{Pallet, Storage} = 23,
+		AuthorMapping: pallet_author_mapping::{
+			Pallet, Call, Storage, Config<T>, Event<T>, HoldReason,
+		} = 24,
 
 		// XCM helpers.
 		XcmpQueue: cumulus_pallet_xcmp_queue::{Pallet, Call, Storage, Event<T>} = 30,
@@ -670,6 +708,22 @@ construct_runtime!(
 	}
 );
 
+/// Prepares the state to predict the authors of the block built on top of `parent_header`.
+fn initialize_author_prediction(parent_header: &<Block as BlockT>::Header) {
+	// This runtime uses an entropy source that is updated during block initialization
+	// Therefore we need to initialize it to match the state it will be in when the
+	// next block is being executed.
+	System::reset_events();
+	System::initialize(
+		&(parent_header.number + 1),
+		&parent_header.hash(),
+		&parent_header.digest,
+	);
+	<Runtime as pallet_author_slot_filter::Config>::RandomnessSource::on_initialize(
+		System::block_number(),
+	);
+}
+
 impl_runtime_apis! {
 	impl sp_api::Core<Block> for Runtime {
 		fn version() -> RuntimeVersion {
@@ -781,18 +835,48 @@ impl_runtime_apis! {
 		}
 	}
 
+	#[api_version(2)]
 	impl nimbus_primitives::NimbusApi<Block> for Runtime {
 		fn can_author(author: NimbusId, slot: u32, parent_header: &<Block as BlockT>::Header) -> bool {
-			// This runtime uses an entropy source that is updated during block initialization
-			// Therefore we need to initialize it to match the state it will be in when the
-			// next block is being executed.
-			System::reset_events();
-			System::initialize(&(parent_header.number + 1), &parent_header.hash(), &parent_header.digest);
-			<Self as pallet_author_slot_filter::Config>::RandomnessSource::on_initialize(System::block_number());
+			initialize_author_prediction(parent_header);
 
 			// And now the actual prediction call
 			<AuthorInherent as nimbus_primitives::CanAuthor<_>>::can_author(&author, &slot)
 		}
+
+		fn backup_eligible_from(
+			author: NimbusId,
+			slot: u32,
+			parent_header: &<Block as BlockT>::Header,
+		) -> Option<u64> {
+			initialize_author_prediction(parent_header);
+
+			// The author slot filter has no backup authors. A runtime using the aura style filter
+			// passes `AuraStyleFilter` instead of `()`.
+			AuthorInherent::backup_eligible_from::<()>(&author, slot)
+		}
+	}
+
+	impl nimbus_primitives::EligibleAuthorsApi<Block, AccountId> for Runtime {
+		fn eligible_authors(slot: u32, parent_header: &<Block as BlockT>::Header) -> Vec<AccountId> {
+			initialize_author_prediction(parent_header);
+
+			<AuthorFilter as nimbus_primitives::EligibleAuthors<_>>::eligible_authors(&slot)
+		}
+
+		fn eligible_authors_in_range(
+			first_slot: u32,
+			count: u32,
+			parent_header: &<Block as BlockT>::Header,
+		) -> Vec<(u32, Vec<AccountId>)> {
+			initialize_author_prediction(parent_header);
+
+			(first_slot..first_slot.saturating_add(count))
+				.map(|slot| {
+					(slot, <AuthorFilter as nimbus_primitives::EligibleAuthors<_>>::eligible_authors(&slot))
+				})
+				.collect()
+		}
 	}
 
 	impl async_backing_primitives::UnincludedSegmentApi<Block> for Runtime {
@@ -817,6 +901,8 @@ impl_runtime_apis! {
 
 			list_benchmark!(list, extra, pallet_balances, Balances);
 			list_benchmark!(list, extra, pallet_timestamp, Timestamp);
+			list_benchmark!(list, extra, pallet_author_slot_filter, AuthorFilter);
+			list_benchmark!(list, extra, pallet_author_mapping, AuthorMapping);
 
 			let storage_info = AllPalletsWithSystem::storage_info();
 
@@ -847,6 +933,8 @@ impl_runtime_apis! {
 
 			add_benchmark!(params, batches, pallet_balances, Balances);
 			add_benchmark!(params, batches, pallet_timestamp, Timestamp);
+			add_benchmark!(params, batches, pallet_author_slot_filter, AuthorFilter);
+			add_benchmark!(params, batches, pallet_author_mapping, AuthorMapping);
 
 			if batches.is_empty() { return Err("Benchmark not found for this pallet.".into()) }
 			Ok(batches)